@@ -0,0 +1,231 @@
+use std::cmp::Ordering;
+
+use crate::model::{ADSBAircraftInformation, FRadarArgs, FRadarUi, FlightData, ListColumn, ListMode, Position};
+
+
+impl ListColumn {
+  pub const ALL: [ListColumn; 10] = [
+    ListColumn::Callsign,
+    ListColumn::Registration,
+    ListColumn::Type,
+    ListColumn::Altitude,
+    ListColumn::Speed,
+    ListColumn::VerticalRate,
+    ListColumn::Distance,
+    ListColumn::Bearing,
+    ListColumn::Squawk,
+    ListColumn::Seen,
+  ];
+
+  pub fn header(&self) -> &'static str {
+    match self {
+      ListColumn::Callsign => "CALLSIGN",
+      ListColumn::Registration => "REG",
+      ListColumn::Type => "TYPE",
+      ListColumn::Altitude => "ALT",
+      ListColumn::Speed => "GS",
+      ListColumn::VerticalRate => "V/S",
+      ListColumn::Distance => "DIST",
+      ListColumn::Bearing => "BRG",
+      ListColumn::Squawk => "SQWK",
+      ListColumn::Seen => "SEEN",
+    }
+  }
+
+  pub fn width(&self) -> usize {
+    match self {
+      ListColumn::Callsign => 8,
+      ListColumn::Registration => 8,
+      ListColumn::Type => 4,
+      ListColumn::Altitude => 6,
+      ListColumn::Speed => 4,
+      ListColumn::VerticalRate => 6,
+      ListColumn::Distance => 6,
+      ListColumn::Bearing => 4,
+      ListColumn::Squawk => 4,
+      ListColumn::Seen => 5,
+    }
+  }
+
+  pub fn next(&self) -> ListColumn {
+    let index = Self::ALL.iter().position(|column| column == self).unwrap_or(0);
+    Self::ALL[(index + 1) % Self::ALL.len()]
+  }
+
+  fn is_numeric(&self) -> bool {
+    !matches!(self, ListColumn::Callsign | ListColumn::Registration | ListColumn::Type | ListColumn::Squawk)
+  }
+}
+
+impl ListMode {
+  pub fn next(&self) -> ListMode {
+    match self {
+      ListMode::Hidden => ListMode::Split,
+      ListMode::Split => ListMode::Full,
+      ListMode::Full => ListMode::Hidden,
+    }
+  }
+}
+
+/// One line of the aircraft list, with both display text and sort keys per column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListRow {
+  pub hex: String,
+  pub text: Vec<String>,
+  pub keys: Vec<SortKey>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortKey {
+  Text(String),
+  Number(f64),
+  Missing,
+}
+
+impl SortKey {
+  fn compare(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+      (SortKey::Text(a), SortKey::Text(b)) => a.cmp(b),
+      (SortKey::Missing, SortKey::Missing) => Ordering::Equal,
+      (SortKey::Missing, _) => Ordering::Greater,
+      (_, SortKey::Missing) => Ordering::Less,
+      (_, _) => Ordering::Equal,
+    }
+  }
+}
+
+pub fn altitude_feet(info: &ADSBAircraftInformation) -> Option<f64> {
+  match info.alt_baro.as_deref() {
+    Some("ground") => Some(0.0),
+    Some(alt) => alt.parse::<f64>().ok(),
+    None => info.alt_geom.map(|alt| alt as f64),
+  }
+}
+
+pub fn seen_seconds(info: &ADSBAircraftInformation, flights_data: &FlightData, now_millis: i64) -> Option<f64> {
+  info.seen.map(|seen| seen as f64 + (now_millis - flights_data.epoch_timestamp) as f64 / 1000.0)
+}
+
+fn build_row(info: &ADSBAircraftInformation, position: &Position, flights_data: &FlightData, args: &FRadarArgs, now_millis: i64) -> ListRow {
+  let text_key = |value: Option<&String>| -> (String, SortKey) {
+    match value.map(|v| v.trim()).filter(|v| !v.is_empty()) {
+      Some(v) => (v.to_string(), SortKey::Text(v.to_string())),
+      None => ("-".to_string(), SortKey::Missing),
+    }
+  };
+  let number_key = |value: Option<f64>, format: &dyn Fn(f64) -> String| -> (String, SortKey) {
+    match value {
+      Some(v) => (format(v), SortKey::Number(v)),
+      None => ("-".to_string(), SortKey::Missing),
+    }
+  };
+
+  let distance: f64 = args.origin.distance_miles(position);
+  let bearing: f64 = args.origin.bearing_to(position);
+
+  let cells: Vec<(String, SortKey)> = ListColumn::ALL.iter()
+    .map(|column| match column {
+      ListColumn::Callsign => text_key(info.flight.as_ref().filter(|flight| !flight.trim().is_empty()).or(Some(&info.hex))),
+      ListColumn::Registration => text_key(info.r.as_ref()),
+      ListColumn::Type => text_key(info.t.as_ref()),
+      ListColumn::Altitude => match info.alt_baro.as_deref() {
+        Some("ground") => ("ground".to_string(), SortKey::Number(0.0)),
+        _ => number_key(altitude_feet(info).map(|alt| args.units.altitude(alt)), &|v| format!("{:.0}", v)),
+      },
+      ListColumn::Speed => number_key(info.gs.map(|gs| args.units.speed(gs as f64)), &|v| format!("{:.0}", v)),
+      ListColumn::VerticalRate => number_key(info.geom_rate.map(|rate| rate as f64), &|v| format!("{:+.0}", v)),
+      ListColumn::Distance => number_key(Some(args.units.distance(distance)), &|v| format!("{:.1}", v)),
+      ListColumn::Bearing => number_key(Some(bearing), &|v| format!("{:03.0}", v)),
+      ListColumn::Squawk => text_key(info.squawk.as_ref()),
+      ListColumn::Seen => number_key(seen_seconds(info, flights_data, now_millis), &|v| format!("{:.0}s", v)),
+    })
+    .collect();
+
+  let (text, keys): (Vec<String>, Vec<SortKey>) = cells.into_iter().unzip();
+  ListRow { hex: info.hex.clone(), text, keys }
+}
+
+/// All tracked aircraft as list rows, ordered by the column and direction chosen in `ui`.
+pub fn sorted_rows(flights_data: &FlightData, args: &FRadarArgs, ui: &FRadarUi, now_millis: i64) -> Vec<ListRow> {
+  let mut rows: Vec<ListRow> = flights_data.aircraft.iter()
+    .zip(flights_data.flights.iter())
+    .map(|(info, (position, _))| build_row(info, position, flights_data, args, now_millis))
+    .collect();
+
+  let column: usize = ListColumn::ALL.iter().position(|column| *column == ui.list_sort).unwrap_or(0);
+  rows.sort_by(|a, b| {
+    let ordering = a.keys[column].compare(&b.keys[column]);
+    let ordering = if ui.list_descending { ordering.reverse() } else { ordering };
+
+    // Missing values always sink to the bottom regardless of direction.
+    match (&a.keys[column], &b.keys[column]) {
+      (SortKey::Missing, SortKey::Missing) => Ordering::Equal,
+      (SortKey::Missing, _) => Ordering::Greater,
+      (_, SortKey::Missing) => Ordering::Less,
+      _ => ordering,
+    }
+  });
+
+  rows
+}
+
+pub fn format_header(ui: &FRadarUi) -> String {
+  ListColumn::ALL.iter()
+    .map(|column| {
+      let marker: &str = match (*column == ui.list_sort, ui.list_descending) {
+        (true, false) => "▲",
+        (true, true) => "▼",
+        (false, _) => "",
+      };
+      pad_cell(&format!("{}{}", column.header(), marker), column)
+    })
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+pub fn format_row(row: &ListRow) -> String {
+  ListColumn::ALL.iter()
+    .zip(row.text.iter())
+    .map(|(column, text)| pad_cell(text, column))
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+fn pad_cell(text: &str, column: &ListColumn) -> String {
+  let text: String = text.chars().take(column.width()).collect();
+  if column.is_numeric() {
+    format!("{:>width$}", text, width = column.width())
+  } else {
+    format!("{:<width$}", text, width = column.width())
+  }
+}
+
+/// Screen rectangle (x, y, w, h) of the list panel, including its border.
+pub fn list_area(args: &FRadarArgs, mode: ListMode) -> Option<(u16, u16, u16, u16)> {
+  match mode {
+    ListMode::Hidden => None,
+    ListMode::Split => {
+      let h: u16 = (args.terminal_rows * 2 / 5).max(5).min(args.terminal_rows);
+      Some((0, args.terminal_rows - h, args.terminal_cols, h))
+    },
+    ListMode::Full => Some((0, 0, args.terminal_cols, args.terminal_rows)),
+  }
+}
+
+/// Number of data rows that fit in the panel below the header line.
+pub fn visible_rows(args: &FRadarArgs, mode: ListMode) -> usize {
+  list_area(args, mode).map(|(_, _, _, h)| h.saturating_sub(3) as usize).unwrap_or(0)
+}
+
+/// Scroll offset adjusted so that the selected row (if any) is on screen.
+pub fn scroll_to_selection(rows: &[ListRow], ui: &FRadarUi, visible: usize) -> usize {
+  let max_scroll: usize = rows.len().saturating_sub(visible);
+  let scroll: usize = ui.list_scroll.min(max_scroll);
+
+  match ui.selected_hex.as_deref().and_then(|hex| rows.iter().position(|row| row.hex == hex)) {
+    Some(index) if index < scroll => index,
+    Some(index) if visible > 0 && index >= scroll + visible => index + 1 - visible,
+    _ => scroll,
+  }
+}
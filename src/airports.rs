@@ -0,0 +1,290 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::model::{Coord, FRadarArgs, Position};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AirportKind {
+  Large,
+  Medium,
+  Small,
+  Heliport,
+  SeaplaneBase,
+  Other,
+}
+
+impl AirportKind {
+  fn from_ourairports(kind: &str) -> Option<AirportKind> {
+    match kind {
+      "large_airport" => Some(AirportKind::Large),
+      "medium_airport" => Some(AirportKind::Medium),
+      "small_airport" => Some(AirportKind::Small),
+      "heliport" => Some(AirportKind::Heliport),
+      "seaplane_base" => Some(AirportKind::SeaplaneBase),
+      "balloonport" => Some(AirportKind::Other),
+      _ => None, // "closed" and anything unknown
+    }
+  }
+
+  pub fn symbol(&self) -> char {
+    match self {
+      AirportKind::Large => '◉',
+      AirportKind::Medium => '◎',
+      AirportKind::Small => '○',
+      AirportKind::Heliport => 'H',
+      AirportKind::SeaplaneBase => '≈',
+      AirportKind::Other => '·',
+    }
+  }
+
+  /// Largest view radius (miles) at which airports of this kind are still drawn.
+  pub fn max_radius(&self) -> f64 {
+    match self {
+      AirportKind::Large => 600.0,
+      AirportKind::Medium => 150.0,
+      AirportKind::Small => 40.0,
+      AirportKind::Heliport | AirportKind::SeaplaneBase | AirportKind::Other => 10.0,
+    }
+  }
+
+  /// Largest view radius (miles) at which airports of this kind get an ICAO label.
+  pub fn max_label_radius(&self) -> f64 {
+    self.max_radius() / 3.0
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Runway {
+  pub le_ident: String,
+  pub he_ident: String,
+  pub le: Position,
+  pub he: Position,
+  pub length_ft: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Airport {
+  pub ident: String,
+  pub kind: AirportKind,
+  pub name: String,
+  pub position: Position,
+  pub elevation_ft: Option<f64>,
+  pub icao: Option<String>,
+  pub iata: Option<String>,
+  pub runways: Vec<Runway>,
+}
+
+impl Airport {
+  /// The code shown on the scope: ICAO where known, otherwise the OurAirports ident.
+  pub fn code(&self) -> &str {
+    self.icao.as_deref().unwrap_or(&self.ident)
+  }
+}
+
+/// Airports loaded from OurAirports CSV exports, sorted by latitude for range queries.
+#[derive(Debug, Default, Clone)]
+pub struct AirportDb {
+  airports: Vec<Airport>,
+  by_code: HashMap<String, usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AirportRecord {
+  ident: String,
+  #[serde(rename = "type")]
+  kind: String,
+  name: String,
+  latitude_deg: f64,
+  longitude_deg: f64,
+  elevation_ft: Option<f64>,
+  gps_code: Option<String>,
+  icao_code: Option<String>,
+  iata_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunwayRecord {
+  airport_ident: String,
+  length_ft: Option<f64>,
+  closed: Option<u8>,
+  le_ident: Option<String>,
+  le_latitude_deg: Option<f64>,
+  le_longitude_deg: Option<f64>,
+  he_ident: Option<String>,
+  he_latitude_deg: Option<f64>,
+  he_longitude_deg: Option<f64>,
+}
+
+impl AirportDb {
+  pub fn load(airports_csv: &Path, runways_csv: Option<&Path>) -> anyhow::Result<AirportDb> {
+    let mut reader = csv::Reader::from_path(airports_csv)
+      .with_context(|| format!("failed to open {}", airports_csv.display()))?;
+
+    let mut airports: Vec<Airport> = Vec::new();
+    for (line, record) in reader.deserialize::<AirportRecord>().enumerate() {
+      let record: AirportRecord = record.with_context(|| format!("{}: bad record on row {}", airports_csv.display(), line + 2))?;
+      let Some(kind) = AirportKind::from_ourairports(&record.kind) else {
+        continue;
+      };
+
+      let non_empty = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+      let icao: Option<String> = non_empty(record.icao_code)
+        .or_else(|| non_empty(record.gps_code).filter(|code| code.len() == 4 && code.chars().all(|c| c.is_ascii_alphanumeric())));
+
+      airports.push(Airport {
+        ident: record.ident,
+        kind,
+        name: record.name,
+        position: Position { lat: record.latitude_deg, long: record.longitude_deg },
+        elevation_ft: record.elevation_ft,
+        icao,
+        iata: non_empty(record.iata_code),
+        runways: Vec::new(),
+      });
+    }
+
+    airports.sort_by(|a, b| a.position.lat.total_cmp(&b.position.lat));
+
+    let mut by_ident: HashMap<String, usize> = HashMap::new();
+    for (index, airport) in airports.iter().enumerate() {
+      by_ident.insert(airport.ident.to_uppercase(), index);
+    }
+
+    if let Some(runways_csv) = runways_csv {
+      let mut reader = csv::Reader::from_path(runways_csv)
+        .with_context(|| format!("failed to open {}", runways_csv.display()))?;
+
+      for (line, record) in reader.deserialize::<RunwayRecord>().enumerate() {
+        let record: RunwayRecord = record.with_context(|| format!("{}: bad record on row {}", runways_csv.display(), line + 2))?;
+        if record.closed == Some(1) {
+          continue;
+        }
+
+        let (Some(le_lat), Some(le_long), Some(he_lat), Some(he_long)) =
+          (record.le_latitude_deg, record.le_longitude_deg, record.he_latitude_deg, record.he_longitude_deg) else {
+          continue;
+        };
+
+        if let Some(&index) = by_ident.get(&record.airport_ident.to_uppercase()) {
+          airports[index].runways.push(Runway {
+            le_ident: record.le_ident.unwrap_or_default(),
+            he_ident: record.he_ident.unwrap_or_default(),
+            le: Position { lat: le_lat, long: le_long },
+            he: Position { lat: he_lat, long: he_long },
+            length_ft: record.length_ft,
+          });
+        }
+      }
+    }
+
+    // Lookups by ICAO, IATA or ident, with the bigger airport winning any clash.
+    let mut by_code: HashMap<String, usize> = HashMap::new();
+    let mut order: Vec<usize> = (0..airports.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(airports[index].kind));
+    for index in order {
+      let airport: &Airport = &airports[index];
+      for code in [Some(&airport.ident), airport.icao.as_ref(), airport.iata.as_ref()].into_iter().flatten() {
+        by_code.insert(code.to_uppercase(), index);
+      }
+    }
+
+    Ok(AirportDb { airports, by_code })
+  }
+
+  pub fn len(&self) -> usize {
+    self.airports.len()
+  }
+
+  pub fn find(&self, code: &str) -> Option<&Airport> {
+    self.by_code.get(&code.to_uppercase()).map(|&index| &self.airports[index])
+  }
+
+  /// Airports on screen that are significant enough for the current zoom, biggest first.
+  pub fn visible(&self, args: &FRadarArgs) -> Vec<&Airport> {
+    let top: Position = Position::from_terminal_coord_float(Coord { col: 0.0, row: 0.0 }, args);
+    let bottom: Position = Position::from_terminal_coord_float(Coord { col: 0.0, row: args.terminal_rows as f64 }, args);
+
+    let start: usize = self.airports.partition_point(|airport| airport.position.lat < bottom.lat);
+    let end: usize = self.airports.partition_point(|airport| airport.position.lat <= top.lat);
+
+    let mut visible: Vec<&Airport> = self.airports[start..end.max(start)].iter()
+      .filter(|airport| args.radius <= airport.kind.max_radius())
+      .filter(|airport| airport.position.is_on_screen(args))
+      .collect();
+    visible.sort_by_key(|airport| airport.kind);
+    visible
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::*;
+
+  const AIRPORTS: &str = "\
+\"id\",\"ident\",\"type\",\"name\",\"latitude_deg\",\"longitude_deg\",\"elevation_ft\",\"gps_code\",\"icao_code\",\"iata_code\"
+1,\"KSEA\",\"large_airport\",\"Seattle-Tacoma International Airport\",47.449,-122.309,433,\"KSEA\",\"KSEA\",\"SEA\"
+2,\"KBFI\",\"medium_airport\",\"Boeing Field\",47.45,-122.25,21,\"KBFI\",\"\",\"BFI\"
+3,\"WA77\",\"small_airport\",\"Sea Strip\",47.449,-122.36,,\"WA77\",\"\",\"SEA\"
+4,\"S43\",\"small_airport\",\"Harvey Field\",47.45,-122.0,22,\"\",\"\",\"\"
+5,\"XXXX\",\"closed\",\"Gone Field\",47.5,-122.3,,\"\",\"\",\"\"
+";
+
+  const RUNWAYS: &str = "\
+\"airport_ident\",\"length_ft\",\"closed\",\"le_ident\",\"le_latitude_deg\",\"le_longitude_deg\",\"he_ident\",\"he_latitude_deg\",\"he_longitude_deg\"
+\"KSEA\",11901,0,\"16L\",47.464,-122.311,\"34R\",47.431,-122.308
+\"ksea\",8500,1,\"16R\",47.463,-122.318,\"34L\",47.440,-122.317
+\"KBFI\",10007,0,\"14R\",,,\"32L\",,
+";
+
+  fn load(name: &str) -> AirportDb {
+    let directory: PathBuf = std::env::temp_dir();
+    let airports: PathBuf = directory.join(format!("fradar-airports-{}-{}.csv", name, std::process::id()));
+    let runways: PathBuf = directory.join(format!("fradar-runways-{}-{}.csv", name, std::process::id()));
+    std::fs::write(&airports, AIRPORTS).unwrap();
+    std::fs::write(&runways, RUNWAYS).unwrap();
+    let db: AirportDb = AirportDb::load(&airports, Some(&runways)).unwrap();
+    let _ = std::fs::remove_file(&airports);
+    let _ = std::fs::remove_file(&runways);
+    db
+  }
+
+  #[test]
+  fn loads_open_airports_and_runways() {
+    let db: AirportDb = load("load");
+    assert_eq!(db.len(), 4);
+    assert!(db.find("XXXX").is_none());
+
+    let ksea: &Airport = db.find("ksea").unwrap();
+    assert_eq!(ksea.kind, AirportKind::Large);
+    assert_eq!(ksea.elevation_ft, Some(433.0));
+    assert_eq!(ksea.runways.len(), 1, "closed runway skipped");
+    assert_eq!((ksea.runways[0].le_ident.as_str(), ksea.runways[0].he_ident.as_str()), ("16L", "34R"));
+    assert!(db.find("KBFI").unwrap().runways.is_empty(), "runway without ends skipped");
+  }
+
+  #[test]
+  fn looks_up_icao_iata_and_ident() {
+    let db: AirportDb = load("codes");
+    assert_eq!(db.find("KBFI").unwrap().code(), "KBFI", "ICAO from the GPS code");
+    assert_eq!(db.find("bfi").unwrap().ident, "KBFI");
+    assert_eq!(db.find("S43").unwrap().code(), "S43");
+    assert_eq!(db.find("SEA").unwrap().ident, "KSEA", "the bigger airport wins a clash");
+  }
+
+  #[test]
+  fn shows_smaller_airports_as_the_view_zooms_in() {
+    let db: AirportDb = load("visible");
+    let args = |radius: f64| FRadarArgs { origin: Position { lat: 47.45, long: -122.3 }, radius, terminal_cols: 120, terminal_rows: 40, ..Default::default() };
+    let idents = |radius: f64| db.visible(&args(radius)).iter().map(|airport| airport.ident.clone()).collect::<Vec<String>>();
+
+    assert_eq!(idents(200.0), ["KSEA"]);
+    assert_eq!(idents(100.0), ["KSEA", "KBFI"]);
+    assert_eq!(idents(30.0), ["KSEA", "KBFI", "WA77", "S43"]);
+    assert_eq!(idents(5.0), ["KSEA", "KBFI", "WA77"], "Harvey Field is off screen");
+  }
+}
@@ -0,0 +1,473 @@
+use std::{fmt::Display, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+use crossterm::style::Color;
+
+use crate::{canvas::{BrailleCanvas, Stroke}, model::{FRadarArgs, Palette, Position, MILES_PER_NM}};
+
+
+/// Arcs and circles are drawn as polylines with a vertex every this many degrees.
+const ARC_STEP_DEGREES: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AirspaceClass {
+  B,
+  C,
+  D,
+  Restricted,
+  Prohibited,
+  Danger,
+  Tfr,
+  Other,
+}
+
+impl AirspaceClass {
+  fn from_openair(class: &str) -> AirspaceClass {
+    match class {
+      "B" => AirspaceClass::B,
+      "C" => AirspaceClass::C,
+      "D" | "CTR" => AirspaceClass::D,
+      "R" => AirspaceClass::Restricted,
+      "P" => AirspaceClass::Prohibited,
+      "Q" => AirspaceClass::Danger,
+      "TFR" | "TRA" => AirspaceClass::Tfr,
+      _ => AirspaceClass::Other,
+    }
+  }
+
+  /// Color and dash pattern, loosely after the sectional chart. `None` for classes that
+  /// cover too much sky (A, E, G and the like) to be useful on the scope.
+  pub fn style(&self, palette: &Palette) -> Option<(Color, Stroke)> {
+    match self {
+      AirspaceClass::B => Some((palette.airspace, Stroke::Solid)),
+      AirspaceClass::C => Some((palette.airspace, Stroke::Dotted)),
+      AirspaceClass::D => Some((palette.airspace, Stroke::Dashed)),
+      AirspaceClass::Prohibited | AirspaceClass::Tfr => Some((palette.restricted, Stroke::Solid)),
+      AirspaceClass::Restricted => Some((palette.restricted, Stroke::Dashed)),
+      AirspaceClass::Danger => Some((palette.restricted, Stroke::Dotted)),
+      AirspaceClass::Other => None,
+    }
+  }
+}
+
+/// An airspace floor or ceiling, as written after `AL` and `AH`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AirspaceLimit {
+  Surface,
+  Msl(f64),
+  /// Height above ground. With no terrain data this is compared as if the ground were at sea level.
+  Agl(f64),
+  FlightLevel(f64),
+  Unlimited,
+}
+
+impl AirspaceLimit {
+  fn parse(text: &str) -> anyhow::Result<AirspaceLimit> {
+    let upper: String = text.trim().to_uppercase();
+    if upper.is_empty() || ["SFC", "GND", "SURFACE"].contains(&upper.as_str()) {
+      return Ok(AirspaceLimit::Surface);
+    }
+    if upper.starts_with("UNL") {
+      return Ok(AirspaceLimit::Unlimited);
+    }
+    if let Some(level) = upper.strip_prefix("FL") {
+      return level.trim().parse::<f64>().map(AirspaceLimit::FlightLevel).map_err(|_| anyhow!("bad flight level '{}'", text));
+    }
+
+    let digits: usize = upper.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(upper.len());
+    let value: f64 = upper[..digits].parse().map_err(|_| anyhow!("bad altitude '{}'", text))?;
+    let rest: &str = upper[digits..].trim();
+    let feet: f64 = match rest.starts_with('M') && !rest.starts_with("MSL") {
+      true => value * 3.28084,
+      false => value,
+    };
+
+    match rest.contains("AGL") || rest.contains("GND") || rest.contains("SFC") {
+      true => Ok(AirspaceLimit::Agl(feet)),
+      false => Ok(AirspaceLimit::Msl(feet)),
+    }
+  }
+
+  fn feet(&self) -> f64 {
+    match self {
+      AirspaceLimit::Surface => 0.0,
+      AirspaceLimit::Msl(feet) | AirspaceLimit::Agl(feet) => *feet,
+      AirspaceLimit::FlightLevel(level) => level * 100.0,
+      AirspaceLimit::Unlimited => f64::INFINITY,
+    }
+  }
+}
+
+impl Display for AirspaceLimit {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AirspaceLimit::Surface => write!(f, "SFC"),
+      AirspaceLimit::Msl(feet) => write!(f, "{:.0}", feet),
+      AirspaceLimit::Agl(feet) => write!(f, "{:.0} AGL", feet),
+      AirspaceLimit::FlightLevel(level) => write!(f, "FL{:.0}", level),
+      AirspaceLimit::Unlimited => write!(f, "UNL"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Airspace {
+  pub class: AirspaceClass,
+  pub name: String,
+  pub floor: AirspaceLimit,
+  pub ceiling: AirspaceLimit,
+  pub boundary: Vec<Position>,
+  min: Position,
+  max: Position,
+}
+
+impl Airspace {
+  pub fn contains_altitude(&self, feet: f64) -> bool {
+    self.floor.feet() <= feet && feet <= self.ceiling.feet()
+  }
+
+  pub fn contains(&self, position: &Position) -> bool {
+    if position.lat < self.min.lat || position.lat > self.max.lat || position.long < self.min.long || position.long > self.max.long {
+      return false;
+    }
+    polygon_contains(&self.boundary, position)
+  }
+
+  fn intersects(&self, min: &Position, max: &Position) -> bool {
+    self.min.lat <= max.lat && self.max.lat >= min.lat && self.min.long <= max.long && self.max.long >= min.long
+  }
+
+  pub fn draw(&self, canvas: &mut BrailleCanvas, args: &FRadarArgs, color: Color, stroke: Stroke) {
+    let (min, max) = Position::screen_bounds(args);
+    if !self.intersects(&min, &max) {
+      return;
+    }
+
+    let closed: Vec<Position> = self.boundary.iter().chain(self.boundary.first()).copied().collect();
+    canvas.styled_polyline(&closed, args, color, stroke);
+  }
+}
+
+/// Point-in-polygon by ray casting, treating degrees as planar.
+pub fn polygon_contains(boundary: &[Position], position: &Position) -> bool {
+  let Some(mut previous) = boundary.last() else {
+    return false;
+  };
+
+  let mut inside: bool = false;
+  for point in boundary.iter() {
+    if (point.lat > position.lat) != (previous.lat > position.lat) &&
+       position.long < (previous.long - point.long) * (position.lat - point.lat) / (previous.lat - point.lat) + point.long {
+      inside = !inside;
+    }
+    previous = point;
+  }
+  inside
+}
+
+/// State carried between the lines of one OpenAir airspace block.
+struct Builder {
+  class: AirspaceClass,
+  name: String,
+  floor: AirspaceLimit,
+  ceiling: AirspaceLimit,
+  boundary: Vec<Position>,
+  center: Option<Position>,
+  clockwise: bool,
+  /// The first record in the block that couldn't be parsed, which rules the whole block out.
+  problem: Option<String>,
+}
+
+impl Builder {
+  fn new(class: AirspaceClass) -> Builder {
+    Builder {
+      class,
+      name: String::new(),
+      floor: AirspaceLimit::Surface,
+      ceiling: AirspaceLimit::Unlimited,
+      boundary: Vec::new(),
+      center: None,
+      clockwise: true,
+      problem: None,
+    }
+  }
+
+  fn record(&mut self, record: &str, value: &str) -> anyhow::Result<()> {
+    match record.to_uppercase().as_str() {
+      "AN" => self.name = value.to_string(),
+      "AL" => self.floor = AirspaceLimit::parse(value)?,
+      "AH" => self.ceiling = AirspaceLimit::parse(value)?,
+      "DP" => self.boundary.push(parse_coordinate(value)?),
+      "V" => match value.split_once('=').map(|(key, value)| (key.trim().to_uppercase(), value.trim())) {
+        Some((key, value)) if key == "X" => self.center = Some(parse_coordinate(value)?),
+        Some((key, value)) if key == "D" => self.clockwise = value != "-",
+        _ => {},
+      },
+      "DA" => {
+        let numbers: Vec<f64> = value.split(',')
+          .map(|number| number.trim().parse::<f64>().map_err(|_| anyhow!("bad number '{}'", number.trim())))
+          .collect::<anyhow::Result<Vec<f64>>>()?;
+        let [radius_nm, start, end] = numbers[..] else { bail!("DA needs radius, start and end angle") };
+        self.arc(radius_nm, start, end)?;
+      },
+      "DB" => {
+        let (from, to) = value.split_once(',').ok_or_else(|| anyhow!("DB needs two coordinates"))?;
+        let (from, to) = (parse_coordinate(from)?, parse_coordinate(to)?);
+        let center: Position = self.center()?;
+        let radius_nm: f64 = center.distance_miles(&from) / MILES_PER_NM;
+        self.arc(radius_nm, center.bearing_to(&from), center.bearing_to(&to))?;
+      },
+      "DC" => {
+        let radius_nm: f64 = value.parse().map_err(|_| anyhow!("bad radius '{}'", value))?;
+        self.sweep(radius_nm, 0.0, 360.0)?;
+      },
+      _ => {},
+    }
+    Ok(())
+  }
+
+  fn finish(self) -> Option<Airspace> {
+    let first: Position = *self.boundary.first()?;
+    let min: Position = self.boundary.iter().fold(first, |min, point| Position { lat: min.lat.min(point.lat), long: min.long.min(point.long) });
+    let max: Position = self.boundary.iter().fold(first, |max, point| Position { lat: max.lat.max(point.lat), long: max.long.max(point.long) });
+
+    Some(Airspace {
+      class: self.class,
+      name: self.name,
+      floor: self.floor,
+      ceiling: self.ceiling,
+      boundary: self.boundary,
+      min,
+      max,
+    })
+  }
+
+  fn center(&self) -> anyhow::Result<Position> {
+    self.center.ok_or_else(|| anyhow!("arc or circle without a center (V X=...)"))
+  }
+
+  /// Appends an arc around the current center, from one bearing to another in the current direction.
+  fn arc(&mut self, radius_nm: f64, start: f64, end: f64) -> anyhow::Result<()> {
+    let sweep: f64 = match self.clockwise {
+      true => (end - start).rem_euclid(360.0),
+      false => -(start - end).rem_euclid(360.0),
+    };
+    self.sweep(radius_nm, start, sweep)
+  }
+
+  fn sweep(&mut self, radius_nm: f64, start: f64, sweep: f64) -> anyhow::Result<()> {
+    let center: Position = self.center()?;
+    let steps: usize = ((sweep.abs() / ARC_STEP_DEGREES).ceil() as usize).max(1);
+
+    for step in 0..=steps {
+      self.boundary.push(center.destination(start + sweep * step as f64 / steps as f64, radius_nm * MILES_PER_NM));
+    }
+    Ok(())
+  }
+}
+
+/// Reads airspaces from an OpenAir file (`AC`/`AN`/`AL`/`AH` headers, `DP` points,
+/// `V`/`DA`/`DB` arcs and `DC` circles). Records fradar doesn't draw are skipped, and so is
+/// any airspace with a record that can't be parsed; those come back as warnings.
+pub fn load_openair(path: &Path) -> anyhow::Result<(Vec<Airspace>, Vec<String>)> {
+  let bytes: Vec<u8> = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+  // OpenAir files in the wild are often Latin-1 rather than UTF-8.
+  let contents: String = String::from_utf8_lossy(&bytes).to_string();
+
+  let mut airspaces: Vec<Airspace> = Vec::new();
+  let mut warnings: Vec<String> = Vec::new();
+  let mut builder: Option<Builder> = None;
+  let mut finish = |builder: Option<Builder>| match builder {
+    Some(Builder { problem: Some(problem), name, .. }) => warnings.push(format!("{}: {}; skipped airspace '{}'", path.display(), problem, name)),
+    Some(builder) => airspaces.extend(builder.finish()),
+    None => {},
+  };
+
+  for (index, line) in contents.lines().enumerate() {
+    let line: &str = line.split('*').next().unwrap_or("").trim();
+    if line.is_empty() {
+      continue;
+    }
+
+    let (record, value): (&str, &str) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let value: &str = value.trim();
+
+    if record.eq_ignore_ascii_case("AC") {
+      finish(builder.take());
+      builder = Some(Builder::new(AirspaceClass::from_openair(&value.to_uppercase())));
+      continue;
+    }
+
+    let Some(current) = builder.as_mut() else {
+      continue;
+    };
+    if let Err(error) = current.record(record, value) && current.problem.is_none() {
+      current.problem = Some(format!("line {}: {}", index + 1, error));
+    }
+  }
+
+  finish(builder.take());
+  Ok((airspaces, warnings))
+}
+
+/// Parses OpenAir coordinates such as `37:37:00 N 122:22:30 W` or `37:37.5N 122:22.5W`.
+fn parse_coordinate(text: &str) -> anyhow::Result<Position> {
+  let upper: String = text.trim().to_uppercase();
+  let lat_end: usize = upper.find(['N', 'S']).ok_or_else(|| anyhow!("bad coordinate '{}'", text))?;
+  let long_end: usize = upper.find(['E', 'W']).filter(|&end| end > lat_end).ok_or_else(|| anyhow!("bad coordinate '{}'", text))?;
+
+  let lat: f64 = parse_sexagesimal(&upper[..lat_end]).ok_or_else(|| anyhow!("bad latitude in '{}'", text))?;
+  let long: f64 = parse_sexagesimal(&upper[lat_end + 1..long_end]).ok_or_else(|| anyhow!("bad longitude in '{}'", text))?;
+
+  Ok(Position {
+    lat: if &upper[lat_end..=lat_end] == "S" { -lat } else { lat },
+    long: if &upper[long_end..=long_end] == "W" { -long } else { long },
+  })
+}
+
+/// `DD`, `DD:MM.mmm` or `DD:MM:SS`.
+fn parse_sexagesimal(text: &str) -> Option<f64> {
+  let parts: Vec<f64> = text.trim().split(':')
+    .map(|part| part.trim().parse::<f64>().ok())
+    .collect::<Option<Vec<f64>>>()?;
+  if parts.len() > 3 {
+    return None;
+  }
+  Some(parts.iter().zip([1.0, 60.0, 3600.0]).map(|(value, divisor)| value / divisor).sum())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::*;
+
+  fn load(name: &str, contents: &str) -> (Vec<Airspace>, Vec<String>) {
+    let path: PathBuf = std::env::temp_dir().join(format!("fradar-openair-{}-{}.txt", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    let loaded = load_openair(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    loaded
+  }
+
+  fn near(a: &Position, b: &Position) -> bool {
+    (a.lat - b.lat).abs() < 1e-6 && (a.long - b.long).abs() < 1e-6
+  }
+
+  #[test]
+  fn parses_coordinates() {
+    let position: Position = parse_coordinate("37:37:00 N 122:22:30 W").unwrap();
+    assert!(near(&position, &Position { lat: 37.0 + 37.0 / 60.0, long: -(122.0 + 22.5 / 60.0) }), "{:?}", position);
+    let position: Position = parse_coordinate("33:52.5S 151:12.75e").unwrap();
+    assert!(near(&position, &Position { lat: -(33.0 + 52.5 / 60.0), long: 151.0 + 12.75 / 60.0 }), "{:?}", position);
+    assert!(near(&parse_coordinate("47 N 122 W").unwrap(), &Position { lat: 47.0, long: -122.0 }));
+
+    for text in ["", "37:37:00 122:22:30 W", "122:22:30 W 37:37:00 N", "37:x N 122 W", "1:2:3:4 N 122 W"] {
+      assert!(parse_coordinate(text).is_err(), "'{}' parsed", text);
+    }
+  }
+
+  #[test]
+  fn parses_limits() {
+    assert_eq!(AirspaceLimit::parse("SFC").unwrap(), AirspaceLimit::Surface);
+    assert_eq!(AirspaceLimit::parse("gnd").unwrap(), AirspaceLimit::Surface);
+    assert_eq!(AirspaceLimit::parse("FL 180").unwrap(), AirspaceLimit::FlightLevel(180.0));
+    assert_eq!(AirspaceLimit::parse("FL95").unwrap(), AirspaceLimit::FlightLevel(95.0));
+    assert_eq!(AirspaceLimit::parse("2500ft AGL").unwrap(), AirspaceLimit::Agl(2500.0));
+    assert_eq!(AirspaceLimit::parse("1500 GND").unwrap(), AirspaceLimit::Agl(1500.0));
+    assert_eq!(AirspaceLimit::parse("10000 MSL").unwrap(), AirspaceLimit::Msl(10000.0));
+    assert_eq!(AirspaceLimit::parse("4000").unwrap(), AirspaceLimit::Msl(4000.0));
+    assert_eq!(AirspaceLimit::parse("1000m").unwrap(), AirspaceLimit::Msl(3280.84));
+    assert_eq!(AirspaceLimit::parse("UNLTD").unwrap(), AirspaceLimit::Unlimited);
+
+    assert!(AirspaceLimit::parse("FLX").is_err());
+    assert!(AirspaceLimit::parse("high").is_err());
+  }
+
+  #[test]
+  fn builds_polygons_arcs_and_circles() {
+    let (airspaces, warnings) = load("shapes", "\
+* Comments and unknown records are ignored
+AC R
+AN R-2531
+AL SFC
+AH FL180
+DP 37:00:00 N 122:00:00 W
+DP 37:00:00 N 121:00:00 W
+DP 38:00:00 N 121:00:00 W
+
+AC D
+AN CLOCKWISE
+AL 2500 MSL
+AH UNL
+V X=37:00:00 N 122:00:00 W
+DA 10,0,90
+
+AC C
+AN ANTICLOCKWISE
+V D=-
+V X=37:00:00 N 122:00:00 W
+DB 37:10:00 N 122:00:00 W, 37:00:00 N 121:47:28 W
+
+AC B
+AN CIRCLE
+AL 1500ft AGL
+V X=37:00:00 N 122:00:00 W
+DC 5
+");
+    assert_eq!(warnings, Vec::<String>::new());
+    assert_eq!(airspaces.iter().map(|airspace| airspace.name.as_str()).collect::<Vec<&str>>(), ["R-2531", "CLOCKWISE", "ANTICLOCKWISE", "CIRCLE"]);
+
+    let restricted: &Airspace = &airspaces[0];
+    assert_eq!((restricted.class, restricted.floor, restricted.ceiling), (AirspaceClass::Restricted, AirspaceLimit::Surface, AirspaceLimit::FlightLevel(180.0)));
+    assert_eq!(restricted.boundary.len(), 3);
+    assert!(restricted.contains(&Position { lat: 37.2, long: -121.2 }));
+    assert!(!restricted.contains(&Position { lat: 37.8, long: -121.8 }));
+    assert!(restricted.contains_altitude(17_999.0) && !restricted.contains_altitude(18_001.0));
+
+    // 10nm from north round to east: 19 points, all on the circle.
+    let center: Position = Position { lat: 37.0, long: -122.0 };
+    let clockwise: &Airspace = &airspaces[1];
+    assert_eq!(clockwise.boundary.len(), 19);
+    assert!((center.bearing_to(&clockwise.boundary[1]) - 5.0).abs() < 0.1);
+    assert!(clockwise.boundary.iter().all(|point| (center.distance_miles(point) - 10.0 * MILES_PER_NM).abs() < 0.01));
+
+    // From north the long way round, anticlockwise, to east.
+    let anticlockwise: &Airspace = &airspaces[2];
+    assert_eq!(anticlockwise.class, AirspaceClass::C);
+    assert!((center.bearing_to(&anticlockwise.boundary[1]) - 355.0).abs() < 0.1);
+    assert!(anticlockwise.contains(&center.destination(225.0, 5.0)));
+    // Beyond the chord that closes the arc, but inside the circle.
+    assert!(!anticlockwise.contains(&center.destination(45.0, 9.5 * MILES_PER_NM)));
+
+    let circle: &Airspace = &airspaces[3];
+    assert_eq!(circle.floor, AirspaceLimit::Agl(1500.0));
+    assert!(circle.contains(&center.destination(300.0, 5.0)));
+    assert!(!circle.contains(&center.destination(300.0, 6.0)));
+  }
+
+  #[test]
+  fn skips_airspaces_it_cannot_read() {
+    let (airspaces, warnings) = load("bad", "\
+AC D
+AN BAD ALTITUDE
+AL lots
+DP 37:00:00 N 122:00:00 W
+DP 37:00:00 N 121:00:00 W
+DP 38:00:00 N 121:00:00 W
+AC D
+AN NO CENTER
+DC 5
+AC D
+AN GOOD
+DP 37:00:00 N 122:00:00 W
+DP 37:00:00 N 121:00:00 W
+DP 38:00:00 N 121:00:00 W
+");
+    assert_eq!(airspaces.len(), 1);
+    assert_eq!(airspaces[0].name, "GOOD");
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].ends_with(": line 3: bad altitude 'lots'; skipped airspace 'BAD ALTITUDE'"), "{}", warnings[0]);
+    assert!(warnings[1].ends_with(": line 9: arc or circle without a center (V X=...); skipped airspace 'NO CENTER'"), "{}", warnings[1]);
+  }
+}
@@ -0,0 +1,308 @@
+use std::{fs::OpenOptions, io::Write, path::{Path, PathBuf}};
+
+use chrono::{TimeZone, Utc};
+
+use crate::{aircraft_list::altitude_feet, model::{ADSBAircraftInformation, FlightData, Position}};
+
+
+/// An alert whose aircraft has dropped out of the data is kept this long before it is
+/// cleared, so one missed update doesn't clear and re-raise it.
+pub const MISSING_GRACE_MILLIS: i64 = 60_000;
+
+/// A watchlist hit stays in the banner this long unless it is acknowledged with `:ack`.
+/// Emergencies stay until they end.
+pub const WATCHLIST_BANNER_MILLIS: i64 = 30_000;
+
+/// Alert sources, most urgent first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertKind {
+  Emergency,
+  Watchlist,
+}
+
+impl AlertKind {
+  fn name(&self) -> &'static str {
+    match self {
+      AlertKind::Emergency => "emergency",
+      AlertKind::Watchlist => "watchlist",
+    }
+  }
+
+  pub fn symbol(&self) -> char {
+    match self {
+      AlertKind::Emergency => '⚠',
+      AlertKind::Watchlist => '★',
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+  pub kind: AlertKind,
+  pub hex: String,
+  pub callsign: String,
+  pub reason: String,
+  pub seen_millis: i64,
+  pub raised_millis: i64,
+  pub acknowledged: bool,
+  /// Where the aircraft was when the condition last held.
+  pub position: Position,
+  pub altitude: Option<f64>,
+}
+
+/// One aircraft for which an alert condition currently holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+  pub hex: String,
+  pub callsign: String,
+  pub reason: String,
+  pub position: Position,
+  pub altitude: Option<f64>,
+}
+
+impl Condition {
+  pub fn new(info: &ADSBAircraftInformation, position: Position, reason: String) -> Condition {
+    Condition {
+      hex: info.hex.clone(),
+      callsign: callsign(info),
+      reason,
+      position,
+      altitude: altitude_feet(info),
+    }
+  }
+}
+
+/// Active alerts, raised and cleared by the controller after every update.
+#[derive(Debug, Default, Clone)]
+pub struct Alerts {
+  pub active: Vec<Alert>,
+  pub log_path: Option<PathBuf>,
+  /// Set when an alert is raised; the view rings the terminal bell and resets it.
+  pub bell_pending: bool,
+  /// Log lines from [`Alerts::sync`] still to be written, so that the file is appended to
+  /// after the lock on the shared data is released.
+  pub pending_log: Vec<String>,
+}
+
+impl Alerts {
+  /// The most urgent alert raised for an aircraft.
+  pub fn kind_of(&self, hex: &str) -> Option<AlertKind> {
+    self.active.iter().filter(|alert| alert.hex == hex).map(|alert| alert.kind).min()
+  }
+
+  /// Alerts for the banner, most urgent first: emergencies for as long as they hold,
+  /// watchlist hits until acknowledged or [`WATCHLIST_BANNER_MILLIS`] after they were raised.
+  pub fn banner(&self, now_millis: i64) -> Vec<&Alert> {
+    let mut shown: Vec<&Alert> = self.active.iter()
+      .filter(|alert| match alert.kind {
+        AlertKind::Emergency => true,
+        AlertKind::Watchlist => !alert.acknowledged && now_millis - alert.raised_millis < WATCHLIST_BANNER_MILLIS,
+      })
+      .collect();
+    shown.sort_by_key(|alert| alert.kind);
+    shown
+  }
+
+  /// Takes every watchlist hit out of the banner. The aircraft stay highlighted on the
+  /// scope. Returns how many were acknowledged.
+  pub fn acknowledge(&mut self) -> usize {
+    self.active.iter_mut()
+      .filter(|alert| alert.kind == AlertKind::Watchlist && !alert.acknowledged)
+      .map(|alert| alert.acknowledged = true)
+      .count()
+  }
+
+  /// Writes out what [`Alerts::sync`] has logged since the last call.
+  pub fn write_log(&mut self) {
+    write_log(self.log_path.as_deref(), &std::mem::take(&mut self.pending_log));
+  }
+
+  /// Brings the alerts of one kind in line with the conditions that hold right now: new
+  /// conditions raise an alert, and alerts whose condition has ended are cleared. Both are
+  /// queued for the log. Returns a status message for anything newly raised.
+  pub fn sync(&mut self, kind: AlertKind, conditions: Vec<Condition>, flights_data: &FlightData) -> Option<String> {
+    let now: i64 = flights_data.epoch_timestamp;
+    let mut raised: Vec<String> = Vec::new();
+    let log: &mut Vec<String> = &mut self.pending_log;
+
+    for condition in conditions.iter() {
+      match self.active.iter_mut().find(|alert| alert.kind == kind && alert.hex == condition.hex) {
+        Some(alert) => {
+          alert.seen_millis = now;
+          alert.reason = condition.reason.clone();
+          alert.position = condition.position;
+          alert.altitude = condition.altitude;
+        },
+        None => {
+          log.push(log_line(now, "RAISED", kind.name(), condition));
+          raised.push(format!("{} {}", condition.callsign, condition.reason));
+          self.active.push(Alert {
+            kind,
+            hex: condition.hex.clone(),
+            callsign: condition.callsign.clone(),
+            reason: condition.reason.clone(),
+            seen_millis: now,
+            raised_millis: now,
+            acknowledged: false,
+            position: condition.position,
+            altitude: condition.altitude,
+          });
+        },
+      }
+    }
+
+    self.active.retain(|alert| {
+      if alert.kind != kind || conditions.iter().any(|condition| condition.hex == alert.hex) {
+        return true;
+      }
+
+      // Only clear an aircraft we can still see, or one that has been gone a while.
+      match flights_data.index_of_hex(&alert.hex) {
+        Some(index) => {
+          let condition: Condition = Condition::new(&flights_data.aircraft[index], flights_data.flights[index].0, alert.reason.clone());
+          log.push(log_line(now, "CLEARED", kind.name(), &condition));
+          false
+        },
+        None if now - alert.seen_millis < MISSING_GRACE_MILLIS => true,
+        None => {
+          let condition: Condition = Condition {
+            hex: alert.hex.clone(),
+            callsign: alert.callsign.clone(),
+            reason: format!("{}, lost contact", alert.reason),
+            position: alert.position,
+            altitude: alert.altitude,
+          };
+          log.push(log_line(now, "CLEARED", kind.name(), &condition));
+          false
+        },
+      }
+    });
+
+    if raised.is_empty() {
+      return None;
+    }
+    self.bell_pending = true;
+    Some(format!("ALERT: {}", raised.join(", ")))
+  }
+}
+
+/// Aircraft squawking or broadcasting an emergency.
+pub fn emergency_conditions(flights_data: &FlightData) -> Vec<Condition> {
+  flights_data.aircraft.iter().zip(flights_data.flights.iter())
+    .filter_map(|(info, (position, _))| emergency_reason(info).map(|reason| Condition::new(info, *position, reason)))
+    .collect()
+}
+
+pub fn emergency_reason(info: &ADSBAircraftInformation) -> Option<String> {
+  let squawk: Option<&str> = match info.squawk.as_deref() {
+    Some("7500") => Some("squawk 7500 (hijack)"),
+    Some("7600") => Some("squawk 7600 (radio failure)"),
+    Some("7700") => Some("squawk 7700 (emergency)"),
+    _ => None,
+  };
+  let emergency: Option<String> = info.emergency.as_deref()
+    .map(str::trim)
+    .filter(|emergency| !emergency.is_empty() && !emergency.eq_ignore_ascii_case("none"))
+    .map(|emergency| format!("emergency: {}", emergency));
+
+  match (squawk, emergency) {
+    (Some(squawk), Some(emergency)) => Some(format!("{}, {}", squawk, emergency)),
+    (Some(squawk), None) => Some(squawk.to_string()),
+    (None, emergency) => emergency,
+  }
+}
+
+pub fn callsign(info: &ADSBAircraftInformation) -> String {
+  info.flight.as_deref().map(str::trim).filter(|flight| !flight.is_empty()).unwrap_or(&info.hex).to_string()
+}
+
+fn log_line(now_millis: i64, event: &str, source: &str, condition: &Condition) -> String {
+  let time: String = Utc.timestamp_millis_opt(now_millis).single().unwrap_or_else(Utc::now).format("%Y-%m-%dT%H:%M:%SZ").to_string();
+  let altitude: String = condition.altitude.map(|feet| format!("{:.0}ft", feet)).unwrap_or_else(|| "-".to_string());
+  format!(
+    "{} {} {} {} {} {:.5},{:.5} {} {}",
+    time, event, source, condition.hex, condition.callsign, condition.position.lat, condition.position.long, altitude, condition.reason,
+  )
+}
+
+/// Appends lines to the alert log, if there is one.
+pub fn write_log(path: Option<&Path>, lines: &[String]) {
+  if let Some(path) = path && !lines.is_empty() {
+    // A log that can't be written must never take the scope down with it.
+    let _ = append_log(path, lines);
+  }
+}
+
+/// Appends a one-off event, such as a geofence crossing, to the alert log.
+pub fn log_event(path: Option<&Path>, now_millis: i64, event: &str, source: &str, condition: &Condition) {
+  write_log(path, &[log_line(now_millis, event, source, condition)]);
+}
+
+fn append_log(path: &Path, lines: &[String]) -> anyhow::Result<()> {
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+  for line in lines.iter() {
+    writeln!(file, "{}", line)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{aircraft, flights_data};
+
+  #[test]
+  fn clears_lost_aircraft_after_the_grace_period() {
+    let path: PathBuf = std::env::temp_dir().join(format!("fradar-alerts-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut alerts: Alerts = Alerts { log_path: Some(path.clone()), ..Default::default() };
+    let info: ADSBAircraftInformation = ADSBAircraftInformation {
+      flight: Some("N123".to_string()),
+      squawk: Some("7700".to_string()),
+      ..aircraft("abc123", &Position { lat: 51.5, long: -0.1 }, 3000)
+    };
+
+    let data: FlightData = flights_data(0, vec![info]);
+    assert!(alerts.sync(AlertKind::Emergency, emergency_conditions(&data), &data).is_some());
+
+    let data: FlightData = flights_data(MISSING_GRACE_MILLIS - 1, Vec::new());
+    assert_eq!(alerts.sync(AlertKind::Emergency, emergency_conditions(&data), &data), None);
+    assert_eq!(alerts.active.len(), 1);
+
+    let data: FlightData = flights_data(MISSING_GRACE_MILLIS, Vec::new());
+    alerts.sync(AlertKind::Emergency, emergency_conditions(&data), &data);
+    assert!(alerts.active.is_empty());
+    assert_eq!(alerts.pending_log.len(), 2);
+    alerts.write_log();
+    assert!(alerts.pending_log.is_empty());
+
+    let log: String = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("RAISED emergency abc123 N123"));
+    assert!(lines[1].ends_with("CLEARED emergency abc123 N123 51.50000,-0.10000 3000ft squawk 7700 (emergency), lost contact"), "{}", lines[1]);
+  }
+
+  #[test]
+  fn watchlist_hits_leave_the_banner() {
+    let mut alerts: Alerts = Alerts::default();
+    let emergency: ADSBAircraftInformation = ADSBAircraftInformation { squawk: Some("7600".to_string()), ..aircraft("aaaaaa", &Position::default(), 3000) };
+    let watched: ADSBAircraftInformation = aircraft("bbbbbb", &Position::default(), 3000);
+    let data: FlightData = flights_data(0, vec![emergency, watched.clone()]);
+    alerts.sync(AlertKind::Emergency, emergency_conditions(&data), &data);
+    alerts.sync(AlertKind::Watchlist, vec![Condition::new(&watched, Position::default(), "watchlist".to_string())], &data);
+
+    let hexes = |alerts: &Alerts, now: i64| alerts.banner(now).iter().map(|alert| alert.hex.clone()).collect::<Vec<String>>();
+    assert_eq!(hexes(&alerts, 0), ["aaaaaa", "bbbbbb"]);
+    assert_eq!(hexes(&alerts, WATCHLIST_BANNER_MILLIS), ["aaaaaa"]);
+
+    assert_eq!(alerts.acknowledge(), 1);
+    assert_eq!(alerts.acknowledge(), 0);
+    assert_eq!(hexes(&alerts, 0), ["aaaaaa"]);
+    assert_eq!(alerts.kind_of("bbbbbb"), Some(AlertKind::Watchlist));
+  }
+}
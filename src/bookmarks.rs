@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table};
+
+use crate::model::Position;
+
+
+/// A saved view, stored in the config file as an entry of the `[[bookmarks]]` array.
+/// Keys 1-9 jump to the bookmarks in file order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+  pub name: String,
+  pub origin: Position,
+  pub radius: f64,
+}
+
+pub fn find_bookmark<'a>(bookmarks: &'a [Bookmark], name: &str) -> Option<&'a Bookmark> {
+  bookmarks.iter().find(|bookmark| bookmark.name.eq_ignore_ascii_case(name))
+}
+
+/// Adds or replaces a bookmark in the config file, keeping the rest of the file untouched.
+pub fn save_bookmark(path: &Path, bookmark: &Bookmark) -> anyhow::Result<()> {
+  edit_bookmarks(path, |bookmarks| {
+    let mut table: Table = Table::new();
+    table.insert("name", value(bookmark.name.clone()));
+    table.insert("origin", value(Array::from_iter([round(bookmark.origin.lat, 5), round(bookmark.origin.long, 5)])));
+    table.insert("radius", value(round(bookmark.radius, 2)));
+
+    match position_of(bookmarks, &bookmark.name) {
+      Some(index) => *bookmarks.get_mut(index).unwrap() = table,
+      None => bookmarks.push(table),
+    }
+    Ok(())
+  })
+}
+
+pub fn delete_bookmark(path: &Path, name: &str) -> anyhow::Result<()> {
+  edit_bookmarks(path, |bookmarks| {
+    let index: usize = position_of(bookmarks, name).ok_or_else(|| anyhow!("no bookmark named '{}'", name))?;
+    bookmarks.remove(index);
+    Ok(())
+  })
+}
+
+fn edit_bookmarks(path: &Path, edit: impl FnOnce(&mut ArrayOfTables) -> anyhow::Result<()>) -> anyhow::Result<()> {
+  let contents: String = match std::fs::read_to_string(path) {
+    Ok(contents) => contents,
+    Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+    Err(error) => return Err(error).with_context(|| format!("failed to read {}", path.display())),
+  };
+
+  let mut document: DocumentMut = contents.parse()
+    .map_err(|error| anyhow!("{}: {}", path.display(), error))?;
+
+  let item: &mut Item = document.entry("bookmarks").or_insert(Item::ArrayOfTables(ArrayOfTables::new()));
+  let Some(bookmarks) = item.as_array_of_tables_mut() else {
+    bail!("{}: key `bookmarks` must be an array of tables ([[bookmarks]])", path.display());
+  };
+  edit(bookmarks)?;
+
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  std::fs::write(path, document.to_string())
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn position_of(bookmarks: &ArrayOfTables, name: &str) -> Option<usize> {
+  bookmarks.iter().position(|table| {
+    table.get("name").and_then(Item::as_str).is_some_and(|existing| existing.eq_ignore_ascii_case(name))
+  })
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+  let factor: f64 = 10f64.powi(decimals);
+  (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::*;
+
+  #[test]
+  fn edits_bookmarks_and_keeps_the_rest_of_the_file() {
+    let path: PathBuf = std::env::temp_dir().join(format!("fradar-bookmarks-{}.toml", std::process::id()));
+    std::fs::write(&path, "# my settings\nradius = 30\n\n[[bookmarks]]\nname = \"KSFO\"\norigin = [37.6, -122.4]\n").unwrap();
+
+    let home: Bookmark = Bookmark { name: "home".to_string(), origin: Position { lat: 47.450001, long: -122.3 }, radius: 12.345 };
+    save_bookmark(&path, &home).unwrap();
+    save_bookmark(&path, &Bookmark { name: "ksfo".to_string(), radius: 5.0, ..home.clone() }).unwrap();
+    let contents: String = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.starts_with("# my settings\nradius = 30\n"), "{}", contents);
+    assert_eq!(contents.matches("[[bookmarks]]").count(), 2, "{}", contents);
+    assert!(contents.contains("name = \"ksfo\"\norigin = [47.45, -122.3]\nradius = 5.0"), "{}", contents);
+    assert!(contents.contains("name = \"home\"\norigin = [47.45, -122.3]\nradius = 12.35"), "{}", contents);
+
+    delete_bookmark(&path, "KSFO").unwrap();
+    assert!(delete_bookmark(&path, "KSFO").is_err());
+    let contents: String = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(contents.matches("[[bookmarks]]").count(), 1, "{}", contents);
+    assert!(!contents.contains("ksfo"), "{}", contents);
+  }
+
+  #[test]
+  fn finds_bookmarks_by_name_in_any_case() {
+    let bookmarks: Vec<Bookmark> = vec![Bookmark { name: "Home".to_string(), origin: Position::default(), radius: 10.0 }];
+    assert_eq!(find_bookmark(&bookmarks, "HOME"), bookmarks.first());
+    assert_eq!(find_bookmark(&bookmarks, "work"), None);
+  }
+}
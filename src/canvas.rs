@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use crossterm::{cursor, queue, style::{self, Color}};
+
+use crate::model::{Coord, FRadarArgs, Position};
+
+
+/// Dash patterns for lines, counted in braille dots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stroke {
+  Solid,
+  Dashed,
+  Dotted,
+}
+
+impl Stroke {
+  fn is_on(&self, step: usize) -> bool {
+    match self {
+      Stroke::Solid => true,
+      Stroke::Dashed => step % 6 < 4,
+      Stroke::Dotted => step.is_multiple_of(3),
+    }
+  }
+}
+
+/// Off-screen buffer of braille sub-character dots (2 wide by 4 tall per terminal cell)
+/// that overlay layers draw lines and points into before it is flushed to stdout.
+pub struct BrailleCanvas {
+  cells: HashMap<Coord<u16>, (u8, Color)>,
+  dots_wide: i64,
+  dots_tall: i64,
+}
+
+impl BrailleCanvas {
+  pub fn new(args: &FRadarArgs) -> BrailleCanvas {
+    BrailleCanvas {
+      cells: HashMap::new(),
+      dots_wide: args.terminal_cols as i64 * 2,
+      dots_tall: args.terminal_rows as i64 * 4,
+    }
+  }
+
+  /// Sets one dot, given in fractional terminal coordinates.
+  pub fn plot(&mut self, coord: Coord<f64>, color: Color) {
+    if coord.col.is_finite() && coord.row.is_finite() {
+      self.plot_dot((coord.col * 2.0).floor() as i64, (coord.row * 4.0).floor() as i64, color);
+    }
+  }
+
+  fn plot_dot(&mut self, x: i64, y: i64, color: Color) {
+    if x < 0 || y < 0 || x >= self.dots_wide || y >= self.dots_tall {
+      return;
+    }
+
+    let bit: u8 = match (x % 2, y % 4) {
+      (0, 0) => 0,
+      (0, 1) => 1,
+      (0, 2) => 2,
+      (1, 0) => 3,
+      (1, 1) => 4,
+      (1, 2) => 5,
+      (0, 3) => 6,
+      _ => 7,
+    };
+
+    let cell = self.cells.entry(Coord { col: (x / 2) as u16, row: (y / 4) as u16 }).or_insert((0, color));
+    cell.0 |= 1 << bit;
+    cell.1 = color;
+  }
+
+  /// Draws a straight segment between two fractional terminal coordinates.
+  pub fn line(&mut self, from: Coord<f64>, to: Coord<f64>, color: Color) {
+    self.stroke(from, to, color, Stroke::Solid, &mut 0);
+  }
+
+  /// Draws a segment with a dash pattern. `step` carries the pattern's phase from one
+  /// segment to the next, so dashes run on smoothly around corners.
+  pub fn stroke(&mut self, from: Coord<f64>, to: Coord<f64>, color: Color, stroke: Stroke, step: &mut usize) {
+    let Some((from, to)) = self.clip(from, to) else {
+      return;
+    };
+
+    let (mut x0, mut y0) = ((from.col * 2.0).floor() as i64, (from.row * 4.0).floor() as i64);
+    let (x1, y1) = ((to.col * 2.0).floor() as i64, (to.row * 4.0).floor() as i64);
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let mut error: i64 = dx + dy;
+
+    loop {
+      if stroke.is_on(*step) {
+        self.plot_dot(x0, y0, color);
+      }
+      *step += 1;
+      if x0 == x1 && y0 == y1 {
+        break;
+      }
+
+      let doubled: i64 = 2 * error;
+      if doubled >= dy {
+        error += dy;
+        x0 += sx;
+      }
+      if doubled <= dx {
+        error += dx;
+        y0 += sy;
+      }
+    }
+  }
+
+  pub fn polyline(&mut self, positions: &[Position], args: &FRadarArgs, color: Color) {
+    self.styled_polyline(positions, args, color, Stroke::Solid);
+  }
+
+  pub fn styled_polyline(&mut self, positions: &[Position], args: &FRadarArgs, color: Color, stroke: Stroke) {
+    let mut step: usize = 0;
+    for pair in positions.windows(2) {
+      self.stroke(pair[0].as_terminal_coord_unclamped(args), pair[1].as_terminal_coord_unclamped(args), color, stroke, &mut step);
+    }
+  }
+
+  /// Clips a segment to the canvas (Liang-Barsky), so far off-screen geometry costs nothing.
+  fn clip(&self, from: Coord<f64>, to: Coord<f64>) -> Option<(Coord<f64>, Coord<f64>)> {
+    if !(from.col.is_finite() && from.row.is_finite() && to.col.is_finite() && to.row.is_finite()) {
+      return None;
+    }
+
+    let (max_col, max_row) = ((self.dots_wide as f64 - 0.01) / 2.0, (self.dots_tall as f64 - 0.01) / 4.0);
+    let (dx, dy) = (to.col - from.col, to.row - from.row);
+    let (mut t0, mut t1): (f64, f64) = (0.0, 1.0);
+
+    for (p, q) in [(-dx, from.col), (dx, max_col - from.col), (-dy, from.row), (dy, max_row - from.row)] {
+      if p == 0.0 {
+        if q < 0.0 {
+          return None;
+        }
+        continue;
+      }
+
+      let r: f64 = q / p;
+      if p < 0.0 {
+        t0 = t0.max(r);
+      } else {
+        t1 = t1.min(r);
+      }
+      if t0 > t1 {
+        return None;
+      }
+    }
+
+    Some((
+      Coord { col: from.col + t0 * dx, row: from.row + t0 * dy },
+      Coord { col: from.col + t1 * dx, row: from.row + t1 * dy },
+    ))
+  }
+
+  /// Queues every touched cell to stdout.
+  pub fn flush(&self) -> anyhow::Result<()> {
+    for (cell, (bits, color)) in self.cells.iter() {
+      queue!(
+        std::io::stdout(),
+        cursor::MoveTo(cell.col, cell.row),
+        style::SetForegroundColor(*color),
+        style::Print(char::from_u32(0x2800 + *bits as u32).unwrap_or(' ')),
+      )?;
+    }
+    queue!(std::io::stdout(), style::ResetColor)?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,495 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, bail};
+use chrono::Local;
+
+use crate::{bookmarks::{delete_bookmark, find_bookmark, save_bookmark, Bookmark}, config::{parse_distance_miles, parse_duration}, conflicts::parse_separation, filter::{Filter, FIELDS}, geofence::{parse_altitude_band, FenceShape, Geofence}, model::{CoordFormat, FRadarData, FRadarState, FlightData, Layers, Position, Transition}};
+
+
+/// Commands accepted at the `:` prompt, in the order they are offered for completion.
+pub const COMMANDS: [&str; 15] = ["goto", "radius", "filter", "select", "follow", "list", "spotter", "layer", "set", "record", "bookmark", "fence", "ack", "clear", "quit"];
+
+/// Keys accepted by `:set`.
+pub const SETTINGS: [&str; 8] = ["trails", "leaders", "conflicts", "separation", "data", "fps", "airspace", "coords"];
+
+/// Parses and runs one command line against the shared state, returning a status message.
+pub fn execute(fradar_data_ref: &mut FRadarData, line: &str) -> anyhow::Result<String> {
+  let line: &str = line.trim();
+  let (command, rest): (&str, &str) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+  let rest: &str = rest.trim();
+  let words: Vec<&str> = rest.split_whitespace().collect();
+
+  match command {
+    "" => Ok(String::new()),
+    "goto" | "g" => {
+      let (target, radius): (Position, Option<f64>) = resolve_location(fradar_data_ref, &words)?;
+      let radius: f64 = radius.unwrap_or(fradar_data_ref.args.radius);
+      fradar_data_ref.ui.transition = Some(Transition::new(&fradar_data_ref.args, target, radius));
+      fradar_data_ref.ui.follow = false;
+      Ok(format!("centered on {:.4} {:.4}", target.lat, target.long))
+    },
+    "bookmark" | "b" => {
+      let config_path = || fradar_data_ref.config_path.clone().ok_or_else(|| anyhow!("no config file to save bookmarks in"));
+      match words[..] {
+        [] => Ok(match fradar_data_ref.bookmarks.is_empty() {
+          true => "no bookmarks".to_string(),
+          false => fradar_data_ref.bookmarks.iter().enumerate()
+            .map(|(index, bookmark)| format!("{}:{}", index + 1, bookmark.name))
+            .collect::<Vec<String>>()
+            .join("  "),
+        }),
+        ["delete", name] => {
+          let path: PathBuf = config_path()?;
+          delete_bookmark(&path, name)?;
+          fradar_data_ref.bookmarks.retain(|bookmark| !bookmark.name.eq_ignore_ascii_case(name));
+          Ok(format!("deleted bookmark '{}'", name))
+        },
+        [name] => {
+          let path: PathBuf = config_path()?;
+          let bookmark: Bookmark = Bookmark { name: name.to_string(), origin: fradar_data_ref.args.origin, radius: fradar_data_ref.args.radius };
+          save_bookmark(&path, &bookmark)?;
+          let bookmarks: &mut Vec<Bookmark> = &mut fradar_data_ref.bookmarks;
+          match bookmarks.iter().position(|existing| existing.name.eq_ignore_ascii_case(name)) {
+            Some(index) => bookmarks[index] = bookmark,
+            None => bookmarks.push(bookmark),
+          }
+          Ok(format!("saved bookmark '{}' to {}", name, path.display()))
+        },
+        _ => bail!("usage: bookmark [<name> | delete <name>]"),
+      }
+    },
+    "fence" => {
+      match words[..] {
+        [] => Ok(match fradar_data_ref.geofences.fences.is_empty() {
+          true => "no fences".to_string(),
+          false => fradar_data_ref.geofences.fences.iter()
+            .map(|fence| fence.describe(&fradar_data_ref.args))
+            .collect::<Vec<String>>()
+            .join("  "),
+        }),
+        ["delete", name] => match fradar_data_ref.geofences.remove(name) {
+          true => Ok(format!("deleted fence '{}'", name)),
+          false => bail!("no fence named '{}'", name),
+        },
+        [name] => match fradar_data_ref.geofences.find(name) {
+          Some(fence) => Ok(fence.describe(&fradar_data_ref.args)),
+          None => bail!("no fence named '{}'", name),
+        },
+        [name, ..] => {
+          let fence: Geofence = parse_fence(fradar_data_ref, name, &words[1..])?;
+          let message: String = format!("fence {}", fence.describe(&fradar_data_ref.args));
+          fradar_data_ref.geofences.insert(fence);
+          Ok(message)
+        },
+      }
+    },
+    "radius" | "r" => {
+      let [value] = words[..] else { bail!("usage: radius <distance>[mi|nm|km]") };
+      let miles: f64 = parse_distance_miles(value)?;
+      if miles <= 0.0 {
+        bail!("radius must be positive");
+      }
+      fradar_data_ref.args.radius = miles;
+      Ok(format!("radius {}", fradar_data_ref.args.units.format_distance(miles)))
+    },
+    "filter" | "f" => {
+      match rest {
+        "" => Ok(match &fradar_data_ref.filter {
+          Some(filter) => format!("filter: {}", filter),
+          None => "no filter".to_string(),
+        }),
+        "off" | "clear" => {
+          fradar_data_ref.filter = None;
+          Ok("filter cleared".to_string())
+        },
+        _ => {
+          let filter: Filter = Filter::parse(rest)?;
+          fradar_data_ref.filter = Some(filter);
+          Ok(format!("filter: {}", rest))
+        },
+      }
+    },
+    "select" | "s" => {
+      let flights_data: FlightData = fradar_data_ref.flights_data.lock().unwrap().clone();
+      let index: usize = flights_data.index_of_callsign(rest)
+        .or_else(|| flights_data.index_of_hex(&rest.to_lowercase()))
+        .ok_or_else(|| anyhow!("no aircraft matching '{}'", rest))?;
+      fradar_data_ref.ui.selected_hex = Some(flights_data.aircraft[index].hex.clone());
+      fradar_data_ref.ui.show_details = true;
+      Ok(format!("selected {}", flights_data.aircraft[index].hex))
+    },
+    "follow" => {
+      if fradar_data_ref.ui.selected_hex.is_none() {
+        bail!("nothing selected");
+      }
+      fradar_data_ref.ui.follow = !fradar_data_ref.ui.follow;
+      Ok(format!("follow {}", if fradar_data_ref.ui.follow { "on" } else { "off" }))
+    },
+    "list" => {
+      fradar_data_ref.ui.list_mode = fradar_data_ref.ui.list_mode.next();
+      Ok(format!("list {:?}", fradar_data_ref.ui.list_mode).to_lowercase())
+    },
+    "spotter" => {
+      fradar_data_ref.ui.spotter = match words[..] {
+        [] => !fradar_data_ref.ui.spotter,
+        ["on"] => true,
+        ["off"] => false,
+        _ => bail!("usage: spotter [on|off]"),
+      };
+      Ok(format!("spotter {}", if fradar_data_ref.ui.spotter { "on" } else { "off" }))
+    },
+    "layer" => {
+      let names: Vec<String> = layer_names(fradar_data_ref);
+      let (name, state): (&str, Option<bool>) = match words[..] {
+        [name] => (name, None),
+        [name, "on"] => (name, Some(true)),
+        [name, "off"] => (name, Some(false)),
+        _ => bail!("usage: layer <{}> [on|off]", names.join("|")),
+      };
+
+      let layers: &mut Layers = &mut fradar_data_ref.ui.layers;
+      let visible: bool = match layers.get_mut(name) {
+        Some(layer) => {
+          *layer = state.unwrap_or(!*layer);
+          *layer
+        },
+        None if names.iter().any(|overlay| overlay == name) => {
+          let visible: bool = state.unwrap_or(layers.hidden_overlays.contains(name));
+          match visible {
+            true => layers.hidden_overlays.remove(name),
+            false => layers.hidden_overlays.insert(name.to_string()),
+          };
+          visible
+        },
+        None => bail!("unknown layer '{}' (expected one of: {})", name, names.join(", ")),
+      };
+      Ok(format!("{} {}", name, if visible { "on" } else { "off" }))
+    },
+    "set" => {
+      let [key, value] = words[..] else { bail!("usage: set <{}> <value>", SETTINGS.join("|")) };
+      set(fradar_data_ref, key, value)
+    },
+    "record" => {
+      match words[..] {
+        ["on"] | ["on", _] => {
+          let path: String = words.get(1).map(|path| path.to_string())
+            .unwrap_or_else(|| format!("fradar-{}.jsonl", Local::now().format("%Y%m%d-%H%M%S")));
+          fradar_data_ref.ui.recording = Some(path.clone());
+          Ok(format!("recording to {}", path))
+        },
+        ["off"] => match fradar_data_ref.ui.recording.take() {
+          Some(path) => Ok(format!("stopped recording to {}", path)),
+          None => Ok("not recording".to_string()),
+        },
+        _ => bail!("usage: record on [path] | record off"),
+      }
+    },
+    "ack" => Ok(match fradar_data_ref.alerts.acknowledge() {
+      0 => "no watchlist alerts to acknowledge".to_string(),
+      count => format!("acknowledged {} watchlist alert{}", count, if count == 1 { "" } else { "s" }),
+    }),
+    "clear" => {
+      fradar_data_ref.ui.selected_hex = None;
+      fradar_data_ref.ui.show_details = false;
+      fradar_data_ref.ui.follow = false;
+      Ok(String::new())
+    },
+    "quit" | "q" => {
+      fradar_data_ref.state = FRadarState::GracefulKill;
+      Ok(String::new())
+    },
+    _ => bail!("unknown command '{}'", command),
+  }
+}
+
+fn set(fradar_data_ref: &mut FRadarData, key: &str, value: &str) -> anyhow::Result<String> {
+  match key {
+    "trails" => {
+      let duration: Duration = if value == "off" { Duration::ZERO } else { parse_duration(value)? };
+      fradar_data_ref.args.trail_duration = duration;
+      fradar_data_ref.args.fit_history_to_trail();
+      Ok(format!("trails {}", if duration.is_zero() { "off".to_string() } else { format!("{:?}", duration) }))
+    },
+    "leaders" => {
+      let duration: Duration = if value == "off" { Duration::ZERO } else { parse_duration(value)? };
+      fradar_data_ref.args.leader_duration = duration;
+      Ok(format!("leaders {}", if duration.is_zero() { "off".to_string() } else { format!("{:?}", duration) }))
+    },
+    "conflicts" => {
+      let duration: Duration = if value == "off" { Duration::ZERO } else { parse_duration(value)? };
+      fradar_data_ref.args.conflict_horizon = duration;
+      if duration.is_zero() {
+        fradar_data_ref.conflicts.clear();
+      }
+      Ok(format!("conflicts {}", if duration.is_zero() { "off".to_string() } else { format!("within {:?}", duration) }))
+    },
+    "separation" => {
+      let (lateral, vertical) = parse_separation(value)?;
+      (fradar_data_ref.args.conflict_lateral, fradar_data_ref.args.conflict_vertical) = (lateral, vertical);
+      Ok(format!("separation {} / {:.0} ft", fradar_data_ref.args.units.format_distance(lateral), vertical))
+    },
+    "data" => {
+      let duration: Duration = parse_duration(value)?;
+      if duration.is_zero() {
+        bail!("data interval must be positive");
+      }
+      fradar_data_ref.args.data_interval = duration;
+      fradar_data_ref.args.fit_history_to_trail();
+      Ok(format!("data interval {:?}", duration))
+    },
+    "fps" => {
+      let fps: f64 = value.parse().map_err(|_| anyhow!("invalid frame rate '{}'", value))?;
+      if fps <= 0.0 {
+        bail!("frame rate must be positive");
+      }
+      fradar_data_ref.args.frame_interval = Duration::from_secs_f64(1.0 / fps);
+      Ok(format!("{} fps", fps))
+    },
+    "airspace" => {
+      fradar_data_ref.ui.layers.airspace_at_altitude = match value {
+        "all" => false,
+        "altitude" => true,
+        _ => bail!("usage: set airspace all|altitude"),
+      };
+      Ok(format!("airspace {}", if fradar_data_ref.ui.layers.airspace_at_altitude { "at the selected aircraft's altitude" } else { "at all altitudes" }))
+    },
+    "coords" => {
+      let coords: CoordFormat = value.parse()?;
+      fradar_data_ref.args.coords = coords;
+      Ok(format!("coordinates in {}", value.to_lowercase()))
+    },
+    _ => bail!("unknown setting '{}' (expected one of: {})", key, SETTINGS.join(", ")),
+  }
+}
+
+/// Builds a fence from `<radius>` (a circle around the view center) or three or more
+/// `<lat>,<long>` points, followed by an optional `<floor>-<ceiling>` band and dwell time.
+fn parse_fence(fradar_data_ref: &FRadarData, name: &str, words: &[&str]) -> anyhow::Result<Geofence> {
+  const USAGE: &str = "usage: fence <name> <radius> | <lat>,<long> <lat>,<long> <lat>,<long>... [<floor>-<ceiling>] [<dwell>]";
+
+  let points: Vec<&str> = words.iter().copied().take_while(|word| word.contains(',')).collect();
+  let (shape, rest): (FenceShape, &[&str]) = match (points.len(), words) {
+    (0, [radius, rest @ ..]) => {
+      let radius_miles: f64 = parse_distance_miles(radius)?;
+      if radius_miles <= 0.0 {
+        bail!("radius must be positive");
+      }
+      (FenceShape::Circle { center: fradar_data_ref.args.origin, radius_miles }, rest)
+    },
+    (count, _) if count >= 3 => {
+      let boundary: Vec<Position> = points.iter()
+        .map(|point| {
+          let (lat, long) = point.split_once(',').unwrap();
+          resolve_location(fradar_data_ref, &[lat, long]).map(|(position, _)| position)
+        })
+        .collect::<anyhow::Result<Vec<Position>>>()?;
+      (FenceShape::Polygon(boundary), &words[count..])
+    },
+    _ => bail!(USAGE),
+  };
+
+  let mut fence: Geofence = Geofence { name: name.to_string(), shape, floor: None, ceiling: None, dwell: None };
+  for word in rest.iter() {
+    match word.contains('-') {
+      true => (fence.floor, fence.ceiling) = parse_altitude_band(word)?,
+      false => fence.dwell = Some(parse_duration(word)?).filter(|dwell| !dwell.is_zero()),
+    }
+  }
+  Ok(fence)
+}
+
+/// Resolves the arguments of `:goto` into a position, and a radius if the target has one.
+fn resolve_location(fradar_data_ref: &FRadarData, words: &[&str]) -> anyhow::Result<(Position, Option<f64>)> {
+  match words {
+    [lat, long] => {
+      let lat: f64 = lat.trim_end_matches(',').parse().map_err(|_| anyhow!("invalid latitude '{}'", lat))?;
+      let long: f64 = long.parse().map_err(|_| anyhow!("invalid longitude '{}'", long))?;
+      if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&long) {
+        bail!("coordinates out of range");
+      }
+      Ok((Position { lat, long }, None))
+    },
+    [name] => {
+      if let Some(bookmark) = find_bookmark(&fradar_data_ref.bookmarks, name) {
+        return Ok((bookmark.origin, Some(bookmark.radius)));
+      }
+      if let Some(airport) = fradar_data_ref.airports.find(name) {
+        return Ok((airport.position, None));
+      }
+      match fradar_data_ref.airports.len() {
+        0 => bail!("unknown location '{}' (load --airports to look up airport codes)", name),
+        _ => bail!("unknown location '{}'", name),
+      }
+    },
+    _ => bail!("usage: goto <lat> <long> | goto <name>"),
+  }
+}
+
+/// Built-in layers followed by the GeoJSON overlays that were loaded.
+fn layer_names(fradar_data_ref: &FRadarData) -> Vec<String> {
+  Layers::NAMES.iter()
+    .map(|name| name.to_string())
+    .chain(fradar_data_ref.overlays.iter().map(|overlay| overlay.name.clone()))
+    .collect()
+}
+
+/// Candidates for the word under the cursor, given the full line typed so far.
+pub fn completions(fradar_data_ref: &FRadarData, line: &str) -> Vec<String> {
+  let words: Vec<&str> = line.split_whitespace().collect();
+  let completing_new_word: bool = line.is_empty() || line.ends_with(char::is_whitespace);
+  let (previous, current): (&[&str], &str) = match (completing_new_word, words.split_last()) {
+    (false, Some((last, previous))) => (previous, last),
+    _ => (&words[..], ""),
+  };
+
+  let candidates: Vec<String> = match previous {
+    [] => COMMANDS.iter().map(|command| command.to_string()).collect(),
+    ["set"] => SETTINGS.iter().map(|setting| setting.to_string()).collect(),
+    ["set", "airspace"] => Vec::from(["all".to_string(), "altitude".to_string()]),
+    ["set", "coords"] => ["dms", "decimal", "maidenhead"].map(str::to_string).to_vec(),
+    ["record"] | ["spotter"] => Vec::from(["on".to_string(), "off".to_string()]),
+    ["layer"] => layer_names(fradar_data_ref),
+    ["layer", _] => Vec::from(["on".to_string(), "off".to_string()]),
+    ["goto"] | ["bookmark", "delete"] => fradar_data_ref.bookmarks.iter().map(|bookmark| bookmark.name.clone()).collect(),
+    ["fence"] => fradar_data_ref.geofences.fences.iter().map(|fence| fence.name.clone()).chain(["delete".to_string()]).collect(),
+    ["fence", "delete"] => fradar_data_ref.geofences.fences.iter().map(|fence| fence.name.clone()).collect(),
+    ["bookmark"] => fradar_data_ref.bookmarks.iter().map(|bookmark| bookmark.name.clone()).chain(["delete".to_string()]).collect(),
+    ["filter", ..] => FIELDS.iter().map(|field| field.to_string()).chain(["and", "or", "not", "between", "off"].map(str::to_string)).collect(),
+    ["select"] => fradar_data_ref.flights_data.lock().unwrap().aircraft.iter()
+      .filter_map(|info| info.flight.as_deref().map(str::trim).filter(|flight| !flight.is_empty()).map(str::to_string))
+      .collect(),
+    _ => Vec::new(),
+  };
+
+  let mut matches: Vec<String> = candidates.into_iter()
+    .filter(|candidate| candidate.to_lowercase().starts_with(&current.to_lowercase()))
+    .collect();
+  matches.sort();
+  matches.dedup();
+  matches
+}
+
+/// Completes the last word of `line` as far as the candidates agree.
+pub fn complete(fradar_data_ref: &FRadarData, line: &str) -> (String, Vec<String>) {
+  let matches: Vec<String> = completions(fradar_data_ref, line);
+  let Some(first) = matches.first() else {
+    return (line.to_string(), matches);
+  };
+
+  let common: String = matches.iter().skip(1).fold(first.clone(), |prefix, candidate| {
+    prefix.chars().zip(candidate.chars())
+      .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+      .map(|(a, _)| a)
+      .collect()
+  });
+
+  let stem: &str = match line.rfind(char::is_whitespace) {
+    Some(index) => &line[..=index],
+    None => "",
+  };
+  let suffix: &str = if matches.len() == 1 { " " } else { "" };
+  (format!("{}{}{}", stem, common, suffix), matches)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::{fixtures::fradar_data, model::FRadarArgs, overlays::Overlay};
+
+  #[test]
+  fn sets_shows_and_clears_the_filter() {
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs::default());
+    assert_eq!(execute(&mut fradar_data, "filter").unwrap(), "no filter");
+
+    assert_eq!(execute(&mut fradar_data, "filter alt > 10000 and type ~ B7*").unwrap(), "filter: alt > 10000 and type ~ B7*");
+    assert!(fradar_data.filter.is_some());
+    assert!(execute(&mut fradar_data, "f").unwrap().starts_with("filter: "));
+
+    assert_eq!(execute(&mut fradar_data, "filter off").unwrap(), "filter cleared");
+    assert_eq!(fradar_data.filter, None);
+  }
+
+  #[test]
+  fn lists_bookmarks_without_a_config_file() {
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs::default());
+    assert_eq!(execute(&mut fradar_data, "bookmark").unwrap(), "no bookmarks");
+    fradar_data.bookmarks.push(Bookmark { name: "home".to_string(), origin: Position::default(), radius: 10.0 });
+    fradar_data.bookmarks.push(Bookmark { name: "work".to_string(), origin: Position::default(), radius: 10.0 });
+    assert_eq!(execute(&mut fradar_data, "b").unwrap(), "1:home  2:work");
+
+    assert_eq!(execute(&mut fradar_data, "bookmark cabin").unwrap_err().to_string(), "no config file to save bookmarks in");
+    assert!(execute(&mut fradar_data, "bookmark delete home").is_err());
+    assert_eq!(fradar_data.bookmarks.len(), 2);
+  }
+
+  #[test]
+  fn saves_deletes_and_goes_to_bookmarks() {
+    let path: PathBuf = std::env::temp_dir().join(format!("fradar-command-bookmarks-{}.toml", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs { origin: Position { lat: 47.45, long: -122.3 }, radius: 15.0, ..Default::default() });
+    fradar_data.config_path = Some(path.clone());
+
+    assert_eq!(execute(&mut fradar_data, "bookmark home").unwrap(), format!("saved bookmark 'home' to {}", path.display()));
+    fradar_data.args.radius = 40.0;
+    execute(&mut fradar_data, "bookmark HOME").unwrap();
+    assert_eq!(fradar_data.bookmarks, [Bookmark { name: "HOME".to_string(), origin: Position { lat: 47.45, long: -122.3 }, radius: 40.0 }]);
+
+    fradar_data.args.origin = Position::default();
+    execute(&mut fradar_data, "goto home").unwrap();
+    let transition: Transition = fradar_data.ui.transition.unwrap();
+    assert_eq!((transition.to_origin, transition.to_radius), (Position { lat: 47.45, long: -122.3 }, 40.0));
+
+    assert_eq!(execute(&mut fradar_data, "bookmark delete home").unwrap(), "deleted bookmark 'home'");
+    let contents: String = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(fradar_data.bookmarks.is_empty());
+    assert!(!contents.contains("HOME"), "{}", contents);
+  }
+
+  #[test]
+  fn toggles_built_in_and_overlay_layers() {
+    let path: PathBuf = std::env::temp_dir().join(format!("coast-{}.geojson", std::process::id()));
+    std::fs::write(&path, r#"{"type": "LineString", "coordinates": [[0, 0], [1, 1]]}"#).unwrap();
+    let overlay: Overlay = Overlay::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let name: String = overlay.name.clone();
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs::default());
+    fradar_data.overlays = Arc::new(vec![overlay]);
+
+    assert_eq!(execute(&mut fradar_data, "layer rings").unwrap(), "rings off");
+    assert_eq!(execute(&mut fradar_data, "layer rings on").unwrap(), "rings on");
+    assert_eq!(execute(&mut fradar_data, &format!("layer {}", name)).unwrap(), format!("{} off", name));
+    assert!(fradar_data.ui.layers.hidden_overlays.contains(&name));
+    assert_eq!(execute(&mut fradar_data, &format!("layer {} on", name)).unwrap(), format!("{} on", name));
+    assert!(fradar_data.ui.layers.hidden_overlays.is_empty());
+
+    let error: String = execute(&mut fradar_data, "layer coastline").unwrap_err().to_string();
+    assert!(error.starts_with("unknown layer 'coastline'") && error.ends_with(&format!("fences, {})", name)), "{}", error);
+  }
+
+  #[test]
+  fn keeps_the_filter_on_a_parse_error() {
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs::default());
+    execute(&mut fradar_data, "filter gs > 100").unwrap();
+
+    let error: String = execute(&mut fradar_data, "filter gs >").unwrap_err().to_string();
+    assert!(error.contains("column"), "{}", error);
+    assert!(fradar_data.filter.is_some());
+    assert!(execute(&mut fradar_data, "filter nonsense_field > 1").is_err());
+  }
+
+  #[test]
+  fn sets_trail_and_leader_lengths() {
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs { data_interval: Duration::from_secs(1), history_rolling_limit: 10, ..Default::default() });
+    assert_eq!(execute(&mut fradar_data, "set leaders 2m").unwrap(), "leaders 120s");
+    assert_eq!(fradar_data.args.leader_duration, Duration::from_secs(120));
+    assert_eq!(execute(&mut fradar_data, "set leaders off").unwrap(), "leaders off");
+    assert!(fradar_data.args.leader_duration.is_zero());
+    assert!(execute(&mut fradar_data, "set leaders soon").is_err());
+
+    assert_eq!(execute(&mut fradar_data, "set trails 1m").unwrap(), "trails 60s");
+    assert_eq!(fradar_data.args.history_rolling_limit, 61);
+  }
+}
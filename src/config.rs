@@ -1,2 +1,917 @@
+use std::{collections::BTreeMap, net::SocketAddr, path::{Path, PathBuf}, str::FromStr, time::Duration};
 
-// TODO: make CLI parser, make log function
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand};
+use serde::Deserialize;
+
+use crate::{bookmarks::Bookmark, conflicts::parse_separation, filter::Filter, geofence::{FenceShape, Geofence}, model::{CoordFormat, DataSource, FRadarArgs, FRadarUi, Position, Theme, Units, MILES_PER_NM}, server::parse_address, session::{load_view, state_dir}};
+
+
+/// Track nearby flights from the command line.
+///
+/// Settings are layered: built-in defaults, then the top level of the config file,
+/// then the selected `[profiles.<name>]` table, then the saved view (with `--resume`),
+/// then these flags.
+#[derive(Debug, Default, Clone, Parser)]
+#[command(version, about)]
+pub struct Cli {
+  /// Config file to read instead of ~/.config/fradar/config.toml
+  #[arg(long, value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Named profile from the config file's [profiles.<name>] tables
+  #[arg(long, short)]
+  pub profile: Option<String>,
+
+  /// Center of the scope, as "LAT,LONG"
+  #[arg(long, value_name = "LAT,LONG", allow_hyphen_values = true)]
+  pub origin: Option<String>,
+
+  /// Visible radius, e.g. 50, 50mi, 40nm or 80km
+  #[arg(long, short, value_name = "DISTANCE")]
+  pub radius: Option<String>,
+
+  /// Time between API requests, e.g. 1s or 500ms
+  #[arg(long, value_name = "DURATION")]
+  pub data_interval: Option<String>,
+
+  /// Frames drawn per second
+  #[arg(long)]
+  pub fps: Option<f64>,
+
+  /// Display units: imperial, nautical or metric
+  #[arg(long, short)]
+  pub units: Option<String>,
+
+  /// Data source: adsb.lol, airplanes.live or adsb.one
+  #[arg(long, short)]
+  pub source: Option<String>,
+
+  /// Color theme: classic, phosphor, amber or high-contrast
+  #[arg(long, short)]
+  pub theme: Option<String>,
+
+  /// Coordinate format: dms, decimal or maidenhead
+  #[arg(long)]
+  pub coords: Option<String>,
+
+  /// Only show aircraft matching this filter expression
+  #[arg(long, short, value_name = "EXPR")]
+  pub filter: Option<String>,
+
+  /// Length of position trails, e.g. 60s (0 to disable)
+  #[arg(long, value_name = "DURATION")]
+  pub trails: Option<String>,
+
+  /// Length of velocity leaders, e.g. 2m (0 to disable)
+  #[arg(long, value_name = "DURATION")]
+  pub leaders: Option<String>,
+
+  /// How far ahead to predict conflicts between aircraft, e.g. 2m (0 to disable)
+  #[arg(long, value_name = "DURATION")]
+  pub conflicts: Option<String>,
+
+  /// Separation minima for conflicts, lateral/vertical in feet, e.g. 3nm/1000
+  #[arg(long, value_name = "LATERAL/VERTICAL")]
+  pub separation: Option<String>,
+
+  /// Ground elevation at the origin, e.g. 120ft or 35m, for spotter mode look angles
+  #[arg(long, value_name = "ALTITUDE")]
+  pub elevation: Option<String>,
+
+  /// Open the spotter panel: where to look for each aircraft from the origin
+  #[arg(long)]
+  pub spotter: bool,
+
+  /// Altitudes whose crossing the ticker reports, e.g. 3000,10000ft
+  #[arg(long, value_name = "ALTITUDES", value_delimiter = ',')]
+  pub altitude_thresholds: Vec<String>,
+
+  /// OurAirports airports.csv to draw airports from
+  #[arg(long, value_name = "PATH")]
+  pub airports: Option<PathBuf>,
+
+  /// OurAirports runways.csv to draw runways from (needs --airports)
+  #[arg(long, value_name = "PATH")]
+  pub runways: Option<PathBuf>,
+
+  /// GeoJSON file to draw as a map overlay (repeatable)
+  #[arg(long = "overlay", value_name = "PATH")]
+  pub overlays: Vec<PathBuf>,
+
+  /// OpenAir file to draw airspace from (repeatable)
+  #[arg(long = "airspace", value_name = "PATH")]
+  pub airspace: Vec<PathBuf>,
+
+  /// Watchlist of hex codes, registrations, callsigns, types and flags to alert on
+  #[arg(long, value_name = "PATH")]
+  pub watchlist: Option<PathBuf>,
+
+  /// Shell command to run on geofence events (details in FRADAR_* variables)
+  #[arg(long, value_name = "COMMAND")]
+  pub fence_hook: Option<String>,
+
+  /// SQLite database to log every sighting to
+  #[arg(long, value_name = "PATH", global = true)]
+  pub logbook: Option<PathBuf>,
+
+  /// File to log alerts to (default: alerts.log in the state directory)
+  #[arg(long, value_name = "PATH")]
+  pub alert_log: Option<PathBuf>,
+
+  /// Serve /aircraft, /events and /health over HTTP on a port or address (default 127.0.0.1:8787)
+  #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "127.0.0.1:8787")]
+  pub serve: Option<String>,
+
+  /// Restore the view saved when fradar last exited
+  #[arg(long)]
+  pub resume: bool,
+
+  /// Print one snapshot of the aircraft in range and exit, without taking over the terminal
+  #[arg(long)]
+  pub once: bool,
+
+  /// Output format for --once: table, json or csv
+  #[arg(long, value_name = "FORMAT", requires = "once")]
+  pub format: Option<String>,
+
+  /// Print one line per event instead of drawing the scope (the default when stdout isn't a
+  /// terminal). The logbook, alert log and geofences still run
+  #[arg(long, conflicts_with = "once")]
+  pub ticker: bool,
+
+  #[command(subcommand)]
+  pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CliCommand {
+  /// List overflights of a point from the logbook or recordings, instead of opening the scope
+  Report(ReportArgs),
+}
+
+#[derive(Debug, Default, Clone, Args)]
+pub struct ReportArgs {
+  /// Point to report on, as "LAT,LONG" (default: the configured origin)
+  #[arg(long, value_name = "LAT,LONG", allow_hyphen_values = true)]
+  pub origin: Option<String>,
+
+  /// Count aircraft passing within this distance, e.g. 1nm (the default) or 2km
+  #[arg(long, value_name = "DISTANCE")]
+  pub within: Option<String>,
+
+  /// Only count aircraft below this altitude at their closest, e.g. 3000ft or 900m
+  #[arg(long, value_name = "ALTITUDE")]
+  pub below: Option<String>,
+
+  /// Start of the period (local time): YYYY-MM-DD, "YYYY-MM-DD HH:MM", today or yesterday
+  #[arg(long, value_name = "TIME")]
+  pub since: Option<String>,
+
+  /// End of the period (local time); a bare date includes the whole day
+  #[arg(long, value_name = "TIME")]
+  pub until: Option<String>,
+
+  /// One row per overflight, or totals per hour or day: flight, hour or day
+  #[arg(long, value_name = "GROUPING")]
+  pub by: Option<String>,
+
+  /// Output format: markdown or csv
+  #[arg(long, value_name = "FORMAT")]
+  pub format: Option<String>,
+
+  /// JSON-lines recordings (from `:record`) to read instead of the logbook (repeatable)
+  #[arg(long = "recording", value_name = "PATH")]
+  pub recordings: Vec<PathBuf>,
+}
+
+/// Everything resolved from defaults, the config file and the command line.
+#[derive(Debug, Clone)]
+pub struct Settings {
+  pub args: FRadarArgs,
+  pub filter: Option<Filter>,
+  pub ui: FRadarUi,
+
+  pub bookmarks: Vec<Bookmark>,
+  pub config_path: Option<PathBuf>,
+
+  pub airports_path: Option<PathBuf>,
+  pub runways_path: Option<PathBuf>,
+  pub overlay_paths: Vec<PathBuf>,
+  pub airspace_paths: Vec<PathBuf>,
+  pub watchlist_path: Option<PathBuf>,
+  pub fences: Vec<Geofence>,
+  pub fence_hook: Option<String>,
+  pub alert_log_path: Option<PathBuf>,
+  pub logbook_path: Option<PathBuf>,
+  pub altitude_thresholds: Vec<f64>,
+  pub serve_address: Option<SocketAddr>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    let sfo: Position = Position {
+      lat: 37.6191,
+      long: -122.3816,
+    };
+
+    Settings {
+      args: FRadarArgs {
+        origin: sfo,
+        radius: 50.0,
+
+        starting_origin: sfo,
+
+        data_interval: Duration::from_millis(1000),
+        frame_interval: Duration::from_millis((1.0 / 10.0 * 1000.0) as u64),
+        event_interval: Duration::from_millis(100),
+
+        terminal_cols: 0,
+        terminal_rows: 0,
+
+        terminal_edge_margins: 3,
+
+        label_label_repelling_force: 4.0,
+        label_point_repelling_force: 4.0,
+        label_snapping_radius: 2.0,
+
+        history_rolling_limit: 20,
+        trail_duration: Duration::ZERO,
+        leader_duration: Duration::ZERO,
+
+        conflict_horizon: Duration::ZERO,
+        conflict_lateral: 3.0 * MILES_PER_NM,
+        conflict_vertical: 1000.0,
+
+        observer_elevation: 0.0,
+
+        units: Units::default(),
+        source: DataSource::default(),
+        theme: Theme::default(),
+        coords: CoordFormat::default(),
+      },
+      filter: None,
+      ui: FRadarUi::default(),
+
+      bookmarks: Vec::new(),
+      config_path: default_config_path(),
+
+      airports_path: None,
+      runways_path: None,
+      overlay_paths: Vec::new(),
+      airspace_paths: Vec::new(),
+      watchlist_path: None,
+      fences: Vec::new(),
+      fence_hook: None,
+      alert_log_path: state_dir().map(|state| state.join("alerts.log")),
+      logbook_path: None,
+      altitude_thresholds: Vec::new(),
+      serve_address: None,
+    }
+  }
+}
+
+/// One layer of settings, as found at the top level of the config file or in a profile.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+  pub origin: Option<OriginValue>,
+  pub radius: Option<NumberOrString>,
+  pub data_interval: Option<NumberOrString>,
+  pub fps: Option<f64>,
+  pub units: Option<String>,
+  pub source: Option<String>,
+  pub theme: Option<String>,
+  pub coords: Option<String>,
+  pub filter: Option<String>,
+  pub trails: Option<NumberOrString>,
+  pub leaders: Option<NumberOrString>,
+  pub conflicts: Option<NumberOrString>,
+  pub separation: Option<String>,
+  pub elevation: Option<NumberOrString>,
+  pub spotter: Option<bool>,
+  pub altitude_thresholds: Option<Vec<NumberOrString>>,
+  pub history: Option<usize>,
+  pub margins: Option<u16>,
+  pub label_label_repelling_force: Option<f64>,
+  pub label_point_repelling_force: Option<f64>,
+  pub label_snapping_radius: Option<f64>,
+  pub airports: Option<PathBuf>,
+  pub runways: Option<PathBuf>,
+  pub overlays: Option<Vec<PathBuf>>,
+  pub airspace: Option<Vec<PathBuf>>,
+  pub watchlist: Option<PathBuf>,
+  pub fence_hook: Option<String>,
+  pub alert_log: Option<PathBuf>,
+  pub logbook: Option<PathBuf>,
+  pub serve: Option<NumberOrString>,
+
+  pub profiles: Option<BTreeMap<String, ConfigLayer>>,
+  pub bookmarks: Option<Vec<BookmarkEntry>>,
+  pub fences: Option<Vec<FenceEntry>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BookmarkEntry {
+  pub name: String,
+  pub origin: OriginValue,
+  pub radius: Option<NumberOrString>,
+}
+
+/// A geofence: either `center` and `radius`, or a list of `points`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FenceEntry {
+  pub name: String,
+  pub center: Option<OriginValue>,
+  pub radius: Option<NumberOrString>,
+  pub points: Option<Vec<OriginValue>>,
+  pub floor: Option<f64>,
+  pub ceiling: Option<f64>,
+  pub dwell: Option<NumberOrString>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OriginValue {
+  Pair([f64; 2]),
+  Table { lat: f64, long: f64 },
+  Text(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum NumberOrString {
+  Number(f64),
+  Text(String),
+}
+
+impl NumberOrString {
+  fn as_text(&self) -> String {
+    match self {
+      NumberOrString::Number(n) => n.to_string(),
+      NumberOrString::Text(s) => s.clone(),
+    }
+  }
+}
+
+/// Where a layer came from, so validation errors can name the offending key.
+enum LayerSource<'a> {
+  File(&'a Path),
+  Profile(&'a Path, &'a str),
+  CommandLine,
+}
+
+impl LayerSource<'_> {
+  fn key(&self, key: &str) -> String {
+    match self {
+      LayerSource::File(path) => format!("{}: key `{}`", path.display(), key),
+      LayerSource::Profile(path, profile) => format!("{}: key `profiles.{}.{}`", path.display(), profile, key),
+      LayerSource::CommandLine if key == "overlays" => "--overlay".to_string(),
+      LayerSource::CommandLine => format!("--{}", key.replace('_', "-")),
+    }
+  }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+  std::env::var_os("XDG_CONFIG_HOME")
+    .map(PathBuf::from)
+    .filter(|path| path.is_absolute())
+    .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+    .map(|config| config.join("fradar").join("config.toml"))
+}
+
+pub fn read_config_file(path: &Path) -> anyhow::Result<ConfigLayer> {
+  let contents: String = std::fs::read_to_string(path)
+    .with_context(|| format!("failed to read {}", path.display()))?;
+  toml::from_str(&contents)
+    .map_err(|error| anyhow!("{}: {}", path.display(), error))
+}
+
+/// Resolves the final settings from defaults, the config file, the chosen profile and the CLI.
+pub fn load_settings(cli: &Cli) -> anyhow::Result<Settings> {
+  let mut settings: Settings = Settings::default();
+
+  let explicit_path: bool = cli.config.is_some();
+  let path: Option<PathBuf> = cli.config.clone().or_else(default_config_path);
+  let file: Option<ConfigLayer> = match &path {
+    Some(path) if path.exists() => Some(read_config_file(path)?),
+    Some(path) if explicit_path => bail!("config file {} does not exist", path.display()),
+    _ => None,
+  };
+
+  settings.config_path = path.clone();
+
+  if let (Some(path), Some(file)) = (&path, &file) {
+    apply_layer(&mut settings, file, &LayerSource::File(path))?;
+
+    if let Some(profiles) = &file.profiles {
+      for (name, profile) in profiles.iter() {
+        if profile.profiles.is_some() {
+          bail!("{}: profiles can't be nested", LayerSource::Profile(path, name).key("profiles"));
+        }
+        if profile.bookmarks.is_some() {
+          bail!("{}: bookmarks are only allowed at the top level", LayerSource::Profile(path, name).key("bookmarks"));
+        }
+        if profile.fences.is_some() {
+          bail!("{}: fences are only allowed at the top level", LayerSource::Profile(path, name).key("fences"));
+        }
+      }
+    }
+
+    for (index, entry) in file.bookmarks.iter().flatten().enumerate() {
+      let key = |field: &str| LayerSource::File(path).key(&format!("bookmarks[{}].{}", index, field));
+      let origin: Position = parse_origin(&entry.origin).map_err(|error| anyhow!("{}: {}", key("origin"), error))?;
+      let radius: f64 = match &entry.radius {
+        Some(radius) => parse_distance_miles(&radius.as_text()).map_err(|error| anyhow!("{}: {}", key("radius"), error))?,
+        None => settings.args.radius,
+      };
+      if radius <= 0.0 {
+        bail!("{}: radius must be positive", key("radius"));
+      }
+      settings.bookmarks.push(Bookmark { name: entry.name.clone(), origin, radius });
+    }
+
+    for (index, entry) in file.fences.iter().flatten().enumerate() {
+      let key = |field: &str| LayerSource::File(path).key(&format!("fences[{}].{}", index, field));
+      settings.fences.push(parse_fence(entry, key)?);
+    }
+  }
+
+  if let Some(profile) = &cli.profile {
+    let profiles: Option<&BTreeMap<String, ConfigLayer>> = file.as_ref().and_then(|file| file.profiles.as_ref());
+    let layer: &ConfigLayer = profiles.and_then(|profiles| profiles.get(profile))
+      .ok_or_else(|| {
+        let available: Vec<String> = profiles.map(|profiles| profiles.keys().cloned().collect()).unwrap_or_default();
+        match available.is_empty() {
+          true => anyhow!("unknown profile '{}' (no profiles defined)", profile),
+          false => anyhow!("unknown profile '{}' (available: {})", profile, available.join(", ")),
+        }
+      })?;
+    apply_layer(&mut settings, layer, &LayerSource::Profile(path.as_deref().unwrap_or(Path::new("config")), profile))?;
+  }
+
+  if cli.resume {
+    load_view()?.apply(&mut settings)?;
+  }
+
+  apply_layer(&mut settings, &cli_layer(cli), &LayerSource::CommandLine)?;
+
+  // Only now are both the trail length and the data interval final.
+  settings.args.fit_history_to_trail();
+
+  Ok(settings)
+}
+
+fn cli_layer(cli: &Cli) -> ConfigLayer {
+  ConfigLayer {
+    origin: cli.origin.clone().map(OriginValue::Text),
+    radius: cli.radius.clone().map(NumberOrString::Text),
+    data_interval: cli.data_interval.clone().map(NumberOrString::Text),
+    fps: cli.fps,
+    units: cli.units.clone(),
+    source: cli.source.clone(),
+    theme: cli.theme.clone(),
+    coords: cli.coords.clone(),
+    filter: cli.filter.clone(),
+    trails: cli.trails.clone().map(NumberOrString::Text),
+    leaders: cli.leaders.clone().map(NumberOrString::Text),
+    conflicts: cli.conflicts.clone().map(NumberOrString::Text),
+    separation: cli.separation.clone(),
+    elevation: cli.elevation.clone().map(NumberOrString::Text),
+    spotter: Some(true).filter(|_| cli.spotter),
+    altitude_thresholds: Some(cli.altitude_thresholds.iter().cloned().map(NumberOrString::Text).collect()).filter(|thresholds: &Vec<NumberOrString>| !thresholds.is_empty()),
+    airports: cli.airports.clone(),
+    runways: cli.runways.clone(),
+    overlays: Some(cli.overlays.clone()).filter(|overlays| !overlays.is_empty()),
+    airspace: Some(cli.airspace.clone()).filter(|airspace| !airspace.is_empty()),
+    watchlist: cli.watchlist.clone(),
+    fence_hook: cli.fence_hook.clone(),
+    alert_log: cli.alert_log.clone(),
+    logbook: cli.logbook.clone(),
+    serve: cli.serve.clone().map(NumberOrString::Text),
+    ..ConfigLayer::default()
+  }
+}
+
+fn apply_layer(settings: &mut Settings, layer: &ConfigLayer, source: &LayerSource) -> anyhow::Result<()> {
+  let args: &mut FRadarArgs = &mut settings.args;
+
+  if let Some(origin) = &layer.origin {
+    args.origin = parse_origin(origin).map_err(|error| anyhow!("{}: {}", source.key("origin"), error))?;
+    args.starting_origin = args.origin;
+  }
+
+  if let Some(radius) = &layer.radius {
+    let miles: f64 = parse_distance_miles(&radius.as_text()).map_err(|error| anyhow!("{}: {}", source.key("radius"), error))?;
+    if miles <= 0.0 {
+      bail!("{}: radius must be positive", source.key("radius"));
+    }
+    args.radius = miles;
+  }
+
+  if let Some(interval) = &layer.data_interval {
+    let interval: Duration = parse_duration(&interval.as_text()).map_err(|error| anyhow!("{}: {}", source.key("data_interval"), error))?;
+    if interval.is_zero() {
+      bail!("{}: interval must be positive", source.key("data_interval"));
+    }
+    args.data_interval = interval;
+  }
+
+  if let Some(fps) = layer.fps {
+    if !(fps > 0.0 && fps <= 120.0) {
+      bail!("{}: expected a frame rate between 0 and 120, got {}", source.key("fps"), fps);
+    }
+    args.frame_interval = Duration::from_secs_f64(1.0 / fps);
+  }
+
+  if let Some(units) = &layer.units {
+    args.units = units.parse().map_err(|error| anyhow!("{}: {}", source.key("units"), error))?;
+  }
+
+  if let Some(data_source) = &layer.source {
+    args.source = data_source.parse().map_err(|error| anyhow!("{}: {}", source.key("source"), error))?;
+  }
+
+  if let Some(theme) = &layer.theme {
+    args.theme = theme.parse().map_err(|error| anyhow!("{}: {}", source.key("theme"), error))?;
+  }
+
+  if let Some(coords) = &layer.coords {
+    args.coords = coords.parse().map_err(|error| anyhow!("{}: {}", source.key("coords"), error))?;
+  }
+
+  if let Some(filter) = &layer.filter {
+    settings.filter = match filter.trim() {
+      "" => None,
+      filter => Some(Filter::parse(filter).map_err(|error| anyhow!("{}: {}", source.key("filter"), error.pretty(filter)))?),
+    };
+  }
+
+  for (key, value, target) in [
+    ("airports", &layer.airports, &mut settings.airports_path),
+    ("runways", &layer.runways, &mut settings.runways_path),
+    ("watchlist", &layer.watchlist, &mut settings.watchlist_path),
+  ] {
+    if let Some(path) = value {
+      let path: PathBuf = expand_home(path);
+      if !path.is_file() {
+        bail!("{}: no such file {}", source.key(key), path.display());
+      }
+      *target = Some(path);
+    }
+  }
+
+  for (key, value, target) in [
+    ("overlays", &layer.overlays, &mut settings.overlay_paths),
+    ("airspace", &layer.airspace, &mut settings.airspace_paths),
+  ] {
+    if let Some(paths) = value {
+      *target = paths.iter()
+        .map(|path| expand_home(path))
+        .map(|path| match path.is_file() {
+          true => Ok(path),
+          false => Err(anyhow!("{}: no such file {}", source.key(key), path.display())),
+        })
+        .collect::<anyhow::Result<Vec<PathBuf>>>()?;
+    }
+  }
+
+  if let Some(hook) = &layer.fence_hook {
+    settings.fence_hook = Some(hook.trim().to_string()).filter(|hook| !hook.is_empty());
+  }
+
+  // An empty path turns alert logging off.
+  if let Some(path) = &layer.alert_log {
+    settings.alert_log_path = Some(expand_home(path)).filter(|path| !path.as_os_str().is_empty());
+  }
+
+  if let Some(path) = &layer.logbook {
+    settings.logbook_path = Some(expand_home(path)).filter(|path| !path.as_os_str().is_empty());
+  }
+
+  // An empty address turns the server off.
+  if let Some(serve) = &layer.serve {
+    settings.serve_address = match serve.as_text().trim() {
+      "" => None,
+      address => Some(parse_address(address).map_err(|error| anyhow!("{}: {}", source.key("serve"), error))?),
+    };
+  }
+
+  if let Some(spotter) = layer.spotter {
+    settings.ui.spotter = spotter;
+  }
+
+  if let Some(thresholds) = &layer.altitude_thresholds {
+    settings.altitude_thresholds = thresholds.iter()
+      .map(|threshold| parse_altitude_feet(&threshold.as_text()).map_err(|error| anyhow!("{}: {}", source.key("altitude_thresholds"), error)))
+      .collect::<anyhow::Result<Vec<f64>>>()?;
+    settings.altitude_thresholds.sort_by(f64::total_cmp);
+    settings.altitude_thresholds.dedup();
+  }
+
+  let args: &mut FRadarArgs = &mut settings.args;
+
+  if let Some(elevation) = &layer.elevation {
+    args.observer_elevation = parse_altitude_feet(&elevation.as_text()).map_err(|error| anyhow!("{}: {}", source.key("elevation"), error))?;
+  }
+
+  if let Some(history) = layer.history {
+    args.history_rolling_limit = history;
+  }
+
+  if let Some(trails) = &layer.trails {
+    args.trail_duration = parse_duration(&trails.as_text()).map_err(|error| anyhow!("{}: {}", source.key("trails"), error))?;
+  }
+
+  if let Some(leaders) = &layer.leaders {
+    args.leader_duration = parse_duration(&leaders.as_text()).map_err(|error| anyhow!("{}: {}", source.key("leaders"), error))?;
+  }
+
+  if let Some(conflicts) = &layer.conflicts {
+    args.conflict_horizon = parse_duration(&conflicts.as_text()).map_err(|error| anyhow!("{}: {}", source.key("conflicts"), error))?;
+  }
+
+  if let Some(separation) = &layer.separation {
+    (args.conflict_lateral, args.conflict_vertical) = parse_separation(separation).map_err(|error| anyhow!("{}: {}", source.key("separation"), error))?;
+  }
+
+  if let Some(margins) = layer.margins {
+    args.terminal_edge_margins = margins;
+  }
+
+  for (key, value, target) in [
+    ("label_label_repelling_force", layer.label_label_repelling_force, &mut args.label_label_repelling_force),
+    ("label_point_repelling_force", layer.label_point_repelling_force, &mut args.label_point_repelling_force),
+    ("label_snapping_radius", layer.label_snapping_radius, &mut args.label_snapping_radius),
+  ] {
+    if let Some(value) = value {
+      if !value.is_finite() || value < 0.0 {
+        bail!("{}: must be a non-negative number", source.key(key));
+      }
+      *target = value;
+    }
+  }
+
+  Ok(())
+}
+
+/// Expands a leading `~/` so paths in the config file can be written relative to home.
+pub fn expand_home(path: &Path) -> PathBuf {
+  match (path.strip_prefix("~"), dirs::home_dir()) {
+    (Ok(rest), Some(home)) => home.join(rest),
+    _ => path.to_path_buf(),
+  }
+}
+
+fn parse_fence(entry: &FenceEntry, key: impl Fn(&str) -> String) -> anyhow::Result<Geofence> {
+  let shape: FenceShape = match (&entry.center, &entry.radius, &entry.points) {
+    (Some(center), Some(radius), None) => {
+      let center: Position = parse_origin(center).map_err(|error| anyhow!("{}: {}", key("center"), error))?;
+      let radius_miles: f64 = parse_distance_miles(&radius.as_text()).map_err(|error| anyhow!("{}: {}", key("radius"), error))?;
+      if radius_miles <= 0.0 {
+        bail!("{}: radius must be positive", key("radius"));
+      }
+      FenceShape::Circle { center, radius_miles }
+    },
+    (None, None, Some(points)) => {
+      let points: Vec<Position> = points.iter()
+        .map(parse_origin)
+        .collect::<anyhow::Result<Vec<Position>>>()
+        .map_err(|error| anyhow!("{}: {}", key("points"), error))?;
+      if points.len() < 3 {
+        bail!("{}: a polygon needs at least 3 points", key("points"));
+      }
+      FenceShape::Polygon(points)
+    },
+    _ => bail!("{}: give either `center` and `radius`, or `points`", key("name")),
+  };
+
+  if let (Some(floor), Some(ceiling)) = (entry.floor, entry.ceiling) && floor > ceiling {
+    bail!("{}: floor {} is above ceiling {}", key("ceiling"), floor, ceiling);
+  }
+  let dwell: Option<Duration> = match &entry.dwell {
+    Some(dwell) => Some(parse_duration(&dwell.as_text()).map_err(|error| anyhow!("{}: {}", key("dwell"), error))?),
+    None => None,
+  };
+
+  Ok(Geofence { name: entry.name.clone(), shape, floor: entry.floor, ceiling: entry.ceiling, dwell: dwell.filter(|dwell| !dwell.is_zero()) })
+}
+
+pub fn parse_origin(origin: &OriginValue) -> anyhow::Result<Position> {
+  let (lat, long): (f64, f64) = match origin {
+    OriginValue::Pair([lat, long]) => (*lat, *long),
+    OriginValue::Table { lat, long } => (*lat, *long),
+    OriginValue::Text(text) => {
+      let (lat, long) = text.split_once([',', ' '])
+        .ok_or_else(|| anyhow!("expected \"LAT,LONG\", got '{}'", text))?;
+      (
+        lat.trim().parse().map_err(|_| anyhow!("invalid latitude '{}'", lat.trim()))?,
+        long.trim().parse().map_err(|_| anyhow!("invalid longitude '{}'", long.trim()))?,
+      )
+    },
+  };
+
+  if !(-90.0..=90.0).contains(&lat) {
+    bail!("latitude {} out of range", lat);
+  }
+  if !(-180.0..=180.0).contains(&long) {
+    bail!("longitude {} out of range", long);
+  }
+  Ok(Position { lat, long })
+}
+
+/// Parses an altitude such as `120ft`, `35m` or `120` (feet) into feet.
+pub fn parse_altitude_feet(value: &str) -> anyhow::Result<f64> {
+  let value: String = value.trim().to_lowercase();
+  let split: usize = value.find(|c: char| c.is_alphabetic()).unwrap_or(value.len());
+  let (number, unit) = value.split_at(split);
+  let number: f64 = number.trim().parse().map_err(|_| anyhow!("invalid altitude '{}'", value))?;
+
+  match unit {
+    "" | "ft" => Ok(number),
+    "m" => Ok(number / 0.3048),
+    _ => bail!("unknown altitude unit '{}' (expected ft or m)", unit),
+  }
+}
+
+/// Parses a distance such as `20nm`, `15km` or `30mi` (the default unit) into statute miles.
+pub fn parse_distance_miles(value: &str) -> anyhow::Result<f64> {
+  let value: String = value.trim().to_lowercase();
+  let split: usize = value.find(|c: char| c.is_alphabetic()).unwrap_or(value.len());
+  let (number, unit) = value.split_at(split);
+  let number: f64 = number.trim().parse().map_err(|_| anyhow!("invalid distance '{}'", value))?;
+
+  match unit {
+    "" | "mi" => Ok(number),
+    "nm" => Ok(number * MILES_PER_NM),
+    "km" => Ok(number * 0.621371),
+    _ => bail!("unknown distance unit '{}' (expected mi, nm or km)", unit),
+  }
+}
+
+/// Parses a duration such as `60s`, `5m`, `1h` or `500ms`; a bare number is taken as seconds.
+pub fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+  let value: String = value.trim().to_lowercase();
+  let split: usize = value.find(|c: char| c.is_alphabetic()).unwrap_or(value.len());
+  let (number, unit) = value.split_at(split);
+  let number: f64 = number.trim().parse().map_err(|_| anyhow!("invalid duration '{}'", value))?;
+  if !number.is_finite() || number < 0.0 {
+    bail!("duration can't be negative");
+  }
+
+  let seconds: f64 = match unit {
+    "ms" => number / 1000.0,
+    "" | "s" => number,
+    "m" | "min" => number * 60.0,
+    "h" => number * 3600.0,
+    _ => bail!("unknown duration unit '{}' (expected ms, s, m or h)", unit),
+  };
+  Ok(Duration::from_secs_f64(seconds))
+}
+
+impl FromStr for Units {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "imperial" | "mi" => Ok(Units::Imperial),
+      "nautical" | "nm" => Ok(Units::Nautical),
+      "metric" | "km" => Ok(Units::Metric),
+      _ => bail!("unknown units '{}' (expected imperial, nautical or metric)", s),
+    }
+  }
+}
+
+impl FromStr for CoordFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "dms" => Ok(CoordFormat::Dms),
+      "decimal" | "dd" => Ok(CoordFormat::Decimal),
+      "maidenhead" | "grid" => Ok(CoordFormat::Maidenhead),
+      _ => bail!("unknown coordinate format '{}' (expected dms, decimal or maidenhead)", s),
+    }
+  }
+}
+
+impl FromStr for DataSource {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "adsb.lol" | "adsblol" => Ok(DataSource::AdsbLol),
+      "airplanes.live" | "airplaneslive" => Ok(DataSource::AirplanesLive),
+      "adsb.one" | "adsbone" => Ok(DataSource::AdsbOne),
+      _ => bail!("unknown source '{}' (expected adsb.lol, airplanes.live or adsb.one)", s),
+    }
+  }
+}
+
+impl FromStr for Theme {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "classic" | "default" => Ok(Theme::Classic),
+      "phosphor" | "green" => Ok(Theme::Phosphor),
+      "amber" => Ok(Theme::Amber),
+      "high-contrast" | "high_contrast" | "contrast" => Ok(Theme::HighContrast),
+      _ => bail!("unknown theme '{}' (expected classic, phosphor, amber or high-contrast)", s),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Loads settings from `config`, written to a file of its own, with `flags` on the command line.
+  fn load(name: &str, config: &str, flags: &[&str]) -> anyhow::Result<Settings> {
+    let path: PathBuf = std::env::temp_dir().join(format!("fradar-config-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, config).unwrap();
+    let cli: Cli = Cli::parse_from(["fradar", "--config", path.to_str().unwrap()].iter().chain(flags));
+    let settings: anyhow::Result<Settings> = load_settings(&cli);
+    let _ = std::fs::remove_file(&path);
+    settings
+  }
+
+  const CONFIG: &str = r#"
+    radius = 30
+    units = "nautical"
+    theme = "amber"
+
+    [profiles.home]
+    radius = "20nm"
+    theme = "phosphor"
+
+    [profiles.work]
+    origin = [47.45, -122.3]
+  "#;
+
+  #[test]
+  fn layers_file_then_profile_then_command_line() {
+    let settings: Settings = load("layers", CONFIG, &[]).unwrap();
+    assert_eq!(settings.args.radius, 30.0);
+    assert_eq!(settings.args.theme, Theme::Amber);
+
+    let settings: Settings = load("layers-profile", CONFIG, &["--profile", "home"]).unwrap();
+    assert_eq!(settings.args.radius, 20.0 * MILES_PER_NM);
+    assert_eq!(settings.args.units, Units::Nautical);
+    assert_eq!(settings.args.theme, Theme::Phosphor);
+
+    let settings: Settings = load("layers-cli", CONFIG, &["--profile", "home", "--theme", "high-contrast", "--radius", "5km"]).unwrap();
+    assert_eq!(settings.args.theme, Theme::HighContrast);
+    assert!((settings.args.radius - 5.0 * 0.621371).abs() < 1e-9);
+    assert_eq!(settings.args.units, Units::Nautical);
+  }
+
+  #[test]
+  fn selects_only_the_named_profile() {
+    let settings: Settings = load("profile", CONFIG, &["-p", "work"]).unwrap();
+    assert_eq!(settings.args.origin, Position { lat: 47.45, long: -122.3 });
+    assert_eq!(settings.args.starting_origin, settings.args.origin);
+    assert_eq!(settings.args.radius, 30.0);
+    assert_eq!(settings.args.theme, Theme::Amber);
+
+    let error: String = load("profile-unknown", CONFIG, &["--profile", "cabin"]).unwrap_err().to_string();
+    assert_eq!(error, "unknown profile 'cabin' (available: home, work)");
+    let error: String = load("profile-none", "radius = 30", &["--profile", "home"]).unwrap_err().to_string();
+    assert_eq!(error, "unknown profile 'home' (no profiles defined)");
+  }
+
+  #[test]
+  fn errors_name_the_offending_key() {
+    let error: String = load("error-file", "radius = \"far\"", &[]).unwrap_err().to_string();
+    assert!(error.ends_with(": key `radius`: invalid distance 'far'"), "{}", error);
+
+    let error: String = load("error-profile", "[profiles.work]\nunits = \"furlongs\"", &["--profile", "work"]).unwrap_err().to_string();
+    assert!(error.contains(": key `profiles.work.units`: unknown units 'furlongs'"), "{}", error);
+
+    let error: String = load("error-nested", "[profiles.work.profiles.home]\nradius = 5", &[]).unwrap_err().to_string();
+    assert!(error.contains("key `profiles.work.profiles`"), "{}", error);
+
+    let error: String = load("error-bookmark", "[[bookmarks]]\nname = \"home\"\norigin = \"47.45\"", &[]).unwrap_err().to_string();
+    assert!(error.contains("key `bookmarks[0].origin`"), "{}", error);
+
+    let error: String = load("error-cli", "", &["--data-interval", "0s"]).unwrap_err().to_string();
+    assert_eq!(error, "--data-interval: interval must be positive");
+
+    let error: String = load("error-unknown", "raduis = 30", &[]).unwrap_err().to_string();
+    assert!(error.contains("unknown field `raduis`"), "{}", error);
+  }
+
+  #[test]
+  fn trail_history_follows_the_final_data_interval() {
+    let settings: Settings = load("trails", "trails = \"60s\"", &[]).unwrap();
+    assert_eq!(settings.args.history_rolling_limit, 61);
+
+    // The interval set last decides how many frames the trail needs.
+    let settings: Settings = load("trails-interval", "trails = \"60s\"", &["--data-interval", "10s"]).unwrap();
+    assert_eq!(settings.args.history_rolling_limit, 20);
+    let settings: Settings = load("trails-later", "data_interval = \"10s\"", &["--trails", "60s", "--data-interval", "500ms"]).unwrap();
+    assert_eq!(settings.args.history_rolling_limit, 121);
+  }
+}
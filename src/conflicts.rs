@@ -0,0 +1,199 @@
+use anyhow::{anyhow, bail};
+
+use crate::{aircraft_list::altitude_feet, config::parse_distance_miles, model::{ADSBAircraftInformation, FRadarArgs, FlightData, Position, MILES_PER_NM}};
+
+
+/// A pair of aircraft predicted to come within the separation minima.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+  /// Indices into the `FlightData` the conflict was found in.
+  pub a: usize,
+  pub b: usize,
+  /// Time to the closest point of approach (zero if they are closest now).
+  pub seconds: f64,
+  pub lateral_miles: f64,
+  pub vertical_feet: f64,
+}
+
+/// Lateral and vertical separation minima, written `3nm/1000` (vertical in feet).
+pub fn parse_separation(value: &str) -> anyhow::Result<(f64, f64)> {
+  let (lateral, vertical) = value.split_once('/').ok_or_else(|| anyhow!("expected <lateral>/<vertical>, e.g. 3nm/1000, got '{}'", value))?;
+  let lateral: f64 = parse_distance_miles(lateral)?;
+  let vertical: f64 = vertical.trim().trim_end_matches("ft").parse().map_err(|_| anyhow!("invalid vertical separation '{}'", vertical.trim()))?;
+  if lateral <= 0.0 || vertical <= 0.0 {
+    bail!("separation must be positive");
+  }
+  Ok((lateral, vertical))
+}
+
+/// Where an aircraft is and where it is heading: east and north in knots, vertical in feet per minute.
+/// Aircraft without a track or ground speed can't be projected and get none.
+struct Track {
+  index: usize,
+  position: Position,
+  altitude: f64,
+  track: f64,
+  gs: f64,
+  east: f64,
+  north: f64,
+  vertical: f64,
+}
+
+impl Track {
+  fn new(index: usize, info: &ADSBAircraftInformation, position: Position) -> Option<Track> {
+    if info.is_on_ground() {
+      return None;
+    }
+    let altitude: f64 = altitude_feet(info)?;
+    let track: f64 = info.track? as f64;
+    let gs: f64 = info.gs? as f64;
+    Some(Track {
+      index,
+      position,
+      altitude,
+      track,
+      gs,
+      east: gs * track.to_radians().sin(),
+      north: gs * track.to_radians().cos(),
+      vertical: info.geom_rate.or(info.baro_rate).unwrap_or(0) as f64,
+    })
+  }
+
+  fn at(&self, hours: f64) -> (Position, f64) {
+    (self.position.destination(self.track, self.gs * MILES_PER_NM * hours), self.altitude + self.vertical * hours * 60.0)
+  }
+}
+
+/// Finds pairs predicted to be within `args.conflict_lateral` and `args.conflict_vertical` of
+/// each other inside `args.conflict_horizon`, assuming both hold their current velocity.
+///
+/// The time of closest approach is solved on a flat plane around the pair, then both
+/// aircraft are flown there along great circles to measure the actual miss distance.
+pub fn detect_conflicts(flights_data: &FlightData, args: &FRadarArgs) -> Vec<Conflict> {
+  let horizon_hours: f64 = args.conflict_horizon.as_secs_f64() / 3600.0;
+  if horizon_hours <= 0.0 {
+    return Vec::new();
+  }
+  let lateral_nm: f64 = args.conflict_lateral / MILES_PER_NM;
+
+  let tracks: Vec<Track> = flights_data.aircraft.iter().zip(flights_data.flights.iter()).enumerate()
+    .filter_map(|(index, (info, (position, _)))| Track::new(index, info, *position))
+    .collect();
+
+  let mut conflicts: Vec<Conflict> = Vec::new();
+  for (offset, a) in tracks.iter().enumerate() {
+    for b in tracks[offset + 1..].iter() {
+      // Rule out pairs that can't close the gap within the horizon, whichever way they fly.
+      let vertical_gap: f64 = (b.altitude - a.altitude).abs() - (a.vertical.abs() + b.vertical.abs()) * horizon_hours * 60.0;
+      if vertical_gap >= args.conflict_vertical {
+        continue;
+      }
+      let east: f64 = ((b.position.long - a.position.long + 540.0).rem_euclid(360.0) - 180.0) * 60.0 * a.position.lat.to_radians().cos();
+      let north: f64 = (b.position.lat - a.position.lat) * 60.0;
+      if east.hypot(north) - (a.gs + b.gs) * horizon_hours >= lateral_nm {
+        continue;
+      }
+
+      let (closing_east, closing_north) = (b.east - a.east, b.north - a.north);
+      let closing_squared: f64 = closing_east * closing_east + closing_north * closing_north;
+      let hours: f64 = match closing_squared > 0.0 {
+        true => (-(east * closing_east + north * closing_north) / closing_squared).clamp(0.0, horizon_hours),
+        false => 0.0,
+      };
+
+      let ((position_a, altitude_a), (position_b, altitude_b)) = (a.at(hours), b.at(hours));
+      let lateral_miles: f64 = position_a.distance_miles(&position_b);
+      let vertical_feet: f64 = (altitude_b - altitude_a).abs();
+      if lateral_miles < args.conflict_lateral && vertical_feet < args.conflict_vertical {
+        conflicts.push(Conflict { a: a.index, b: b.index, seconds: hours * 3600.0, lateral_miles, vertical_feet });
+      }
+    }
+  }
+
+  conflicts.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+  conflicts
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+  use crate::fixtures::{flights_data, flying};
+
+  /// An aircraft `east_nm` east and `north_nm` north of 0,0.
+  fn aircraft(east_nm: f64, north_nm: f64, altitude: i32, track: f32, gs: f32) -> ADSBAircraftInformation {
+    flying(&Position { lat: north_nm / 60.0, long: east_nm / 60.0 }, altitude, track, gs)
+  }
+
+  fn conflicts(aircraft: Vec<ADSBAircraftInformation>) -> Vec<Conflict> {
+    let flights_data: FlightData = flights_data(0, aircraft);
+    let args: FRadarArgs = FRadarArgs {
+      conflict_horizon: Duration::from_secs(120),
+      conflict_lateral: 3.0 * MILES_PER_NM,
+      conflict_vertical: 1000.0,
+      ..Default::default()
+    };
+    detect_conflicts(&flights_data, &args)
+  }
+
+  #[test]
+  fn head_on() {
+    let found: Vec<Conflict> = conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), aircraft(20.0, 0.0, 10000, 270.0, 400.0)]);
+    assert_eq!(found.len(), 1);
+    assert!((found[0].seconds - 90.0).abs() < 1.0, "{:?}", found[0]);
+    assert!(found[0].lateral_miles < 0.1);
+    assert_eq!(found[0].vertical_feet, 0.0);
+  }
+
+  #[test]
+  fn head_on_beyond_the_horizon() {
+    assert!(conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), aircraft(40.0, 0.0, 10000, 270.0, 400.0)]).is_empty());
+  }
+
+  #[test]
+  fn parallel() {
+    assert!(conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), aircraft(0.0, 5.0, 10000, 90.0, 400.0)]).is_empty());
+
+    let found: Vec<Conflict> = conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), aircraft(0.0, 2.0, 10000, 90.0, 400.0)]);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].seconds, 0.0);
+  }
+
+  #[test]
+  fn diverging() {
+    assert!(conflicts(vec![aircraft(0.0, 0.0, 10000, 270.0, 400.0), aircraft(4.0, 0.0, 10000, 90.0, 400.0)]).is_empty());
+  }
+
+  #[test]
+  fn vertically_separated() {
+    assert!(conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), aircraft(20.0, 0.0, 12000, 270.0, 400.0)]).is_empty());
+
+    // Descending towards the other aircraft, with only a barometric rate reported.
+    let descending: ADSBAircraftInformation = ADSBAircraftInformation { baro_rate: Some(-1000), ..aircraft(20.0, 0.0, 12000, 270.0, 400.0) };
+    let found: Vec<Conflict> = conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), descending]);
+    assert_eq!(found.len(), 1);
+    assert!((found[0].vertical_feet - 500.0).abs() < 20.0, "{:?}", found[0]);
+  }
+
+  #[test]
+  fn skips_aircraft_without_a_velocity() {
+    let still: ADSBAircraftInformation = ADSBAircraftInformation { track: None, ..aircraft(1.0, 0.0, 10000, 270.0, 400.0) };
+    assert!(conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), still]).is_empty());
+    let still: ADSBAircraftInformation = ADSBAircraftInformation { gs: None, ..aircraft(1.0, 0.0, 10000, 270.0, 400.0) };
+    assert!(conflicts(vec![aircraft(0.0, 0.0, 10000, 90.0, 400.0), still]).is_empty());
+  }
+
+  #[test]
+  fn parses_separation() {
+    assert_eq!(parse_separation("3nm/1000").unwrap(), (3.0 * MILES_PER_NM, 1000.0));
+    assert_eq!(parse_separation("5/500ft").unwrap(), (5.0, 500.0));
+    let (lateral, vertical) = parse_separation("10km/ 2000").unwrap();
+    assert!((lateral - 6.21371).abs() < 1e-6);
+    assert_eq!(vertical, 2000.0);
+
+    for value in ["3nm", "3nm/", "/1000", "3nm/fast", "3parsecs/1000", "0/1000", "3nm/-500"] {
+      assert!(parse_separation(value).is_err(), "'{}' parsed", value);
+    }
+  }
+}
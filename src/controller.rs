@@ -1,12 +1,13 @@
-use std::sync::{Arc, Mutex};
+use std::{fs::OpenOptions, io::Write, path::{Path, PathBuf}, sync::{Arc, Mutex}};
 
 use chrono::Utc;
 use tokio::time::{timeout, Instant};
 
-use crate::model::{ADSBData, FRadarArgs, FRadarData, FRadarState, FlightData, Label, Position};
+use crate::{alerts::{emergency_conditions, emergency_reason, log_event, write_log, AlertKind, Condition}, conflicts::{detect_conflicts, Conflict}, filter::Filter, geofence::{run_hook, FenceEvent, Geofences}, logbook::LogbookWriter, model::{ADSBAircraftInformation, ADSBData, FRadarArgs, FRadarData, FRadarState, FlightData, Label, Position}, server::{Server, ServerEvent}, ticker::{TickerEvent, Tracker}, watchlist::{watchlist_conditions, Watchlist}};
 
 
-pub async fn controller_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio::task::JoinHandle<anyhow::Result<()>> {
+/// `tracker` finds the events for the server's `/events`, and is only used when there is a server.
+pub async fn controller_thread(fradar_data: Arc<Mutex<FRadarData>>, mut logbook: Option<LogbookWriter>, server: Option<Server>, mut tracker: Tracker) -> tokio::task::JoinHandle<anyhow::Result<()>> {
   tokio::spawn(async move {
     let client = reqwest::Client::new();
     
@@ -15,12 +16,7 @@ pub async fn controller_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio::ta
 
       let args: FRadarArgs = fradar_data.lock().unwrap().args;
 
-      let url = format!("https://api.adsb.lol/v2/point/{:.4}/{:.4}/{}", args.origin.lat, args.origin.long, (args.radius as u32).min(250));
-      // let url = format!("https://api.adsb.lol/v2/point/{:.4}/{:.4}/{}", args.origin.lat, args.origin.long, 250);
-      let request_future = client
-        .get(url)
-        .header(reqwest::header::ACCEPT, "application/json")
-        .send();
+      let request_future = request(&client, &args).send();
 
       let result = match timeout(args.data_interval, request_future).await {
         Ok(res) => res?,
@@ -38,35 +34,69 @@ pub async fn controller_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio::ta
         // println!("[{:?}] Request successful.", Utc::now().time());
       }
 
-      let updated_adsb_data: ADSBData = result.json::<ADSBData>().await?;
-
-      let updated_adsb_position_data: Vec<Position> = updated_adsb_data.ac.clone()
-        .into_iter()
-        .map(Position::try_from)
-        .collect::<anyhow::Result<Vec<Position>>>()?;
-
-      let updated_adsb_label_data: Vec<Label> = updated_adsb_data.ac.clone()
-        .into_iter()
-        .map(Label::try_from)
-        .collect::<anyhow::Result<Vec<Label>>>()?;
-
-      let unified_data: Vec<(Position, Label)> = updated_adsb_position_data
-        .iter()
-        .zip(updated_adsb_label_data.iter())
-        .map(|(position, label)| (position.clone(), label.clone()))
-        .collect();
-      
-      let updated_flights_data: FlightData = FlightData { 
-        flights: unified_data,
-        epoch_timestamp: Utc::now().timestamp_millis(),
+      let mut updated_adsb_data: ADSBData = result.json::<ADSBData>().await?;
+
+      let recording: Option<String> = fradar_data.lock().unwrap().ui.recording.clone();
+      if let Some(path) = recording && let Err(error) = record_snapshot(&path, &updated_adsb_data) {
+        let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+        fradar_data_ref.ui.recording = None;
+        fradar_data_ref.ui.set_status(format!("error: recording stopped: {}", error));
+      }
+
+      // The logbook keeps everything, whatever the filter.
+      if let Some(writer) = logbook.as_mut() && let Err(error) = writer.record(updated_adsb_data.ac.clone(), args.starting_origin, Utc::now().timestamp()) {
+        logbook = None;
+        fradar_data.lock().unwrap().ui.set_status(format!("error: logbook stopped: {:#}", error));
+      }
+
+      let (filter, watchlist, mut geofences, log_path): (Option<Filter>, Arc<Watchlist>, Geofences, Option<PathBuf>) = {
+        let fradar_data_ref: &FRadarData = &fradar_data.lock().unwrap();
+        (fradar_data_ref.filter.clone(), fradar_data_ref.watchlist.clone(), fradar_data_ref.geofences.clone(), fradar_data_ref.alerts.log_path.clone())
       };
 
-      {
-        let flights_data: Arc<Mutex<FlightData>> = fradar_data.lock().unwrap().flights_data.clone();
-        let flights_data_ref: &mut FlightData = &mut flights_data.lock().unwrap();
-        *flights_data_ref = updated_flights_data;
+      // Fences and alerts see all the traffic; only the scope is filtered.
+      let all_flights_data: FlightData = flight_data(updated_adsb_data.ac.clone())?;
+      let events: Vec<FenceEvent> = geofences.update(&all_flights_data);
+      let emergencies: Vec<Condition> = emergency_conditions(&all_flights_data);
+      let watched: Vec<Condition> = watchlist_conditions(&watchlist, &all_flights_data);
+
+      // Emergencies and watched aircraft stay on the scope whatever the filter says.
+      if let Some(filter) = filter {
+        updated_adsb_data.ac.retain(|info| filter.matches(info) || emergency_reason(info).is_some() || watchlist.reason(info).is_some());
+      }
+      let updated_flights_data: FlightData = FlightData { epoch_timestamp: all_flights_data.epoch_timestamp, ..flight_data(updated_adsb_data.ac)? };
+      let conflicts: Vec<Conflict> = detect_conflicts(&updated_flights_data, &args);
+
+      if let Some(server) = &server {
+        let events: Vec<TickerEvent> = tracker.update(&updated_flights_data, &args, &watchlist);
+        server.update(&updated_flights_data, &args, &events);
+        if let Some(error) = server.take_failure() {
+          fradar_data.lock().unwrap().ui.set_status(format!("error: server stopped: {}", error));
+        }
       }
 
+      // Only swap the results in under the lock; the files and the hook are dealt with after.
+      let log: Vec<String> = {
+        let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+        let raised: Vec<String> = [
+          fradar_data_ref.alerts.sync(AlertKind::Emergency, emergencies, &all_flights_data),
+          fradar_data_ref.alerts.sync(AlertKind::Watchlist, watched, &all_flights_data),
+        ].into_iter().flatten().collect();
+        fradar_data_ref.geofences.adopt_visits(geofences.clone());
+
+        let messages: Vec<String> = raised.into_iter().chain(events.iter().map(FenceEvent::message)).collect();
+        if !messages.is_empty() {
+          fradar_data_ref.ui.set_status(messages.join(" · "));
+        }
+        fradar_data_ref.conflicts = conflicts;
+        fradar_data_ref.flights_data = Arc::new(Mutex::new(updated_flights_data));
+        fradar_data_ref.enqueue_data();
+        std::mem::take(&mut fradar_data_ref.alerts.pending_log)
+      };
+
+      write_log(log_path.as_deref(), &log);
+      report_fence_events(&events, &geofences, log_path.as_deref(), server.as_ref(), all_flights_data.epoch_timestamp);
+
       // TODO: revisit this logic, do we need to force data rate?
       let elapsed = start_time.elapsed();
       if elapsed < args.data_interval {
@@ -74,6 +104,69 @@ pub async fn controller_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio::ta
       }
     }
 
+    // Let the open sightings be closed before the process exits.
+    if let Some(writer) = logbook {
+      tokio::task::spawn_blocking(move || writer.finish()).await??;
+    }
+
     Ok(())
   })
 }
+
+/// Logs each fence event, runs the hook for it and announces it to `/events` clients.
+pub fn report_fence_events(events: &[FenceEvent], geofences: &Geofences, log_path: Option<&Path>, server: Option<&Server>, now_millis: i64) {
+  for event in events.iter() {
+    log_event(log_path, now_millis, event.kind.name(), "fence", &event.condition);
+    if let Some(hook) = &geofences.hook {
+      run_hook(hook, event);
+    }
+    if let Some(server) = server {
+      server.announce(ServerEvent::new(now_millis, "fence", &fence_condition(event)));
+    }
+  }
+}
+
+/// The event's aircraft, with `enter`, `exit` or `dwell` and the fence as its reason.
+pub fn fence_condition(event: &FenceEvent) -> Condition {
+  Condition { reason: format!("{} {}", event.kind.name().to_lowercase(), event.fence), ..event.condition.clone() }
+}
+
+/// The point query for the current origin and radius.
+pub fn request(client: &reqwest::Client, args: &FRadarArgs) -> reqwest::RequestBuilder {
+  let url = args.source.point_url(&args.origin, (args.radius as u32).min(250));
+  client
+    .get(url)
+    .header(reqwest::header::ACCEPT, "application/json")
+}
+
+/// Pairs each aircraft with its position and label, stamped with the current time.
+pub fn flight_data(aircraft: Vec<ADSBAircraftInformation>) -> anyhow::Result<FlightData> {
+  let updated_adsb_position_data: Vec<Position> = aircraft.clone()
+    .into_iter()
+    .map(Position::try_from)
+    .collect::<anyhow::Result<Vec<Position>>>()?;
+
+  let updated_adsb_label_data: Vec<Label> = aircraft.clone()
+    .into_iter()
+    .map(Label::try_from)
+    .collect::<anyhow::Result<Vec<Label>>>()?;
+
+  let unified_data: Vec<(Position, Label)> = updated_adsb_position_data
+    .iter()
+    .zip(updated_adsb_label_data.iter())
+    .map(|(position, label)| (*position, label.clone()))
+    .collect();
+
+  Ok(FlightData {
+    flights: unified_data,
+    aircraft,
+    epoch_timestamp: Utc::now().timestamp_millis(),
+  })
+}
+
+/// Appends one raw API response to a JSON-lines recording file.
+fn record_snapshot(path: &str, adsb_data: &ADSBData) -> anyhow::Result<()> {
+  let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+  writeln!(file, "{}", serde_json::to_string(adsb_data)?)?;
+  Ok(())
+}
@@ -1,8 +1,10 @@
 use std::sync::{Arc, Mutex};
 
-use crossterm::{event::{read, Event, KeyCode}, execute};
+use crossterm::{event::{read, Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind}, execute};
 
-use crate::model::{FRadarArgs, FRadarData, FRadarState, Position};
+use chrono::Utc;
+
+use crate::{command, aircraft_list::{list_area, scroll_to_selection, sorted_rows, visible_rows, ListRow}, model::{Coord, FRadarArgs, FRadarData, FRadarState, FlightData, ListMode, Position, Transition}, session::save_view};
 
 pub async fn event_dispatch_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio::task::JoinHandle<anyhow::Result<()>> {
   tokio::task::spawn_blocking(move || {
@@ -10,20 +12,75 @@ pub async fn event_dispatch_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio
       let args: FRadarArgs = fradar_data.lock().unwrap().args;
 
       match read()? {
+        Event::Key(key_event) if fradar_data.lock().unwrap().state == FRadarState::Search => {
+          match key_event.code {
+            KeyCode::Esc => end_search(fradar_data.clone(), false),
+            KeyCode::Enter => end_search(fradar_data.clone(), true),
+            KeyCode::Backspace => { fradar_data.lock().unwrap().ui.input_buffer.pop(); },
+            KeyCode::Char(c) => fradar_data.lock().unwrap().ui.input_buffer.push(c),
+            _ => continue,
+          }
+        },
+        Event::Key(key_event) if fradar_data.lock().unwrap().state == FRadarState::Command => {
+          match key_event.code {
+            KeyCode::Esc => end_command(fradar_data.clone(), false),
+            KeyCode::Enter => end_command(fradar_data.clone(), true),
+            KeyCode::Tab => complete_command(fradar_data.clone()),
+            KeyCode::Up => recall_command(fradar_data.clone(), -1),
+            KeyCode::Down => recall_command(fradar_data.clone(), 1),
+            KeyCode::Backspace => {
+              let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+              if fradar_data_ref.ui.input_buffer.pop().is_none() {
+                fradar_data_ref.state = FRadarState::Main;
+              }
+            },
+            KeyCode::Char(c) => fradar_data.lock().unwrap().ui.input_buffer.push(c),
+            _ => continue,
+          }
+        },
         Event::Key(key_event) => {
           match key_event.code {
             KeyCode::Delete | KeyCode::Esc | KeyCode::End | KeyCode::Char('q') => graceful_shutdown(fradar_data.clone()),
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => graceful_shutdown(fradar_data.clone()),
             KeyCode::Char('w') | KeyCode::Up    => change_origin(fradar_data.clone(),  lat_per_pixel(&args),  0.0),
             KeyCode::Char('s') | KeyCode::Down  => change_origin(fradar_data.clone(), -lat_per_pixel(&args),  0.0),
             KeyCode::Char('a') | KeyCode::Left  => change_origin(fradar_data.clone(),  0.0, -long_per_pixel(&args)),
             KeyCode::Char('d') | KeyCode::Right => change_origin(fradar_data.clone(),  0.0,  long_per_pixel(&args)),
+            KeyCode::Tab     => cycle_selection(fradar_data.clone(),  1),
+            KeyCode::BackTab => cycle_selection(fradar_data.clone(), -1),
+            KeyCode::Char('/') => begin_search(fradar_data.clone()),
+            KeyCode::Char(':') => begin_command(fradar_data.clone()),
+            KeyCode::Char(c @ '1'..='9') => jump_to_bookmark(fradar_data.clone(), c as usize - '1' as usize),
+            KeyCode::Char('i') | KeyCode::Enter => toggle_details(fradar_data.clone()),
+            KeyCode::Char('x') => clear_selection(fradar_data.clone()),
+            KeyCode::Char('f') => toggle_follow(fradar_data.clone()),
+            KeyCode::Char('l') => cycle_list_mode(fradar_data.clone()),
+            KeyCode::Char('o') => cycle_list_sort(fradar_data.clone()),
+            KeyCode::Char('O') => toggle_list_order(fradar_data.clone()),
+            KeyCode::Char('j') => step_list_selection(fradar_data.clone(),  1),
+            KeyCode::Char('k') => step_list_selection(fradar_data.clone(), -1),
+            KeyCode::PageDown => scroll_list(fradar_data.clone(), visible_rows(&args, fradar_data.lock().unwrap().ui.list_mode) as isize),
+            KeyCode::PageUp   => scroll_list(fradar_data.clone(), -(visible_rows(&args, fradar_data.lock().unwrap().ui.list_mode) as isize)),
             _ => continue,
           }
         },
         Event::Mouse(mouse_event) => {
+          let cursor: Coord<u16> = Coord { col: mouse_event.column, row: mouse_event.row };
+          let list_mode: ListMode = fradar_data.lock().unwrap().ui.list_mode;
+          let in_list: bool = list_area(&args, list_mode)
+            .is_some_and(|(x, y, w, h)| cursor.is_in_box(x, y, w.saturating_sub(1), h.saturating_sub(1)));
+
           match mouse_event.kind {
-              crossterm::event::MouseEventKind::ScrollDown => change_radius(fradar_data.clone(), 0.8),
-              crossterm::event::MouseEventKind::ScrollUp => change_radius(fradar_data.clone(), 1.25),
+              MouseEventKind::ScrollDown if in_list => scroll_list(fradar_data.clone(),  3),
+              MouseEventKind::ScrollUp if in_list => scroll_list(fradar_data.clone(), -3),
+              MouseEventKind::Down(MouseButton::Left) if in_list => select_list_row_at(fradar_data.clone(), cursor),
+              MouseEventKind::ScrollDown => zoom_at(fradar_data.clone(), 0.8, cursor),
+              MouseEventKind::ScrollUp => zoom_at(fradar_data.clone(), 1.25, cursor),
+              MouseEventKind::Down(MouseButton::Left | MouseButton::Middle) => begin_drag(fradar_data.clone(), cursor),
+              MouseEventKind::Drag(MouseButton::Left | MouseButton::Middle) => drag_to(fradar_data.clone(), cursor),
+              MouseEventKind::Up(MouseButton::Left) => end_drag(fradar_data.clone(), cursor, true),
+              MouseEventKind::Up(MouseButton::Middle) => end_drag(fradar_data.clone(), cursor, false),
+              MouseEventKind::Moved => fradar_data.lock().unwrap().ui.mouse = Some(cursor),
               _ => continue,
           }
         },
@@ -37,8 +94,11 @@ pub async fn event_dispatch_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio
 }
 
 pub fn graceful_shutdown(fradar_data: Arc<Mutex<FRadarData>>) {
-  let fradar_state: &mut FRadarState = &mut fradar_data.lock().unwrap().state;
-  *fradar_state = FRadarState::GracefulKill;
+  let saved: anyhow::Result<()> = {
+    let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+    fradar_data_ref.state = FRadarState::GracefulKill;
+    save_view(fradar_data_ref)
+  };
   
   execute!(
     std::io::stdout(),
@@ -48,33 +108,187 @@ pub fn graceful_shutdown(fradar_data: Arc<Mutex<FRadarData>>) {
   ).unwrap();
   
   crossterm::terminal::disable_raw_mode().unwrap();
+
+  if let Err(error) = saved {
+    eprintln!("fradar: couldn't save view state: {}", error);
+  }
 }
 
 pub fn change_radius(fradar_data: Arc<Mutex<FRadarData>>, factor: f64) {
   {
+    fradar_data.lock().unwrap().ui.transition = None;
     let fradar_args: &mut FRadarArgs = &mut fradar_data.lock().unwrap().args;
-    fradar_args.radius = fradar_args.radius as f64 * factor;
+    fradar_args.radius *= factor;
   }
-
-  execute!(
-    std::io::stdout(),
-    crossterm::cursor::MoveTo(3, 3),
-    crossterm::style::Print(fradar_data.lock().unwrap().args.radius),
-  ).unwrap();
 }
 
 pub fn change_origin(fradar_data: Arc<Mutex<FRadarData>>, delta_lat: f64, delta_long: f64) {
   {
+    // Manual panning takes the camera back from follow mode and any transition.
+    fradar_data.lock().unwrap().ui.follow = false;
+    fradar_data.lock().unwrap().ui.transition = None;
+
     let fradar_origin: &mut Position = &mut fradar_data.lock().unwrap().args.origin;
-    (*fradar_origin).lat += delta_lat;
-    (*fradar_origin).long += delta_long;
+    fradar_origin.lat += delta_lat;
+    fradar_origin.long += delta_long;
   }
+}
 
-  execute!(
-    std::io::stdout(),
-    crossterm::cursor::MoveTo(3, 4),
-    crossterm::style::Print(format!("{:?}", fradar_data.lock().unwrap().args.origin)),
-  ).unwrap();
+pub fn zoom_at(fradar_data: Arc<Mutex<FRadarData>>, factor: f64, cursor: Coord<u16>) {
+  let (args, follow): (FRadarArgs, bool) = {
+    let fradar_data_ref: &FRadarData = &fradar_data.lock().unwrap();
+    (fradar_data_ref.args, fradar_data_ref.ui.follow)
+  };
+
+  change_radius(fradar_data.clone(), factor);
+
+  // Follow mode owns the origin, so only the radius changes.
+  if follow {
+    return;
+  }
+
+  // Scale the origin about the point under the cursor so that point stays put.
+  let anchor: Position = Position::from_terminal_cell(cursor, &args);
+  fradar_data.lock().unwrap().args.origin = Position {
+    lat:  anchor.lat  - (anchor.lat  - args.origin.lat)  * factor,
+    long: anchor.long - (anchor.long - args.origin.long) * factor,
+  };
+}
+
+pub fn begin_drag(fradar_data: Arc<Mutex<FRadarData>>, cursor: Coord<u16>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.drag_anchor = Some((cursor, fradar_data_ref.args.origin));
+  fradar_data_ref.ui.dragged = false;
+}
+
+pub fn drag_to(fradar_data: Arc<Mutex<FRadarData>>, cursor: Coord<u16>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let Some((anchor_cursor, anchor_origin)) = fradar_data_ref.ui.drag_anchor else {
+    return;
+  };
+
+  // Grab-style panning: the point under the anchor cursor follows the pointer.
+  let args: FRadarArgs = FRadarArgs { origin: anchor_origin, ..fradar_data_ref.args };
+  let grabbed: Position = Position::from_terminal_cell(anchor_cursor, &args);
+  let pointed: Position = Position::from_terminal_cell(cursor, &args);
+
+  fradar_data_ref.args.origin = Position {
+    lat:  anchor_origin.lat  + grabbed.lat  - pointed.lat,
+    long: anchor_origin.long + grabbed.long - pointed.long,
+  };
+  fradar_data_ref.ui.mouse = Some(cursor);
+  fradar_data_ref.ui.dragged = true;
+  fradar_data_ref.ui.follow = false;
+  fradar_data_ref.ui.transition = None;
+}
+
+pub fn end_drag(fradar_data: Arc<Mutex<FRadarData>>, cursor: Coord<u16>, select_on_click: bool) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let was_click: bool = fradar_data_ref.ui.drag_anchor.is_some() && !fradar_data_ref.ui.dragged;
+  fradar_data_ref.ui.drag_anchor = None;
+  fradar_data_ref.ui.dragged = false;
+
+  if !(was_click && select_on_click) {
+    return;
+  }
+
+  let flights_data: FlightData = fradar_data_ref.flights_data.lock().unwrap().clone();
+  let click: Coord<f64> = Coord { col: cursor.col as f64 + 0.5, row: cursor.row as f64 + 0.5 };
+  match flights_data.nearest_to_coord(click, 3.0, &fradar_data_ref.args) {
+    Some(index) => {
+      fradar_data_ref.ui.selected_hex = Some(flights_data.aircraft[index].hex.clone());
+      fradar_data_ref.ui.show_details = true;
+      reveal_selection(fradar_data_ref);
+    },
+    None => {
+      fradar_data_ref.ui.selected_hex = None;
+      fradar_data_ref.ui.show_details = false;
+      fradar_data_ref.ui.follow = false;
+    },
+  }
+}
+
+pub fn cycle_list_mode(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.list_mode = fradar_data_ref.ui.list_mode.next();
+  reveal_selection(fradar_data_ref);
+}
+
+pub fn cycle_list_sort(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.list_sort = fradar_data_ref.ui.list_sort.next();
+  reveal_selection(fradar_data_ref);
+}
+
+pub fn toggle_list_order(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.list_descending = !fradar_data_ref.ui.list_descending;
+  reveal_selection(fradar_data_ref);
+}
+
+pub fn scroll_list(fradar_data: Arc<Mutex<FRadarData>>, delta: isize) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let rows: usize = fradar_data_ref.flights_data.lock().unwrap().aircraft.len();
+  let max_scroll: usize = rows.saturating_sub(visible_rows(&fradar_data_ref.args, fradar_data_ref.ui.list_mode));
+
+  fradar_data_ref.ui.list_scroll = fradar_data_ref.ui.list_scroll.saturating_add_signed(delta).min(max_scroll);
+}
+
+/// Moves the selection up or down the list in its current sort order.
+pub fn step_list_selection(fradar_data: Arc<Mutex<FRadarData>>, step: isize) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  if fradar_data_ref.ui.list_mode == ListMode::Hidden {
+    return;
+  }
+
+  let rows: Vec<ListRow> = current_rows(fradar_data_ref);
+  if rows.is_empty() {
+    return;
+  }
+
+  let current: Option<usize> = fradar_data_ref.ui.selected_hex.as_deref().and_then(|hex| rows.iter().position(|row| row.hex == hex));
+  let next: usize = match current {
+    Some(index) => index.saturating_add_signed(step).min(rows.len() - 1),
+    None => 0,
+  };
+
+  fradar_data_ref.ui.selected_hex = Some(rows[next].hex.clone());
+  reveal_selection(fradar_data_ref);
+}
+
+pub fn select_list_row_at(fradar_data: Arc<Mutex<FRadarData>>, cursor: Coord<u16>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let Some((_, y, _, _)) = list_area(&fradar_data_ref.args, fradar_data_ref.ui.list_mode) else {
+    return;
+  };
+
+  // Rows start below the top border and the header line.
+  let Some(offset) = cursor.row.checked_sub(y + 2) else {
+    return;
+  };
+
+  let rows: Vec<ListRow> = current_rows(fradar_data_ref);
+  let scroll: usize = fradar_data_ref.ui.list_scroll.min(rows.len().saturating_sub(visible_rows(&fradar_data_ref.args, fradar_data_ref.ui.list_mode)));
+  if let Some(row) = rows.get(scroll + offset as usize) {
+    fradar_data_ref.ui.selected_hex = Some(row.hex.clone());
+    fradar_data_ref.ui.show_details = true;
+  }
+}
+
+fn current_rows(fradar_data_ref: &FRadarData) -> Vec<ListRow> {
+  let flights_data: FlightData = fradar_data_ref.flights_data.lock().unwrap().clone();
+  sorted_rows(&flights_data, &fradar_data_ref.args, &fradar_data_ref.ui, Utc::now().timestamp_millis())
+}
+
+/// Scrolls the list so that the selected aircraft is visible.
+fn reveal_selection(fradar_data_ref: &mut FRadarData) {
+  if fradar_data_ref.ui.list_mode == ListMode::Hidden {
+    return;
+  }
+
+  let rows: Vec<ListRow> = current_rows(fradar_data_ref);
+  let visible: usize = visible_rows(&fradar_data_ref.args, fradar_data_ref.ui.list_mode);
+  fradar_data_ref.ui.list_scroll = scroll_to_selection(&rows, &fradar_data_ref.ui, visible);
 }
 
 pub fn change_term_size(fradar_data: Arc<Mutex<FRadarData>>, new_width: u16, new_height: u16) {
@@ -85,15 +299,162 @@ pub fn change_term_size(fradar_data: Arc<Mutex<FRadarData>>, new_width: u16, new
   }
 }
 
+pub fn cycle_selection(fradar_data: Arc<Mutex<FRadarData>>, step: isize) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let flights_data: FlightData = fradar_data_ref.flights_data.lock().unwrap().clone();
+
+  let order: Vec<usize> = flights_data.nearest_first(&fradar_data_ref.args.origin);
+  if order.is_empty() {
+    return;
+  }
+
+  let current: Option<usize> = fradar_data_ref.ui.selected_hex.as_deref()
+    .and_then(|hex| flights_data.index_of_hex(hex))
+    .and_then(|index| order.iter().position(|&i| i == index));
+
+  let next: usize = match current {
+    Some(position) => (position as isize + step).rem_euclid(order.len() as isize) as usize,
+    None if step < 0 => order.len() - 1,
+    None => 0,
+  };
+
+  fradar_data_ref.ui.selected_hex = Some(flights_data.aircraft[order[next]].hex.clone());
+  fradar_data_ref.ui.show_details = true;
+  reveal_selection(fradar_data_ref);
+}
+
+pub fn begin_search(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.input_buffer.clear();
+  fradar_data_ref.state = FRadarState::Search;
+}
+
+pub fn end_search(fradar_data: Arc<Mutex<FRadarData>>, accept: bool) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.state = FRadarState::Main;
+
+  let callsign: String = std::mem::take(&mut fradar_data_ref.ui.input_buffer);
+  if !accept {
+    return;
+  }
+
+  let flights_data: FlightData = fradar_data_ref.flights_data.lock().unwrap().clone();
+  if let Some(index) = flights_data.index_of_callsign(&callsign) {
+    fradar_data_ref.ui.selected_hex = Some(flights_data.aircraft[index].hex.clone());
+    fradar_data_ref.ui.show_details = true;
+    reveal_selection(fradar_data_ref);
+  }
+}
+
+pub fn begin_command(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.input_buffer.clear();
+  fradar_data_ref.ui.command_history_cursor = None;
+  fradar_data_ref.state = FRadarState::Command;
+}
+
+pub fn end_command(fradar_data: Arc<Mutex<FRadarData>>, accept: bool) {
+  let quit: bool = {
+    let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+    fradar_data_ref.state = FRadarState::Main;
+    fradar_data_ref.ui.command_history_cursor = None;
+
+    let line: String = std::mem::take(&mut fradar_data_ref.ui.input_buffer);
+    if !accept || line.trim().is_empty() {
+      return;
+    }
+
+    if fradar_data_ref.ui.command_history.last() != Some(&line) {
+      fradar_data_ref.ui.command_history.push(line.clone());
+    }
+
+    match command::execute(fradar_data_ref, &line) {
+      Ok(message) if message.is_empty() => {},
+      Ok(message) => fradar_data_ref.ui.set_status(message),
+      Err(error) => fradar_data_ref.ui.set_status(format!("error: {}", error)),
+    }
+
+    fradar_data_ref.state == FRadarState::GracefulKill
+  };
+
+  if quit {
+    graceful_shutdown(fradar_data);
+  }
+}
+
+pub fn complete_command(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let (completed, candidates) = command::complete(fradar_data_ref, &fradar_data_ref.ui.input_buffer);
+  fradar_data_ref.ui.input_buffer = completed;
+
+  if candidates.len() > 1 {
+    fradar_data_ref.ui.set_status(candidates.join("  "));
+  }
+}
+
+/// Steps through previously executed commands, newest first.
+pub fn recall_command(fradar_data: Arc<Mutex<FRadarData>>, step: isize) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let history_len: usize = fradar_data_ref.ui.command_history.len();
+  if history_len == 0 {
+    return;
+  }
+
+  let cursor: Option<usize> = match (fradar_data_ref.ui.command_history_cursor, step < 0) {
+    (None, true) => Some(history_len - 1),
+    (None, false) => None,
+    (Some(0), true) => Some(0),
+    (Some(index), true) => Some(index - 1),
+    (Some(index), false) if index + 1 < history_len => Some(index + 1),
+    (Some(_), false) => None,
+  };
+
+  fradar_data_ref.ui.command_history_cursor = cursor;
+  fradar_data_ref.ui.input_buffer = cursor
+    .map(|index| fradar_data_ref.ui.command_history[index].clone())
+    .unwrap_or_default();
+}
+
+pub fn jump_to_bookmark(fradar_data: Arc<Mutex<FRadarData>>, index: usize) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  let Some(bookmark) = fradar_data_ref.bookmarks.get(index).cloned() else {
+    fradar_data_ref.ui.set_status(format!("no bookmark {}", index + 1));
+    return;
+  };
+
+  fradar_data_ref.ui.transition = Some(Transition::new(&fradar_data_ref.args, bookmark.origin, bookmark.radius));
+  fradar_data_ref.ui.follow = false;
+  fradar_data_ref.ui.set_status(format!("{}: {}", index + 1, bookmark.name));
+}
+
+pub fn toggle_details(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  if fradar_data_ref.ui.selected_hex.is_some() {
+    fradar_data_ref.ui.show_details = !fradar_data_ref.ui.show_details;
+  }
+}
+
+pub fn clear_selection(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.selected_hex = None;
+  fradar_data_ref.ui.show_details = false;
+  fradar_data_ref.ui.follow = false;
+}
+
+pub fn toggle_follow(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+  fradar_data_ref.ui.follow = fradar_data_ref.ui.selected_hex.is_some() && !fradar_data_ref.ui.follow;
+}
+
 fn lat_per_pixel(args: &FRadarArgs) -> f64 {
-  (args.radius as f64) /
+  args.radius /
     Position::latlong_miles_ratio() /
     (f64::max(args.terminal_cols as f64 / 2.0, args.terminal_rows as f64 / 2.0)) /
     Position::character_aspect_ratio()
 }
 
 fn long_per_pixel(args: &FRadarArgs) -> f64 {
-  (args.radius as f64) /
+  args.radius /
     Position::latlong_miles_ratio() /
     (f64::max(args.terminal_cols as f64 / 2.0, args.terminal_rows as f64 / 2.0))
 }
@@ -0,0 +1,640 @@
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{aircraft_list::altitude_feet, model::ADSBAircraftInformation};
+
+
+/// A compiled filter expression over aircraft fields, e.g.
+/// `alt_baro between 5000 and 18000 and category == "A3" and not flight ~ "^SWA"`.
+///
+/// Comparisons against a field the aircraft doesn't report are false. `~` matches a
+/// quoted string as a regex, or a bare word as a glob (`type ~ B7*`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Filter {
+  source: String,
+  expr: Expr,
+}
+
+impl Filter {
+  pub fn parse(source: &str) -> Result<Filter, FilterError> {
+    let tokens: Vec<(usize, Token)> = tokenize(source)?;
+    let mut parser = Parser { tokens, index: 0, source_len: source.len() };
+    let expr: Expr = parser.parse_or()?;
+
+    if let Some((position, token)) = parser.peek() {
+      return Err(FilterError::new(*position, format!("unexpected {}", token)));
+    }
+
+    Ok(Filter { source: source.trim().to_string(), expr })
+  }
+
+  pub fn source(&self) -> &str {
+    &self.source
+  }
+
+  pub fn matches(&self, info: &ADSBAircraftInformation) -> bool {
+    self.expr.eval(info)
+  }
+}
+
+impl PartialEq for Filter {
+  fn eq(&self, other: &Self) -> bool {
+    self.source == other.source
+  }
+}
+
+impl fmt::Display for Filter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.source)
+  }
+}
+
+impl TryFrom<String> for Filter {
+  type Error = FilterError;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    Filter::parse(&value)
+  }
+}
+
+impl From<Filter> for String {
+  fn from(value: Filter) -> Self {
+    value.source
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+  pub position: usize,
+  pub message: String,
+}
+
+impl FilterError {
+  fn new(position: usize, message: impl Into<String>) -> FilterError {
+    FilterError { position, message: message.into() }
+  }
+
+  /// Multi-line rendering with a caret under the offending column, for the CLI.
+  pub fn pretty(&self, source: &str) -> String {
+    format!("{}\n  {}\n  {}^", self, source, " ".repeat(source[..self.position.min(source.len())].chars().count()))
+  }
+}
+
+impl fmt::Display for FilterError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} at column {}", self.message, self.position + 1)
+  }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Fields that can appear on the left-hand side of a comparison.
+pub const FIELDS: [&str; 22] = [
+  "hex", "flight", "r", "t", "type", "category", "squawk", "emergency",
+  "alt_baro", "alt_geom", "gs", "tas", "track", "roll", "geom_rate",
+  "nic", "nac_p", "sil", "rssi", "messages", "seen", "dbflags",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+  Hex,
+  Flight,
+  Registration,
+  TypeCode,
+  Category,
+  Squawk,
+  Emergency,
+  AltBaro,
+  AltGeom,
+  GroundSpeed,
+  TrueAirspeed,
+  Track,
+  Roll,
+  GeomRate,
+  Nic,
+  NacP,
+  Sil,
+  Rssi,
+  Messages,
+  Seen,
+  DbFlags,
+}
+
+impl Field {
+  fn from_name(name: &str) -> Option<Field> {
+    Some(match name.to_lowercase().as_str() {
+      "hex" | "icao" => Field::Hex,
+      "flight" | "callsign" => Field::Flight,
+      "r" | "reg" | "registration" => Field::Registration,
+      "t" | "type" => Field::TypeCode,
+      "category" | "cat" => Field::Category,
+      "squawk" => Field::Squawk,
+      "emergency" => Field::Emergency,
+      "alt_baro" | "alt" | "altitude" => Field::AltBaro,
+      "alt_geom" => Field::AltGeom,
+      "gs" | "speed" => Field::GroundSpeed,
+      "tas" => Field::TrueAirspeed,
+      "track" | "heading" => Field::Track,
+      "roll" => Field::Roll,
+      "geom_rate" | "rate" | "vs" => Field::GeomRate,
+      "nic" => Field::Nic,
+      "nac_p" | "nac" => Field::NacP,
+      "sil" => Field::Sil,
+      "rssi" => Field::Rssi,
+      "messages" => Field::Messages,
+      "seen" => Field::Seen,
+      "dbflags" => Field::DbFlags,
+      _ => return None,
+    })
+  }
+
+  fn value(&self, info: &ADSBAircraftInformation) -> Option<Value> {
+    let text = |value: Option<&String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).map(Value::Text);
+    let number = |value: Option<f64>| value.map(Value::Number);
+
+    match self {
+      Field::Hex => Some(Value::Text(info.hex.clone())),
+      Field::Flight => text(info.flight.as_ref()),
+      Field::Registration => text(info.r.as_ref()),
+      Field::TypeCode => text(info.t.as_ref()),
+      Field::Category => text(info.category.as_ref()),
+      Field::Squawk => text(info.squawk.as_ref()),
+      Field::Emergency => text(info.emergency.as_ref()),
+      Field::AltBaro => match info.alt_baro.as_deref() {
+        Some("ground") => Some(Value::Ground),
+        _ => number(altitude_feet(info).filter(|_| info.alt_baro.is_some())),
+      },
+      Field::AltGeom => number(info.alt_geom.map(f64::from)),
+      Field::GroundSpeed => number(info.gs.map(f64::from)),
+      Field::TrueAirspeed => number(info.tas.map(f64::from)),
+      Field::Track => number(info.track.map(f64::from)),
+      Field::Roll => number(info.roll.map(f64::from)),
+      Field::GeomRate => number(info.geom_rate.map(f64::from)),
+      Field::Nic => number(info.nic.map(f64::from)),
+      Field::NacP => number(info.nac_p.map(f64::from)),
+      Field::Sil => number(info.sil.map(f64::from)),
+      Field::Rssi => number(info.rssi.map(f64::from)),
+      Field::Messages => number(info.messages.map(f64::from)),
+      Field::Seen => number(info.seen.map(f64::from)),
+      Field::DbFlags => number(info.dbFlags.map(f64::from)),
+    }
+  }
+}
+
+/// A field value as seen by the evaluator. `Ground` compares as altitude 0 or the text "ground".
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+  Text(String),
+  Number(f64),
+  Ground,
+}
+
+impl Value {
+  fn as_number(&self) -> Option<f64> {
+    match self {
+      Value::Number(n) => Some(*n),
+      Value::Ground => Some(0.0),
+      Value::Text(s) => s.parse().ok(),
+    }
+  }
+
+  fn as_text(&self) -> String {
+    match self {
+      Value::Number(n) => n.to_string(),
+      Value::Ground => "ground".to_string(),
+      Value::Text(s) => s.clone(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+  And(Box<Expr>, Box<Expr>),
+  Or(Box<Expr>, Box<Expr>),
+  Not(Box<Expr>),
+  Compare(Field, CompareOp, Value),
+  Between(Field, f64, f64),
+  Matches(Field, Regex),
+  Exists(Field),
+}
+
+impl Expr {
+  fn eval(&self, info: &ADSBAircraftInformation) -> bool {
+    match self {
+      Expr::And(a, b) => a.eval(info) && b.eval(info),
+      Expr::Or(a, b) => a.eval(info) || b.eval(info),
+      Expr::Not(a) => !a.eval(info),
+      Expr::Exists(field) => field.value(info).is_some(),
+      Expr::Between(field, low, high) => field.value(info)
+        .and_then(|value| value.as_number())
+        .is_some_and(|n| *low <= n && n <= *high),
+      Expr::Matches(field, regex) => field.value(info)
+        .is_some_and(|value| regex.is_match(&value.as_text())),
+      Expr::Compare(field, op, rhs) => {
+        let Some(lhs) = field.value(info) else {
+          return false;
+        };
+
+        match (lhs.as_number(), rhs) {
+          (Some(l), Value::Number(r)) => match op {
+            CompareOp::Eq => l == *r,
+            CompareOp::Ne => l != *r,
+            CompareOp::Lt => l < *r,
+            CompareOp::Le => l <= *r,
+            CompareOp::Gt => l > *r,
+            CompareOp::Ge => l >= *r,
+          },
+          _ => {
+            let (l, r) = (lhs.as_text().to_lowercase(), rhs.as_text().to_lowercase());
+            match op {
+              CompareOp::Eq => l == r,
+              CompareOp::Ne => l != r,
+              CompareOp::Lt => l < r,
+              CompareOp::Le => l <= r,
+              CompareOp::Gt => l > r,
+              CompareOp::Ge => l >= r,
+            }
+          },
+        }
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Word(String),
+  Quoted(String),
+  Compare(CompareOp),
+  Match,
+  NotMatch,
+  And,
+  Or,
+  Not,
+  Between,
+  LParen,
+  RParen,
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Word(word) => write!(f, "'{}'", word),
+      Token::Quoted(string) => write!(f, "\"{}\"", string),
+      Token::Compare(op) => write!(f, "'{}'", match op {
+        CompareOp::Eq => "==",
+        CompareOp::Ne => "!=",
+        CompareOp::Lt => "<",
+        CompareOp::Le => "<=",
+        CompareOp::Gt => ">",
+        CompareOp::Ge => ">=",
+      }),
+      Token::Match => write!(f, "'~'"),
+      Token::NotMatch => write!(f, "'!~'"),
+      Token::And => write!(f, "'and'"),
+      Token::Or => write!(f, "'or'"),
+      Token::Not => write!(f, "'not'"),
+      Token::Between => write!(f, "'between'"),
+      Token::LParen => write!(f, "'('"),
+      Token::RParen => write!(f, "')'"),
+    }
+  }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+  let mut tokens: Vec<(usize, Token)> = Vec::new();
+  let chars: Vec<(usize, char)> = source.char_indices().collect();
+  let mut i: usize = 0;
+
+  let is_word_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '*' | '?' | '-' | '+');
+
+  while i < chars.len() {
+    let (position, c) = chars[i];
+    let next: Option<char> = chars.get(i + 1).map(|(_, c)| *c);
+
+    let (token, consumed): (Token, usize) = match (c, next) {
+      (c, _) if c.is_whitespace() => { i += 1; continue; },
+      ('(', _) => (Token::LParen, 1),
+      (')', _) => (Token::RParen, 1),
+      ('=', Some('=')) => (Token::Compare(CompareOp::Eq), 2),
+      ('=', _) => (Token::Compare(CompareOp::Eq), 1),
+      ('!', Some('=')) => (Token::Compare(CompareOp::Ne), 2),
+      ('!', Some('~')) => (Token::NotMatch, 2),
+      ('!', _) => (Token::Not, 1),
+      ('<', Some('=')) => (Token::Compare(CompareOp::Le), 2),
+      ('<', _) => (Token::Compare(CompareOp::Lt), 1),
+      ('>', Some('=')) => (Token::Compare(CompareOp::Ge), 2),
+      ('>', _) => (Token::Compare(CompareOp::Gt), 1),
+      ('~', _) => (Token::Match, 1),
+      ('&', Some('&')) => (Token::And, 2),
+      ('|', Some('|')) => (Token::Or, 2),
+      ('"' | '\'', _) => {
+        let quote: char = c;
+        let mut string: String = String::new();
+        let mut j: usize = i + 1;
+        loop {
+          match chars.get(j) {
+            None => return Err(FilterError::new(position, "unterminated string")),
+            Some((_, '\\')) if chars.get(j + 1).is_some() => {
+              string.push(chars[j + 1].1);
+              j += 2;
+            },
+            Some((_, c)) if *c == quote => break,
+            Some((_, c)) => {
+              string.push(*c);
+              j += 1;
+            },
+          }
+        }
+        (Token::Quoted(string), j + 1 - i)
+      },
+      (c, _) if is_word_char(c) => {
+        let length: usize = chars[i..].iter().take_while(|(_, c)| is_word_char(*c)).count();
+        let word: String = chars[i..i + length].iter().map(|(_, c)| c).collect();
+        let token: Token = match word.to_lowercase().as_str() {
+          "and" => Token::And,
+          "or" => Token::Or,
+          "not" => Token::Not,
+          "between" => Token::Between,
+          _ => Token::Word(word),
+        };
+        (token, length)
+      },
+      (c, _) => return Err(FilterError::new(position, format!("unexpected character '{}'", c))),
+    };
+
+    tokens.push((position, token));
+    i += consumed;
+  }
+
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<(usize, Token)>,
+  index: usize,
+  source_len: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&(usize, Token)> {
+    self.tokens.get(self.index)
+  }
+
+  fn next(&mut self) -> Option<(usize, Token)> {
+    let token = self.tokens.get(self.index).cloned();
+    self.index += 1;
+    token
+  }
+
+  fn eat(&mut self, expected: &Token) -> bool {
+    if self.peek().is_some_and(|(_, token)| token == expected) {
+      self.index += 1;
+      return true;
+    }
+    false
+  }
+
+  fn error_here(&self, message: &str) -> FilterError {
+    match self.peek() {
+      Some((position, token)) => FilterError::new(*position, format!("{}, found {}", message, token)),
+      None => FilterError::new(self.source_len, format!("{}, found end of filter", message)),
+    }
+  }
+
+  fn parse_or(&mut self) -> Result<Expr, FilterError> {
+    let mut lhs: Expr = self.parse_and()?;
+    while self.eat(&Token::Or) {
+      lhs = Expr::Or(Box::new(lhs), Box::new(self.parse_and()?));
+    }
+    Ok(lhs)
+  }
+
+  fn parse_and(&mut self) -> Result<Expr, FilterError> {
+    let mut lhs: Expr = self.parse_not()?;
+    while self.eat(&Token::And) {
+      lhs = Expr::And(Box::new(lhs), Box::new(self.parse_not()?));
+    }
+    Ok(lhs)
+  }
+
+  fn parse_not(&mut self) -> Result<Expr, FilterError> {
+    if self.eat(&Token::Not) {
+      return Ok(Expr::Not(Box::new(self.parse_not()?)));
+    }
+    self.parse_comparison()
+  }
+
+  fn parse_comparison(&mut self) -> Result<Expr, FilterError> {
+    if self.eat(&Token::LParen) {
+      let expr: Expr = self.parse_or()?;
+      if !self.eat(&Token::RParen) {
+        return Err(self.error_here("expected ')'"));
+      }
+      return Ok(expr);
+    }
+
+    let field: Field = match self.next() {
+      Some((position, Token::Word(name))) => Field::from_name(&name)
+        .ok_or_else(|| FilterError::new(position, format!("unknown field '{}' (expected one of: {})", name, FIELDS.join(", "))))?,
+      _ => {
+        self.index = self.index.saturating_sub(1);
+        return Err(self.error_here("expected a field name"));
+      },
+    };
+
+    match self.peek().map(|(_, token)| token.clone()) {
+      Some(Token::Compare(op)) => {
+        self.index += 1;
+        let value: Value = self.parse_value()?;
+        Ok(Expr::Compare(field, op, value))
+      },
+      Some(Token::Match) | Some(Token::NotMatch) => {
+        let negated: bool = matches!(self.next(), Some((_, Token::NotMatch)));
+        let regex: Regex = self.parse_pattern()?;
+        let expr: Expr = Expr::Matches(field, regex);
+        Ok(if negated { Expr::Not(Box::new(expr)) } else { expr })
+      },
+      Some(Token::Between) => {
+        self.index += 1;
+        let low: f64 = self.parse_number()?;
+        if !self.eat(&Token::And) {
+          return Err(self.error_here("expected 'and' in 'between'"));
+        }
+        let high: f64 = self.parse_number()?;
+        Ok(Expr::Between(field, low.min(high), low.max(high)))
+      },
+      // A bare field name tests that the aircraft reports it at all.
+      _ => Ok(Expr::Exists(field)),
+    }
+  }
+
+  fn parse_value(&mut self) -> Result<Value, FilterError> {
+    match self.next() {
+      Some((_, Token::Quoted(string))) => Ok(Value::Text(string)),
+      Some((_, Token::Word(word))) => Ok(match word.parse::<f64>() {
+        Ok(number) => Value::Number(number),
+        Err(_) => Value::Text(word),
+      }),
+      _ => {
+        self.index = self.index.saturating_sub(1);
+        Err(self.error_here("expected a value"))
+      },
+    }
+  }
+
+  fn parse_number(&mut self) -> Result<f64, FilterError> {
+    match self.next() {
+      Some((position, Token::Word(word))) => word.parse::<f64>()
+        .map_err(|_| FilterError::new(position, format!("expected a number, found '{}'", word))),
+      _ => {
+        self.index = self.index.saturating_sub(1);
+        Err(self.error_here("expected a number"))
+      },
+    }
+  }
+
+  fn parse_pattern(&mut self) -> Result<Regex, FilterError> {
+    let (position, pattern): (usize, String) = match self.next() {
+      Some((position, Token::Quoted(string))) => (position, string),
+      Some((position, Token::Word(word))) => (position, glob_to_regex(&word)),
+      _ => {
+        self.index = self.index.saturating_sub(1);
+        return Err(self.error_here("expected a pattern"));
+      },
+    };
+
+    RegexBuilder::new(&pattern)
+      .case_insensitive(true)
+      .build()
+      .map_err(|error| FilterError::new(position, format!("invalid regex: {}", error.to_string().lines().last().unwrap_or_default())))
+  }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+  let escaped: String = glob.chars()
+    .map(|c| match c {
+      '*' => ".*".to_string(),
+      '?' => ".".to_string(),
+      c => regex::escape(&c.to_string()),
+    })
+    .collect();
+  format!("^{}$", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fixtures, model::Position};
+
+  fn aircraft() -> ADSBAircraftInformation {
+    ADSBAircraftInformation {
+      flight: Some("SWA1234 ".to_string()),
+      t: Some("B738".to_string()),
+      category: Some("A3".to_string()),
+      gs: Some(410.0),
+      squawk: Some("4512".to_string()),
+      ..fixtures::aircraft("a1b2c3", &Position::default(), 12000)
+    }
+  }
+
+  fn matches(source: &str, info: &ADSBAircraftInformation) -> bool {
+    Filter::parse(source).unwrap_or_else(|error| panic!("{}", error.pretty(source))).matches(info)
+  }
+
+  #[test]
+  fn compares_numbers_and_text() {
+    let info: ADSBAircraftInformation = aircraft();
+    assert!(matches("alt_baro > 10000", &info));
+    assert!(!matches("alt_baro <= 10000", &info));
+    assert!(matches("gs >= 410 and gs != 400", &info));
+    assert!(matches("category == \"a3\"", &info));
+    assert!(matches("squawk = 4512", &info));
+    assert!(matches("flight == SWA1234", &info));
+  }
+
+  #[test]
+  fn combines_with_precedence() {
+    let info: ADSBAircraftInformation = aircraft();
+    assert!(matches("alt between 18000 and 5000 and category == \"A3\" and not flight ~ \"^DAL\"", &info));
+    assert!(matches("category == A1 or category == A3 and gs > 400", &info));
+    assert!(!matches("(category == A1 or category == A3) and gs > 500", &info));
+    assert!(matches("!(gs < 100) && squawk || hex == 000000", &info));
+  }
+
+  #[test]
+  fn matches_regexes_and_globs() {
+    let info: ADSBAircraftInformation = aircraft();
+    assert!(matches("flight ~ \"^swa\\\\d+$\"", &info));
+    assert!(matches("type ~ B7*", &info));
+    assert!(matches("type ~ B73?", &info));
+    assert!(!matches("type ~ B7", &info));
+    assert!(matches("flight !~ \"^DAL\"", &info));
+  }
+
+  #[test]
+  fn missing_fields_never_compare() {
+    let info: ADSBAircraftInformation = aircraft();
+    assert!(!matches("roll > -90", &info));
+    assert!(!matches("roll == 0", &info));
+    assert!(!matches("emergency", &info));
+    assert!(matches("not emergency", &info));
+    assert!(matches("squawk", &info));
+  }
+
+  #[test]
+  fn ground_is_zero_altitude() {
+    let info: ADSBAircraftInformation = ADSBAircraftInformation { alt_baro: Some("ground".to_string()), ..aircraft() };
+    assert!(matches("alt_baro == ground", &info));
+    assert!(matches("alt_baro < 100", &info));
+    assert!(!matches("alt_baro > 0", &info));
+  }
+
+  #[test]
+  fn rejects_malformed_input() {
+    let cases: [(&str, usize); 12] = [
+      ("", 0),
+      ("alt_baro >", 10),
+      ("altitde > 5000", 0),
+      ("alt_baro > 5000 and", 19),
+      ("(gs > 100", 9),
+      ("gs > 100)", 8),
+      ("flight ~ \"SWA", 9),
+      ("flight ~ \"[\"", 9),
+      ("gs between 100 200", 15),
+      ("gs between fast and 200", 11),
+      ("gs # 100", 3),
+      ("> 100", 0),
+    ];
+
+    for (source, position) in cases {
+      match Filter::parse(source) {
+        Ok(filter) => panic!("'{}' parsed as {:?}", source, filter),
+        Err(error) => {
+          assert_eq!(error.position, position, "'{}': {}", source, error);
+          assert!(error.pretty(source).contains('^'));
+        },
+      }
+    }
+  }
+
+  #[test]
+  fn round_trips_through_its_source() {
+    let filter: Filter = Filter::parse("  gs > 100  ").unwrap();
+    assert_eq!(filter.to_string(), "gs > 100");
+    assert_eq!(Filter::try_from(String::from(filter.clone())), Ok(filter));
+  }
+}
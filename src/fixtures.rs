@@ -0,0 +1,59 @@
+//! State shared by the unit tests.
+
+use std::{collections::VecDeque, sync::{Arc, Mutex}};
+
+use crate::{airports::AirportDb, alerts::Alerts, geofence::Geofences, model::{ADSBAircraftInformation, FRadarArgs, FRadarData, FRadarState, FRadarUi, FlightData, Label, Position}, watchlist::Watchlist};
+
+
+/// A freshly started fradar with nothing loaded and no traffic.
+pub fn fradar_data(args: FRadarArgs) -> FRadarData {
+  FRadarData {
+    flights_data: Arc::new(Mutex::new(FlightData::default())),
+    flights_data_history: VecDeque::default(),
+    state: FRadarState::default(),
+    args,
+    ui: FRadarUi::default(),
+    filter: None,
+
+    bookmarks: Vec::new(),
+    config_path: None,
+
+    airports: Arc::new(AirportDb::default()),
+    overlays: Arc::new(Vec::new()),
+    airspaces: Arc::new(Vec::new()),
+
+    alerts: Alerts::default(),
+    watchlist: Arc::new(Watchlist::default()),
+    geofences: Geofences::default(),
+    conflicts: Vec::new(),
+  }
+}
+
+/// An aircraft at `position` and `altitude` feet, with nothing else reported.
+pub fn aircraft(hex: &str, position: &Position, altitude: i32) -> ADSBAircraftInformation {
+  ADSBAircraftInformation {
+    hex: hex.to_string(),
+    alt_baro: Some(altitude.to_string()),
+    lat: position.lat,
+    lon: position.long,
+    ..Default::default()
+  }
+}
+
+/// An aircraft with a velocity, named after where it is.
+pub fn flying(position: &Position, altitude: i32, track: f32, gs: f32) -> ADSBAircraftInformation {
+  ADSBAircraftInformation {
+    track: Some(track),
+    gs: Some(gs),
+    ..aircraft(&format!("{:.4}{:.4}", position.lat, position.long), position, altitude)
+  }
+}
+
+/// One update from the feed, taken at `now` (milliseconds).
+pub fn flights_data(now: i64, aircraft: Vec<ADSBAircraftInformation>) -> FlightData {
+  FlightData {
+    flights: aircraft.iter().map(|info| (Position { lat: info.lat, long: info.lon }, Label::default())).collect(),
+    aircraft,
+    epoch_timestamp: now,
+  }
+}
@@ -0,0 +1,334 @@
+use std::{collections::HashMap, process::{Command, Stdio}, time::Duration};
+
+use anyhow::{anyhow, bail};
+use crossterm::style::Color;
+
+use crate::{airspace::polygon_contains, alerts::{Condition, MISSING_GRACE_MILLIS}, canvas::{BrailleCanvas, Stroke}, model::{Coord, FRadarArgs, FlightData, Position}};
+
+
+/// Vertices per circular fence.
+const CIRCLE_SEGMENTS: usize = 72;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenceShape {
+  Circle { center: Position, radius_miles: f64 },
+  Polygon(Vec<Position>),
+}
+
+/// A named area to watch, optionally limited to a band of altitudes (feet).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geofence {
+  pub name: String,
+  pub shape: FenceShape,
+  pub floor: Option<f64>,
+  pub ceiling: Option<f64>,
+  /// Report aircraft that stay inside longer than this.
+  pub dwell: Option<Duration>,
+}
+
+impl Geofence {
+  /// Aircraft with no altitude are counted as inside any altitude band.
+  pub fn contains(&self, position: &Position, altitude: Option<f64>) -> bool {
+    let in_band: bool = altitude.is_none_or(|feet| self.floor.is_none_or(|floor| feet >= floor) && self.ceiling.is_none_or(|ceiling| feet <= ceiling));
+    in_band && match &self.shape {
+      FenceShape::Circle { center, radius_miles } => center.distance_miles(position) <= *radius_miles,
+      FenceShape::Polygon(boundary) => polygon_contains(boundary, position),
+    }
+  }
+
+  fn outline(&self) -> Vec<Position> {
+    match &self.shape {
+      FenceShape::Circle { center, radius_miles } => (0..=CIRCLE_SEGMENTS)
+        .map(|segment| center.destination(360.0 * segment as f64 / CIRCLE_SEGMENTS as f64, *radius_miles))
+        .collect(),
+      FenceShape::Polygon(boundary) => boundary.iter().chain(boundary.first()).copied().collect(),
+    }
+  }
+
+  /// Draws the outline into the canvas, returning a name label for its northernmost point.
+  pub fn draw(&self, canvas: &mut BrailleCanvas, args: &FRadarArgs, color: Color) -> Option<(Coord<f64>, String)> {
+    let outline: Vec<Position> = self.outline();
+    canvas.styled_polyline(&outline, args, color, Stroke::Dashed);
+
+    let top: &Position = outline.iter().max_by(|a, b| a.lat.total_cmp(&b.lat))?;
+    let coord: Coord<f64> = top.as_terminal_coord_unclamped(args);
+    let label: Coord<f64> = Coord { col: coord.col - self.name.chars().count() as f64 / 2.0, row: coord.row - 1.0 };
+    let fits: bool = label.col >= 1.0 && label.row >= 1.0 &&
+      label.col + (self.name.chars().count() as f64) < args.terminal_cols as f64 - 1.0 && label.row < args.terminal_rows as f64 - 1.0;
+    fits.then(|| (label, self.name.clone()))
+  }
+
+  /// One-line summary for `:fence`.
+  pub fn describe(&self, args: &FRadarArgs) -> String {
+    let shape: String = match &self.shape {
+      FenceShape::Circle { radius_miles, .. } => format!("{} circle", args.units.format_distance(*radius_miles)),
+      FenceShape::Polygon(boundary) => format!("{}-point polygon", boundary.len()),
+    };
+    let band: String = match (self.floor, self.ceiling) {
+      (None, None) => String::new(),
+      (floor, ceiling) => format!(
+        ", {}-{}",
+        floor.map(|feet| format!("{:.0}ft", feet)).unwrap_or_else(|| "SFC".to_string()),
+        ceiling.map(|feet| format!("{:.0}ft", feet)).unwrap_or_else(|| "UNL".to_string()),
+      ),
+    };
+    let dwell: String = self.dwell.map(|dwell| format!(", dwell {:?}", dwell)).unwrap_or_default();
+    format!("{} ({}{}{})", self.name, shape, band, dwell)
+  }
+}
+
+/// Parses an altitude band in feet such as `0-3000`, `-3000` (up to) or `10000-` (from).
+pub fn parse_altitude_band(text: &str) -> anyhow::Result<(Option<f64>, Option<f64>)> {
+  let (floor, ceiling) = text.split_once('-').ok_or_else(|| anyhow!("expected an altitude band like 0-3000, got '{}'", text))?;
+  let parse = |value: &str| -> anyhow::Result<Option<f64>> {
+    match value.trim().trim_end_matches("ft") {
+      "" => Ok(None),
+      value => value.parse::<f64>().map(Some).map_err(|_| anyhow!("invalid altitude '{}'", value)),
+    }
+  };
+  let (floor, ceiling) = (parse(floor)?, parse(ceiling)?);
+  if let (Some(floor), Some(ceiling)) = (floor, ceiling) && floor > ceiling {
+    bail!("floor {} is above ceiling {}", floor, ceiling);
+  }
+  Ok((floor, ceiling))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenceEventKind {
+  Enter,
+  Exit,
+  Dwell,
+}
+
+impl FenceEventKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      FenceEventKind::Enter => "ENTER",
+      FenceEventKind::Exit => "EXIT",
+      FenceEventKind::Dwell => "DWELL",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenceEvent {
+  pub kind: FenceEventKind,
+  pub fence: String,
+  pub condition: Condition,
+}
+
+impl FenceEvent {
+  pub fn message(&self) -> String {
+    let verb: &str = match self.kind {
+      FenceEventKind::Enter => "entered",
+      FenceEventKind::Exit => "left",
+      FenceEventKind::Dwell => "is dwelling in",
+    };
+    format!("{} {} {}", self.condition.callsign, verb, self.fence)
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Visit {
+  entered_millis: i64,
+  seen_millis: i64,
+  last: Condition,
+  dwell_reported: bool,
+}
+
+/// The fences in force and which aircraft are inside each of them.
+#[derive(Debug, Clone, Default)]
+pub struct Geofences {
+  pub fences: Vec<Geofence>,
+  /// Shell command run for every event, with the details in `FRADAR_*` variables.
+  pub hook: Option<String>,
+  /// Keyed by fence name and aircraft hex.
+  visits: HashMap<(String, String), Visit>,
+}
+
+impl Geofences {
+  pub fn new(fences: Vec<Geofence>, hook: Option<String>) -> Geofences {
+    Geofences { fences, hook, visits: HashMap::new() }
+  }
+
+  pub fn find(&self, name: &str) -> Option<&Geofence> {
+    self.fences.iter().find(|fence| fence.name.eq_ignore_ascii_case(name))
+  }
+
+  /// Adds a fence, replacing any with the same name.
+  pub fn insert(&mut self, fence: Geofence) {
+    self.remove(&fence.name);
+    self.fences.push(fence);
+  }
+
+  pub fn remove(&mut self, name: &str) -> bool {
+    let count: usize = self.fences.len();
+    self.fences.retain(|fence| !fence.name.eq_ignore_ascii_case(name));
+    self.visits.retain(|(fence, _), _| !fence.eq_ignore_ascii_case(name));
+    self.fences.len() != count
+  }
+
+  /// Takes the visits from a copy that was updated without holding the lock, except those
+  /// for fences that were removed or redefined in the meantime.
+  pub fn adopt_visits(&mut self, updated: Geofences) {
+    let fences: &[Geofence] = &self.fences;
+    self.visits = updated.visits.into_iter()
+      .filter(|((name, _), _)| fences.iter().any(|fence| fence.name == *name && updated.fences.contains(fence)))
+      .collect();
+  }
+
+  /// Updates who is inside which fence, returning the crossings and overstays since the last update.
+  pub fn update(&mut self, flights_data: &FlightData) -> Vec<FenceEvent> {
+    let now: i64 = flights_data.epoch_timestamp;
+    let mut events: Vec<FenceEvent> = Vec::new();
+
+    for fence in self.fences.iter() {
+      for (info, (position, _)) in flights_data.aircraft.iter().zip(flights_data.flights.iter()) {
+        let condition: Condition = Condition::new(info, *position, format!("fence {}", fence.name));
+        let key: (String, String) = (fence.name.clone(), info.hex.clone());
+        let inside: bool = fence.contains(position, condition.altitude);
+
+        match (inside, self.visits.get_mut(&key)) {
+          (true, Some(visit)) => {
+            visit.seen_millis = now;
+            visit.last = condition;
+            if let Some(dwell) = fence.dwell && !visit.dwell_reported && now - visit.entered_millis >= dwell.as_millis() as i64 {
+              visit.dwell_reported = true;
+              events.push(FenceEvent { kind: FenceEventKind::Dwell, fence: fence.name.clone(), condition: visit.last.clone() });
+            }
+          },
+          (true, None) => {
+            events.push(FenceEvent { kind: FenceEventKind::Enter, fence: fence.name.clone(), condition: condition.clone() });
+            self.visits.insert(key, Visit { entered_millis: now, seen_millis: now, last: condition, dwell_reported: false });
+          },
+          (false, Some(_)) => {
+            self.visits.remove(&key);
+            events.push(FenceEvent { kind: FenceEventKind::Exit, fence: fence.name.clone(), condition });
+          },
+          (false, None) => {},
+        }
+      }
+    }
+
+    // Aircraft that drop out of the data are let go once they have been gone a while.
+    self.visits.retain(|(fence, _), visit| {
+      if now - visit.seen_millis < MISSING_GRACE_MILLIS {
+        return true;
+      }
+      events.push(FenceEvent { kind: FenceEventKind::Exit, fence: fence.clone(), condition: visit.last.clone() });
+      false
+    });
+
+    events
+  }
+}
+
+/// Runs the hook for one event in the background. Failures are ignored: a broken hook
+/// must not stop the scope.
+pub fn run_hook(hook: &str, event: &FenceEvent) {
+  let condition: &Condition = &event.condition;
+  let child = Command::new("sh")
+    .arg("-c")
+    .arg(hook)
+    .env("FRADAR_EVENT", event.kind.name())
+    .env("FRADAR_FENCE", &event.fence)
+    .env("FRADAR_HEX", &condition.hex)
+    .env("FRADAR_CALLSIGN", &condition.callsign)
+    .env("FRADAR_LAT", format!("{:.5}", condition.position.lat))
+    .env("FRADAR_LON", format!("{:.5}", condition.position.long))
+    .env("FRADAR_ALT", condition.altitude.map(|feet| format!("{:.0}", feet)).unwrap_or_default())
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn();
+
+  // Reap the child so finished hooks don't linger as zombies.
+  if let Ok(mut child) = child {
+    std::thread::spawn(move || child.wait());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{aircraft, flights_data};
+
+  const CENTER: Position = Position { lat: 47.45, long: -122.3 };
+
+  fn fences(dwell: Option<Duration>) -> Geofences {
+    let fence: Geofence = Geofence {
+      name: "field".to_string(),
+      shape: FenceShape::Circle { center: CENTER, radius_miles: 5.0 },
+      floor: None,
+      ceiling: Some(3000.0),
+      dwell,
+    };
+    Geofences::new(Vec::from([fence]), None)
+  }
+
+  /// Where one aircraft is at `now`, `miles` east of the centre.
+  fn update(geofences: &mut Geofences, now: i64, miles: f64, altitude: i32) -> Vec<FenceEventKind> {
+    let info = aircraft("abc123", &CENTER.destination(90.0, miles), altitude);
+    geofences.update(&flights_data(now, vec![info])).into_iter().map(|event| event.kind).collect()
+  }
+
+  #[test]
+  fn reports_entering_and_leaving() {
+    let mut geofences: Geofences = fences(None);
+    assert_eq!(update(&mut geofences, 0, 6.0, 2000), []);
+    assert_eq!(update(&mut geofences, 1000, 4.0, 2000), [FenceEventKind::Enter]);
+    assert_eq!(update(&mut geofences, 2000, 3.0, 2000), []);
+    assert_eq!(update(&mut geofences, 3000, 3.0, 4000), [FenceEventKind::Exit], "climbed out of the band");
+    assert_eq!(update(&mut geofences, 4000, 3.0, 2500), [FenceEventKind::Enter]);
+
+    let events: Vec<FenceEvent> = geofences.update(&flights_data(5000, vec![aircraft("abc123", &CENTER.destination(90.0, 7.0), 2500)]));
+    assert_eq!(events[0].message(), "abc123 left field");
+  }
+
+  #[test]
+  fn reports_a_dwell_once() {
+    let mut geofences: Geofences = fences(Some(Duration::from_secs(60)));
+    assert_eq!(update(&mut geofences, 0, 1.0, 1000), [FenceEventKind::Enter]);
+    assert_eq!(update(&mut geofences, 59_000, 1.0, 1000), []);
+    assert_eq!(update(&mut geofences, 60_000, 1.0, 1000), [FenceEventKind::Dwell]);
+    assert_eq!(update(&mut geofences, 120_000, 1.0, 1000), []);
+  }
+
+  #[test]
+  fn lets_lost_aircraft_go_after_the_grace_period() {
+    let mut geofences: Geofences = fences(None);
+    assert_eq!(update(&mut geofences, 0, 1.0, 1000), [FenceEventKind::Enter]);
+    assert!(geofences.update(&flights_data(MISSING_GRACE_MILLIS - 1, Vec::new())).is_empty());
+
+    let events: Vec<FenceEvent> = geofences.update(&flights_data(MISSING_GRACE_MILLIS, Vec::new()));
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].kind, events[0].condition.altitude), (FenceEventKind::Exit, Some(1000.0)), "last seen inside");
+    assert_eq!(update(&mut geofences, MISSING_GRACE_MILLIS + 1000, 1.0, 1000), [FenceEventKind::Enter]);
+  }
+
+  #[test]
+  fn drops_visits_to_fences_changed_meanwhile() {
+    let mut shared: Geofences = fences(None);
+    let mut updated: Geofences = shared.clone();
+    assert_eq!(update(&mut updated, 0, 1.0, 1000), [FenceEventKind::Enter]);
+
+    shared.adopt_visits(updated.clone());
+    assert_eq!(update(&mut shared, 1000, 1.0, 1000), []);
+
+    shared.insert(Geofence { ceiling: None, ..shared.fences[0].clone() });
+    shared.adopt_visits(updated);
+    assert_eq!(update(&mut shared, 2000, 1.0, 1000), [FenceEventKind::Enter]);
+  }
+
+  #[test]
+  fn parses_altitude_bands() {
+    assert_eq!(parse_altitude_band("0-3000").unwrap(), (Some(0.0), Some(3000.0)));
+    assert_eq!(parse_altitude_band("-3000ft").unwrap(), (None, Some(3000.0)));
+    assert_eq!(parse_altitude_band("10000- ").unwrap(), (Some(10000.0), None));
+    assert_eq!(parse_altitude_band("-").unwrap(), (None, None));
+
+    assert_eq!(parse_altitude_band("3000").unwrap_err().to_string(), "expected an altitude band like 0-3000, got '3000'");
+    assert_eq!(parse_altitude_band("low-3000").unwrap_err().to_string(), "invalid altitude 'low'");
+    assert_eq!(parse_altitude_band("5000-3000").unwrap_err().to_string(), "floor 5000 is above ceiling 3000");
+  }
+}
@@ -0,0 +1,401 @@
+use std::{collections::HashMap, path::Path, sync::mpsc::{self, Sender}, thread::JoinHandle};
+
+use anyhow::{anyhow, bail, Context};
+use rusqlite::{params, Connection, OpenFlags, Transaction};
+
+use crate::{aircraft_list::altitude_feet, model::{ADSBAircraftInformation, Position, MILES_PER_NM}};
+
+
+/// An airframe unseen for this long starts a new sighting when it comes back.
+pub const SIGHTING_GAP_SECONDS: i64 = 600;
+
+/// Track points are kept at most this often.
+const TRACK_INTERVAL_SECONDS: i64 = 10;
+
+const SCHEMA_VERSION: i64 = 1;
+
+/// One row per sighting, and one row in `track_points` per point of its track. Times are
+/// Unix seconds (UTC), distances are nautical miles from the starting origin, and
+/// altitudes are feet.
+const SCHEMA: &str = "
+  CREATE TABLE IF NOT EXISTS sightings (
+    id                 INTEGER PRIMARY KEY,
+    hex                TEXT NOT NULL,
+    registration       TEXT,
+    callsign           TEXT,
+    type               TEXT,
+    first_seen         INTEGER NOT NULL,
+    last_seen          INTEGER NOT NULL,
+    min_distance_nm    REAL NOT NULL,
+    closest_time       INTEGER NOT NULL,
+    closest_altitude_ft REAL,
+    min_altitude_ft    REAL
+  );
+  CREATE TABLE IF NOT EXISTS track_points (
+    sighting_id        INTEGER NOT NULL REFERENCES sightings (id),
+    time               INTEGER NOT NULL,
+    lat                REAL NOT NULL,
+    long               REAL NOT NULL,
+    altitude_ft        REAL
+  );
+  CREATE INDEX IF NOT EXISTS sightings_hex ON sightings (hex);
+  CREATE INDEX IF NOT EXISTS sightings_first_seen ON sightings (first_seen);
+  CREATE INDEX IF NOT EXISTS sightings_last_seen ON sightings (last_seen);
+  CREATE INDEX IF NOT EXISTS track_points_sighting ON track_points (sighting_id, time);
+";
+
+/// Latitude, longitude, altitude (feet) and Unix time.
+pub type TrackPoint = (f64, f64, Option<f64>, i64);
+
+/// One aircraft's passage, as stored in the logbook or rebuilt from a recording.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sighting {
+  pub hex: String,
+  pub registration: Option<String>,
+  pub callsign: Option<String>,
+  pub aircraft_type: Option<String>,
+  pub track: Vec<TrackPoint>,
+}
+
+/// A sighting still being added to, as last written to its row.
+struct Visit {
+  id: i64,
+  registration: Option<String>,
+  callsign: Option<String>,
+  aircraft_type: Option<String>,
+  last_seen: i64,
+  min_distance_nm: f64,
+  closest_time: i64,
+  closest_altitude_ft: Option<f64>,
+  min_altitude_ft: Option<f64>,
+  /// When the newest point in `track_points` was seen.
+  last_point_time: i64,
+  /// The newest point, held back until the track interval is up or the sighting ends.
+  pending: Option<TrackPoint>,
+}
+
+impl Visit {
+  fn add_point(&mut self, transaction: &Transaction, point: TrackPoint) -> anyhow::Result<()> {
+    transaction.execute(
+      "INSERT INTO track_points (sighting_id, time, lat, long, altitude_ft) VALUES (?1, ?2, ?3, ?4, ?5)",
+      params![self.id, point.3, point.0, point.1, point.2],
+    )?;
+    self.last_point_time = point.3;
+    self.pending = None;
+    Ok(())
+  }
+
+  fn update(&self, transaction: &Transaction) -> anyhow::Result<()> {
+    transaction.execute(
+      "UPDATE sightings SET
+        registration = ?2, callsign = ?3, type = ?4,
+        last_seen = ?5, min_distance_nm = ?6, closest_time = ?7, closest_altitude_ft = ?8, min_altitude_ft = ?9
+      WHERE id = ?1",
+      params![
+        self.id, self.registration, self.callsign, self.aircraft_type,
+        self.last_seen, self.min_distance_nm, self.closest_time, self.closest_altitude_ft, self.min_altitude_ft,
+      ],
+    )?;
+    Ok(())
+  }
+
+  /// Writes the held-back point and the final `last_seen`.
+  fn close(mut self, transaction: &Transaction) -> anyhow::Result<()> {
+    if let Some(point) = self.pending {
+      self.add_point(transaction, point)?;
+    }
+    self.update(transaction)
+  }
+}
+
+/// Every aircraft fradar has seen, kept in a SQLite database.
+pub struct Logbook {
+  connection: Connection,
+  open: HashMap<String, Visit>,
+}
+
+impl Logbook {
+  pub fn open(path: &Path) -> anyhow::Result<Logbook> {
+    if let Some(parent) = path.parent() && !parent.as_os_str().is_empty() {
+      std::fs::create_dir_all(parent).with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let connection: Connection = Connection::open(path).with_context(|| format!("failed to open logbook {}", path.display()))?;
+    Logbook::with_connection(connection).with_context(|| format!("failed to set up logbook {}", path.display()))
+  }
+
+  /// Creates the tables if they aren't there yet.
+  fn with_connection(mut connection: Connection) -> anyhow::Result<Logbook> {
+    let version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+      bail!("written by a newer fradar (version {})", version);
+    }
+
+    let transaction: Transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    transaction.commit()?;
+
+    Ok(Logbook { connection, open: HashMap::new() })
+  }
+
+  /// Adds one update's worth of aircraft, extending their open sightings or starting new
+  /// ones. A sighting's row is only rewritten when its summary changes or a track point is
+  /// added, so `last_seen` may trail by up to the track interval until the sighting ends.
+  pub fn record(&mut self, aircraft: &[ADSBAircraftInformation], origin: &Position, now: i64) -> anyhow::Result<()> {
+    let transaction: Transaction = self.connection.transaction()?;
+
+    let gone: Vec<String> = self.open.iter()
+      .filter(|(_, visit)| now - visit.last_seen > SIGHTING_GAP_SECONDS)
+      .map(|(hex, _)| hex.clone())
+      .collect();
+    for hex in gone.iter() {
+      if let Some(visit) = self.open.remove(hex) {
+        visit.close(&transaction)?;
+      }
+    }
+
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+    for info in aircraft.iter() {
+      let position: Position = Position { lat: info.lat, long: info.lon };
+      let distance_nm: f64 = origin.distance_miles(&position) / MILES_PER_NM;
+      let altitude_ft: Option<f64> = altitude_feet(info);
+      let point: TrackPoint = ((position.lat * 1e5).round() / 1e5, (position.long * 1e5).round() / 1e5, altitude_ft, now);
+
+      let Some(visit) = self.open.get_mut(&info.hex) else {
+        transaction.execute(
+          "INSERT INTO sightings (hex, registration, callsign, type, first_seen, last_seen, min_distance_nm, closest_time, closest_altitude_ft, min_altitude_ft)
+          VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6, ?5, ?7, ?7)",
+          params![info.hex, text(&info.r), text(&info.flight), text(&info.t), now, distance_nm, altitude_ft],
+        )?;
+        let mut visit: Visit = Visit {
+          id: transaction.last_insert_rowid(),
+          registration: text(&info.r),
+          callsign: text(&info.flight),
+          aircraft_type: text(&info.t),
+          last_seen: now,
+          min_distance_nm: distance_nm,
+          closest_time: now,
+          closest_altitude_ft: altitude_ft,
+          min_altitude_ft: altitude_ft,
+          last_point_time: now,
+          pending: None,
+        };
+        visit.add_point(&transaction, point)?;
+        self.open.insert(info.hex.clone(), visit);
+        continue;
+      };
+
+      visit.last_seen = now;
+      let mut changed: bool = false;
+
+      // Identity fields keep the first value seen, since some messages arrive without them.
+      for (field, value) in [(&mut visit.registration, &info.r), (&mut visit.callsign, &info.flight), (&mut visit.aircraft_type, &info.t)] {
+        if field.is_none() && let Some(value) = text(value) {
+          *field = Some(value);
+          changed = true;
+        }
+      }
+
+      let closer: bool = distance_nm < visit.min_distance_nm;
+      if closer {
+        (visit.min_distance_nm, visit.closest_time, visit.closest_altitude_ft) = (distance_nm, now, altitude_ft);
+        changed = true;
+      }
+      let min_altitude_ft: Option<f64> = [visit.min_altitude_ft, altitude_ft].into_iter().flatten().reduce(f64::min);
+      if min_altitude_ft != visit.min_altitude_ft {
+        visit.min_altitude_ft = min_altitude_ft;
+        changed = true;
+      }
+
+      // The closest approach is always kept so reports don't miss it between track points.
+      if closer || now - visit.last_point_time >= TRACK_INTERVAL_SECONDS {
+        visit.add_point(&transaction, point)?;
+        changed = true;
+      } else {
+        visit.pending = Some(point);
+      }
+
+      if changed {
+        visit.update(&transaction)?;
+      }
+    }
+    transaction.commit()?;
+
+    Ok(())
+  }
+
+  /// Ends every open sighting, writing out their last points.
+  pub fn close(&mut self) -> anyhow::Result<()> {
+    let transaction: Transaction = self.connection.transaction()?;
+    for (_, visit) in self.open.drain() {
+      visit.close(&transaction)?;
+    }
+    transaction.commit()?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+impl Logbook {
+  pub fn in_memory() -> Logbook {
+    Logbook::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+  }
+
+  /// What [`read_sightings`] would find in this logbook.
+  pub fn sightings(&self, since: Option<i64>, until: Option<i64>) -> anyhow::Result<Vec<Sighting>> {
+    sightings_between(&self.connection, since, until)
+  }
+}
+
+/// Runs a logbook on a thread of its own, so the controller never waits on SQLite.
+pub struct LogbookWriter {
+  sender: Sender<(Vec<ADSBAircraftInformation>, Position, i64)>,
+  thread: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl LogbookWriter {
+  pub fn spawn(mut logbook: Logbook) -> LogbookWriter {
+    let (sender, receiver) = mpsc::channel::<(Vec<ADSBAircraftInformation>, Position, i64)>();
+    let thread: JoinHandle<anyhow::Result<()>> = std::thread::spawn(move || {
+      for (aircraft, origin, now) in receiver.iter() {
+        logbook.record(&aircraft, &origin, now)?;
+      }
+      logbook.close()
+    });
+    LogbookWriter { sender, thread: Some(thread) }
+  }
+
+  /// Queues an update for [`Logbook::record`]. Once the writer has stopped, returns the
+  /// error it stopped on.
+  pub fn record(&mut self, aircraft: Vec<ADSBAircraftInformation>, origin: Position, now: i64) -> anyhow::Result<()> {
+    if self.sender.send((aircraft, origin, now)).is_ok() {
+      return Ok(());
+    }
+    Err(match self.thread.take().map(JoinHandle::join) {
+      Some(Ok(Err(error))) => error,
+      Some(Err(_)) => anyhow!("logbook writer panicked"),
+      _ => anyhow!("logbook writer stopped"),
+    })
+  }
+
+  /// Closes the open sightings and waits for everything queued to be written.
+  pub fn finish(self) -> anyhow::Result<()> {
+    drop(self.sender);
+    match self.thread {
+      Some(thread) => thread.join().map_err(|_| anyhow!("logbook writer panicked"))?,
+      None => Ok(()),
+    }
+  }
+}
+
+/// Reads the sightings that overlap `since..until` (Unix seconds) without opening the logbook for writing.
+pub fn read_sightings(path: &Path, since: Option<i64>, until: Option<i64>) -> anyhow::Result<Vec<Sighting>> {
+  let connection: Connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+    .with_context(|| format!("failed to open logbook {}", path.display()))?;
+  let version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+  if version != SCHEMA_VERSION {
+    bail!("logbook {} is version {}, expected {}", path.display(), version, SCHEMA_VERSION);
+  }
+  sightings_between(&connection, since, until).with_context(|| format!("failed to read logbook {}", path.display()))
+}
+
+fn sightings_between(connection: &Connection, since: Option<i64>, until: Option<i64>) -> anyhow::Result<Vec<Sighting>> {
+  let mut statement = connection.prepare(
+    "SELECT id, hex, registration, callsign, type FROM sightings WHERE last_seen >= ?1 AND first_seen < ?2 ORDER BY first_seen",
+  )?;
+  let mut points = connection.prepare("SELECT lat, long, altitude_ft, time FROM track_points WHERE sighting_id = ?1 ORDER BY time")?;
+
+  let rows = statement.query_map(params![since.unwrap_or(i64::MIN), until.unwrap_or(i64::MAX)], |row| {
+    Ok((row.get::<_, i64>(0)?, Sighting { hex: row.get(1)?, registration: row.get(2)?, callsign: row.get(3)?, aircraft_type: row.get(4)?, track: Vec::new() }))
+  })?;
+
+  rows.map(|row| {
+    let (id, mut sighting) = row?;
+    sighting.track = points.query_map([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+      .collect::<Result<Vec<TrackPoint>, rusqlite::Error>>()?;
+    Ok(sighting)
+  }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures;
+
+  const ORIGIN: Position = Position { lat: 47.0, long: -122.0 };
+
+  /// N123, `north_nm` north of the origin.
+  fn aircraft(hex: &str, north_nm: f64, altitude: i32) -> ADSBAircraftInformation {
+    let position: Position = Position { lat: ORIGIN.lat + north_nm / 60.0, long: ORIGIN.long };
+    ADSBAircraftInformation { flight: Some("N123  ".to_string()), ..fixtures::aircraft(hex, &position, altitude) }
+  }
+
+  #[test]
+  fn tracks_a_sighting_between_updates() {
+    let mut logbook: Logbook = Logbook::in_memory();
+    // Flies north past the origin, one update every four seconds.
+    for (step, north_nm) in [-3.0, -2.0, -1.0, 0.5, 1.5, 2.5].into_iter().enumerate() {
+      logbook.record(&[aircraft("abc123", north_nm, 3000 - step as i32 * 100)], &ORIGIN, 1000 + step as i64 * 4).unwrap();
+    }
+    logbook.close().unwrap();
+
+    let (last_seen, min_distance_nm, closest_time, min_altitude_ft): (i64, f64, i64, f64) = logbook.connection
+      .query_row("SELECT last_seen, min_distance_nm, closest_time, min_altitude_ft FROM sightings", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+      .unwrap();
+    assert_eq!(last_seen, 1020);
+    assert!((min_distance_nm - 0.5).abs() < 0.01, "{}", min_distance_nm);
+    assert_eq!(closest_time, 1012);
+    assert_eq!(min_altitude_ft, 2500.0);
+
+    let sightings: Vec<Sighting> = logbook.sightings(None, None).unwrap();
+    assert_eq!(sightings.len(), 1);
+    assert_eq!(sightings[0].callsign.as_deref(), Some("N123"));
+    // Every closer point, then the last one, held back until the sighting closed.
+    let times: Vec<i64> = sightings[0].track.iter().map(|point| point.3).collect();
+    assert_eq!(times, [1000, 1004, 1008, 1012, 1020]);
+  }
+
+  #[test]
+  fn leaves_unchanged_rows_alone() {
+    let mut logbook: Logbook = Logbook::in_memory();
+    logbook.record(&[aircraft("abc123", 2.0, 3000)], &ORIGIN, 1000).unwrap();
+    let changes: u64 = logbook.connection.total_changes();
+    logbook.record(&[aircraft("abc123", 2.0, 3000)], &ORIGIN, 1001).unwrap();
+    logbook.record(&[aircraft("abc123", 2.0, 3000)], &ORIGIN, 1002).unwrap();
+    assert_eq!(logbook.connection.total_changes(), changes);
+
+    // A new identity field is a change.
+    logbook.record(&[ADSBAircraftInformation { t: Some("C172".to_string()), ..aircraft("abc123", 2.0, 3000) }], &ORIGIN, 1003).unwrap();
+    assert_eq!(logbook.connection.total_changes(), changes + 1);
+  }
+
+  #[test]
+  fn starts_a_new_sighting_after_a_gap() {
+    let mut logbook: Logbook = Logbook::in_memory();
+    logbook.record(&[aircraft("abc123", 2.0, 3000)], &ORIGIN, 1000).unwrap();
+    logbook.record(&[aircraft("abc123", 2.0, 3000)], &ORIGIN, 1005).unwrap();
+    logbook.record(&[aircraft("abc123", 1.0, 3000)], &ORIGIN, 1005 + SIGHTING_GAP_SECONDS + 1).unwrap();
+    logbook.close().unwrap();
+
+    let sightings: Vec<Sighting> = logbook.sightings(None, None).unwrap();
+    let tracks: Vec<Vec<i64>> = sightings.iter().map(|sighting| sighting.track.iter().map(|point| point.3).collect()).collect();
+    assert_eq!(tracks, [vec![1000, 1005], vec![1005 + SIGHTING_GAP_SECONDS + 1]]);
+    assert_eq!(logbook.sightings(Some(1100), None).unwrap().len(), 1);
+  }
+
+  #[test]
+  fn writes_from_its_own_thread() {
+    let path = std::env::temp_dir().join(format!("fradar-logbook-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut writer: LogbookWriter = LogbookWriter::spawn(Logbook::open(&path).unwrap());
+    writer.record(vec![aircraft("abc123", 2.0, 3000)], ORIGIN, 1000).unwrap();
+    writer.record(vec![aircraft("abc123", 1.0, 3000)], ORIGIN, 1001).unwrap();
+    writer.record(vec![aircraft("abc123", 1.5, 3000)], ORIGIN, 1002).unwrap();
+    writer.finish().unwrap();
+
+    let sightings: Vec<Sighting> = read_sightings(&path, None, None).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(sightings[0].track.iter().map(|point| point.3).collect::<Vec<i64>>(), [1000, 1001, 1002]);
+  }
+}
@@ -1,60 +1,197 @@
-use std::{collections::VecDeque, sync::{Arc, Mutex}, time::Duration};
+use std::{collections::VecDeque, io::IsTerminal, sync::{Arc, Mutex}};
 
+use airports::AirportDb;
+use airspace::{load_openair, Airspace};
+use alerts::Alerts;
+use clap::Parser;
+use config::{load_settings, Cli, CliCommand, Settings};
 use controller::controller_thread;
 use crossterm::terminal::size;
-use model::{FlightData, Position};
+use geofence::Geofences;
+use logbook::{Logbook, LogbookWriter};
+use model::FlightData;
+use overlays::Overlay;
+use server::Server;
+use snapshot::SnapshotFormat;
+use ticker::Tracker;
 use view::view_thread;
+use watchlist::Watchlist;
 
-use crate::{event_dispatcher::event_dispatch_thread, model::{FRadarArgs, FRadarData, FRadarState}};
+use crate::{event_dispatcher::event_dispatch_thread, model::{FRadarArgs, FRadarData, FRadarState, FRadarUi}};
 
+mod aircraft_list;
+mod airports;
+mod airspace;
+mod alerts;
+mod bookmarks;
+mod canvas;
+mod command;
 mod config;
+mod conflicts;
 mod controller;
 mod event_dispatcher;
+mod filter;
+#[cfg(test)]
+mod fixtures;
+mod geofence;
+mod logbook;
 mod model;
+mod overlays;
+mod report;
+mod scope;
+mod server;
+mod session;
+mod snapshot;
+mod spotter;
+mod ticker;
 mod view;
+mod watchlist;
 
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     
-    // TODO: add cli parsing through `config.rs`
-    let command_line_args: FRadarArgs = FRadarArgs {
-        origin: Position {
-            lat: 37.6191,
-            long: -122.3816,
+    let cli: Cli = Cli::parse();
+    let settings: Settings = match load_settings(&cli) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("fradar: {}", error);
+            std::process::exit(2);
         },
-        radius: 50.0,
+    };
+
+    if let Some(CliCommand::Report(report)) = &cli.command {
+        if let Err(error) = report::run(report, &settings) {
+            eprintln!("fradar: {:#}", error);
+            std::process::exit(2);
+        }
+        return Ok(());
+    }
+
+    if cli.once {
+        let format: SnapshotFormat = match cli.format.as_deref().map(str::parse).transpose() {
+            Ok(format) => format.unwrap_or_default(),
+            Err(error) => {
+                eprintln!("fradar: --format: {}", error);
+                std::process::exit(2);
+            },
+        };
+        if let Err(error) = snapshot::run(&settings, format).await {
+            eprintln!("fradar: {:#}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-        starting_origin: Position {
-            lat: 37.6191,
-            long: -122.3816,
+    let watchlist: Watchlist = match &settings.watchlist_path {
+        Some(path) => match Watchlist::load(path) {
+            Ok(watchlist) => watchlist,
+            Err(error) => {
+                eprintln!("fradar: {:#}", error);
+                std::process::exit(2);
+            },
         },
+        None => Watchlist::default(),
+    };
 
-        data_interval: Duration::from_millis((1.0 / 1.0 * 1000.0) as u64),
-        frame_interval: Duration::from_millis((1.0 / 10.0 * 1000.0) as u64),
-        event_interval: Duration::from_millis(100),
+    let server: Option<Server> = match settings.serve_address {
+        Some(address) => {
+            let server: Server = Server::new(settings.args.data_interval);
+            if let Err(error) = server.start(address).await {
+                eprintln!("fradar: {:#}", error);
+                std::process::exit(2);
+            }
+            Some(server)
+        },
+        None => None,
+    };
 
-        terminal_cols: size()?.0,
-        terminal_rows: size()?.1,
+    let logbook: Option<LogbookWriter> = match settings.logbook_path.as_deref().map(Logbook::open) {
+        Some(Ok(logbook)) => Some(LogbookWriter::spawn(logbook)),
+        Some(Err(error)) => {
+            eprintln!("fradar: {:#}", error);
+            std::process::exit(2);
+        },
+        None => None,
+    };
+
+    // Piped output gets the ticker, since there is no terminal to draw the scope on.
+    if cli.ticker || !std::io::stdout().is_terminal() {
+        if let Err(error) = ticker::run(&settings, &watchlist, server, logbook).await {
+            eprintln!("fradar: {:#}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let airports: AirportDb = match &settings.airports_path {
+        Some(path) => match AirportDb::load(path, settings.runways_path.as_deref()) {
+            Ok(airports) => airports,
+            Err(error) => {
+                eprintln!("fradar: {:#}", error);
+                std::process::exit(2);
+            },
+        },
+        None => AirportDb::default(),
+    };
 
-        terminal_edge_margins: 3,
+    let overlays: Vec<Overlay> = match settings.overlay_paths.iter().map(|path| Overlay::load(path)).collect() {
+        Ok(overlays) => overlays,
+        Err(error) => {
+            eprintln!("fradar: {:#}", error);
+            std::process::exit(2);
+        },
+    };
 
-        label_label_repelling_force: 4.0,
-        label_point_repelling_force: 4.0,
-        label_snapping_radius: 2.0,
+    let mut ui: FRadarUi = settings.ui.clone();
+    let airspaces: Vec<Airspace> = match settings.airspace_paths.iter().map(|path| load_openair(path)).collect::<anyhow::Result<Vec<(Vec<Airspace>, Vec<String>)>>>() {
+        Ok(loaded) => {
+            let (airspaces, warnings): (Vec<Vec<Airspace>>, Vec<Vec<String>>) = loaded.into_iter().unzip();
+            let warnings: Vec<String> = warnings.into_iter().flatten().collect();
+            for warning in warnings.iter() {
+                eprintln!("fradar: {}", warning);
+            }
+            if !warnings.is_empty() {
+                ui.set_status(format!("skipped {} airspace{} that couldn't be read", warnings.len(), if warnings.len() == 1 { "" } else { "s" }));
+            }
+            airspaces.into_iter().flatten().collect()
+        },
+        Err(error) => {
+            eprintln!("fradar: {:#}", error);
+            std::process::exit(2);
+        },
+    };
 
-        history_rolling_limit: 20,
+    let command_line_args: FRadarArgs = FRadarArgs {
+        terminal_cols: size()?.0,
+        terminal_rows: size()?.1,
+        ..settings.args
     };
+    let tracker: Tracker = Tracker::new(settings.altitude_thresholds.clone());
 
     let fradar_data: Arc<Mutex<FRadarData>> = Arc::new(Mutex::new(FRadarData {
         flights_data: Arc::new(Mutex::new(FlightData::default())),
         flights_data_history: VecDeque::default(),
         state: FRadarState::default(),
         args: command_line_args,
+        ui,
+        filter: settings.filter,
+
+        bookmarks: settings.bookmarks,
+        config_path: settings.config_path,
+
+        airports: Arc::new(airports),
+        overlays: Arc::new(overlays),
+        airspaces: Arc::new(airspaces),
+
+        alerts: Alerts { log_path: settings.alert_log_path, ..Alerts::default() },
+        watchlist: Arc::new(watchlist),
+        geofences: Geofences::new(settings.fences, settings.fence_hook),
+        conflicts: Vec::new(),
     }));
 
     let event_dispatch_thread_handle = event_dispatch_thread(fradar_data.clone()).await;    
-    let controller_thread_handle = controller_thread(fradar_data.clone()).await;
+    let controller_thread_handle = controller_thread(fradar_data.clone(), logbook, server, tracker).await;
     let view_thread_handle = view_thread(fradar_data.clone()).await;
 
     event_dispatch_thread_handle.await??;
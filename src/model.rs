@@ -1,10 +1,13 @@
-use std::{collections::VecDeque, sync::{Arc, Mutex}, time::Duration};
+use std::{collections::{BTreeSet, VecDeque}, path::PathBuf, sync::{Arc, Mutex}, time::Duration};
 
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
+use crossterm::style::Color;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+use crate::{airports::AirportDb, airspace::Airspace, alerts::Alerts, bookmarks::Bookmark, conflicts::Conflict, filter::Filter, geofence::Geofences, overlays::Overlay, watchlist::Watchlist};
+
 #[derive(Debug, Clone)]
 pub struct FRadarData {
   pub flights_data: Arc<Mutex<FlightData>>,
@@ -12,6 +15,21 @@ pub struct FRadarData {
 
   pub state: FRadarState,
   pub args: FRadarArgs,
+  pub ui: FRadarUi,
+  pub filter: Option<Filter>,
+
+  pub bookmarks: Vec<Bookmark>,
+  pub config_path: Option<PathBuf>,
+
+  pub airports: Arc<AirportDb>,
+  pub overlays: Arc<Vec<Overlay>>,
+  pub airspaces: Arc<Vec<Airspace>>,
+
+  pub alerts: Alerts,
+  pub watchlist: Arc<Watchlist>,
+  pub geofences: Geofences,
+  /// Conflicts in the current `flights_data`, sorted by time to closest approach.
+  pub conflicts: Vec<Conflict>,
 }
 
 impl FRadarData {
@@ -28,9 +46,156 @@ impl FRadarData {
 pub enum FRadarState {
   #[default]
   Main,
+  Search,
+  Command,
   GracefulKill,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FRadarUi {
+  pub selected_hex: Option<String>,
+  pub show_details: bool,
+  pub follow: bool,
+  pub input_buffer: String,
+
+  pub mouse: Option<Coord<u16>>,
+  pub drag_anchor: Option<(Coord<u16>, Position)>,
+  pub dragged: bool,
+
+  pub list_mode: ListMode,
+  pub list_sort: ListColumn,
+  pub list_descending: bool,
+  pub list_scroll: usize,
+
+  pub command_history: Vec<String>,
+  pub command_history_cursor: Option<usize>,
+  pub status_message: Option<(String, i64)>,
+  pub recording: Option<String>,
+
+  pub transition: Option<Transition>,
+  pub layers: Layers,
+  /// Show the spotter panel of look angles from the origin.
+  pub spotter: bool,
+}
+
+/// Overlay layers that can be switched on and off with `:layer`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Layers {
+  pub airports: bool,
+  pub runways: bool,
+  pub airspace: bool,
+  pub rings: bool,
+  pub scale: bool,
+  pub graticule: bool,
+  pub fences: bool,
+  /// Only draw airspaces whose floor and ceiling bracket the selected aircraft's altitude.
+  pub airspace_at_altitude: bool,
+  /// GeoJSON overlays (by name) that are switched off.
+  pub hidden_overlays: BTreeSet<String>,
+}
+
+impl Default for Layers {
+  fn default() -> Self {
+    Layers {
+      airports: true,
+      runways: true,
+      airspace: true,
+      rings: true,
+      scale: true,
+      graticule: false,
+      fences: true,
+      airspace_at_altitude: false,
+      hidden_overlays: BTreeSet::new(),
+    }
+  }
+}
+
+impl Layers {
+  pub const NAMES: [&str; 7] = ["airports", "runways", "airspace", "rings", "scale", "graticule", "fences"];
+
+  pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+    match name {
+      "airports" => Some(&mut self.airports),
+      "runways" => Some(&mut self.runways),
+      "airspace" => Some(&mut self.airspace),
+      "rings" => Some(&mut self.rings),
+      "scale" => Some(&mut self.scale),
+      "graticule" => Some(&mut self.graticule),
+      "fences" => Some(&mut self.fences),
+      _ => None,
+    }
+  }
+}
+
+/// An animated move of the camera, stepped by the view every frame.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Transition {
+  pub from_origin: Position,
+  pub from_radius: f64,
+  pub to_origin: Position,
+  pub to_radius: f64,
+  pub started_millis: i64,
+  pub duration_millis: i64,
+}
+
+impl Transition {
+  pub fn new(args: &FRadarArgs, to_origin: Position, to_radius: f64) -> Transition {
+    Transition {
+      from_origin: args.origin,
+      from_radius: args.radius,
+      to_origin,
+      to_radius,
+      started_millis: Utc::now().timestamp_millis(),
+      duration_millis: 600,
+    }
+  }
+
+  /// Camera origin and radius at `now_millis`, and whether the transition has finished.
+  pub fn at(&self, now_millis: i64) -> (Position, f64, bool) {
+    let t: f64 = ((now_millis - self.started_millis) as f64 / self.duration_millis.max(1) as f64).clamp(0.0, 1.0);
+    let eased: f64 = t * t * (3.0 - 2.0 * t);
+
+    let origin: Position = Position {
+      lat:  self.from_origin.lat  + (self.to_origin.lat  - self.from_origin.lat)  * eased,
+      long: self.from_origin.long + (self.to_origin.long - self.from_origin.long) * eased,
+    };
+    // Interpolate the radius geometrically so zooming feels uniform.
+    let radius: f64 = self.from_radius * (self.to_radius / self.from_radius).powf(eased);
+
+    (origin, radius, t >= 1.0)
+  }
+}
+
+impl FRadarUi {
+  pub fn set_status(&mut self, message: impl Into<String>) {
+    self.status_message = Some((message.into(), Utc::now().timestamp_millis()));
+  }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ListMode {
+  #[default]
+  Hidden,
+  Split,
+  Full,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ListColumn {
+  Callsign,
+  Registration,
+  Type,
+  Altitude,
+  Speed,
+  VerticalRate,
+  #[default]
+  Distance,
+  Bearing,
+  Squawk,
+  Seen,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct FRadarArgs {
   pub origin: Position,
@@ -52,14 +217,254 @@ pub struct FRadarArgs {
   pub label_snapping_radius: f64,
 
   pub history_rolling_limit: usize,
+  pub trail_duration: Duration,
+  /// How far ahead velocity leaders reach (zero to hide them).
+  pub leader_duration: Duration,
+
+  /// How far ahead to look for conflicts between aircraft (zero to turn detection off).
+  pub conflict_horizon: Duration,
+  /// Separation minima: lateral in miles, vertical in feet.
+  pub conflict_lateral: f64,
+  pub conflict_vertical: f64,
+
+  /// Ground elevation at `starting_origin` in feet, for spotter look angles.
+  pub observer_elevation: f64,
+
+  pub units: Units,
+  pub source: DataSource,
+  pub theme: Theme,
+  pub coords: CoordFormat,
+}
+
+impl FRadarArgs {
+  /// Grows the history enough to draw the whole trail at the current data interval.
+  pub fn fit_history_to_trail(&mut self) {
+    let needed: usize = (self.trail_duration.as_secs_f64() / self.data_interval.as_secs_f64().max(0.001)).ceil() as usize + 1;
+    self.history_rolling_limit = self.history_rolling_limit.max(needed);
+  }
+}
+
+/// Statute miles in one nautical mile.
+pub const MILES_PER_NM: f64 = 1.150779;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Units {
+  #[default]
+  Imperial,
+  Nautical,
+  Metric,
+}
+
+impl Units {
+  pub fn distance(&self, miles: f64) -> f64 {
+    match self {
+      Units::Imperial => miles,
+      Units::Nautical => miles / MILES_PER_NM,
+      Units::Metric => miles / 0.621371,
+    }
+  }
+
+  pub fn distance_unit(&self) -> &'static str {
+    match self {
+      Units::Imperial => "mi",
+      Units::Nautical => "nm",
+      Units::Metric => "km",
+    }
+  }
+
+  pub fn altitude(&self, feet: f64) -> f64 {
+    match self {
+      Units::Imperial | Units::Nautical => feet,
+      Units::Metric => feet * 0.3048,
+    }
+  }
+
+  pub fn altitude_unit(&self) -> &'static str {
+    match self {
+      Units::Imperial | Units::Nautical => "ft",
+      Units::Metric => "m",
+    }
+  }
+
+  pub fn speed(&self, knots: f64) -> f64 {
+    match self {
+      Units::Imperial => knots * MILES_PER_NM,
+      Units::Nautical => knots,
+      Units::Metric => knots * 1.852,
+    }
+  }
+
+  pub fn speed_unit(&self) -> &'static str {
+    match self {
+      Units::Imperial => "mph",
+      Units::Nautical => "kt",
+      Units::Metric => "km/h",
+    }
+  }
+
+  pub fn format_distance(&self, miles: f64) -> String {
+    format!("{:.1} {}", self.distance(miles), self.distance_unit())
+  }
+
+  pub fn format_altitude(&self, feet: f64) -> String {
+    format!("{:.0} {}", self.altitude(feet), self.altitude_unit())
+  }
+
+  pub fn format_speed(&self, knots: f64) -> String {
+    format!("{:.0} {}", self.speed(knots), self.speed_unit())
+  }
+}
+
+/// How positions are written out in the cursor readout and graticule labels.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum CoordFormat {
+  #[default]
+  Dms,
+  Decimal,
+  Maidenhead,
+}
+
+impl CoordFormat {
+  pub fn format(&self, position: &Position) -> String {
+    match self {
+      CoordFormat::Dms => format!("{} {}", Self::dms(position.lat, 'N', 'S'), Self::dms(position.long, 'E', 'W')),
+      CoordFormat::Decimal => format!("{:.5} {:.5}", position.lat, position.long),
+      CoordFormat::Maidenhead => Self::maidenhead(position),
+    }
+  }
+
+  /// A single graticule line value, as short as the spacing allows.
+  pub fn format_line(&self, degrees: f64, spacing: f64, positive: char, negative: char) -> String {
+    let hemisphere: char = if degrees < 0.0 { negative } else { positive };
+    let minutes: f64 = (degrees.abs() * 60.0).round();
+    match (self, spacing >= 1.0) {
+      (CoordFormat::Decimal, _) => format!("{}", (degrees * 1000.0).round() / 1000.0),
+      (_, true) => format!("{}°{}", minutes / 60.0, hemisphere),
+      (_, false) => format!("{}°{:02}'{}", (minutes / 60.0).floor(), minutes % 60.0, hemisphere),
+    }
+  }
+
+  fn dms(degrees: f64, positive: char, negative: char) -> String {
+    let hemisphere: char = if degrees < 0.0 { negative } else { positive };
+    let tenths: u64 = (degrees.abs() * 36000.0).round() as u64;
+    format!("{}°{:02}'{:02}.{}\"{}", tenths / 36000, tenths / 600 % 60, tenths / 10 % 60, tenths % 10, hemisphere)
+  }
+
+  /// Six character Maidenhead locator (field, square, subsquare).
+  fn maidenhead(position: &Position) -> String {
+    let long: f64 = (position.long + 180.0).clamp(0.0, 359.9999);
+    let lat: f64 = (position.lat + 90.0).clamp(0.0, 179.9999);
+    let letter = |base: u8, value: f64| (base + value as u8) as char;
+
+    [
+      letter(b'A', long / 20.0),
+      letter(b'A', lat / 10.0),
+      letter(b'0', long % 20.0 / 2.0),
+      letter(b'0', lat % 10.0),
+      letter(b'a', long % 2.0 * 12.0),
+      letter(b'a', lat % 1.0 * 24.0),
+    ].iter().collect()
+  }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum DataSource {
+  #[default]
+  AdsbLol,
+  AirplanesLive,
+  AdsbOne,
+}
+
+impl DataSource {
+  /// Point query URL; all supported sources speak the same readsb-style v2 API.
+  pub fn point_url(&self, origin: &Position, radius: u32) -> String {
+    let base: &str = match self {
+      DataSource::AdsbLol => "https://api.adsb.lol/v2",
+      DataSource::AirplanesLive => "https://api.airplanes.live/v2",
+      DataSource::AdsbOne => "https://api.adsb.one/v2",
+    };
+    format!("{}/point/{:.4}/{:.4}/{}", base, origin.lat, origin.long, radius)
+  }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Theme {
+  #[default]
+  Classic,
+  Phosphor,
+  Amber,
+  HighContrast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+  pub border: Color,
+  pub aircraft: Color,
+  pub label: Color,
+  pub highlight: Color,
+  pub trail: Color,
+  pub ground: Color,
+  pub map: Color,
+  pub airspace: Color,
+  pub restricted: Color,
+  pub rings: Color,
+  pub alert: Color,
+  pub fence: Color,
+}
+
+impl Theme {
+  pub fn palette(&self) -> Palette {
+    match self {
+      Theme::Classic => Palette { border: Color::Reset, aircraft: Color::Reset, label: Color::Reset, highlight: Color::Yellow, trail: Color::DarkGrey, ground: Color::DarkCyan, map: Color::DarkBlue, airspace: Color::Blue, restricted: Color::Red, rings: Color::DarkGrey, alert: Color::Red, fence: Color::Magenta },
+      Theme::Phosphor => Palette { border: Color::DarkGreen, aircraft: Color::Green, label: Color::Green, highlight: Color::White, trail: Color::DarkGreen, ground: Color::DarkGreen, map: Color::DarkGreen, airspace: Color::DarkGreen, restricted: Color::Green, rings: Color::DarkGreen, alert: Color::White, fence: Color::Green },
+      Theme::Amber => Palette { border: Color::DarkYellow, aircraft: Color::Yellow, label: Color::DarkYellow, highlight: Color::White, trail: Color::DarkYellow, ground: Color::DarkYellow, map: Color::DarkYellow, airspace: Color::DarkYellow, restricted: Color::Yellow, rings: Color::DarkYellow, alert: Color::Red, fence: Color::Yellow },
+      Theme::HighContrast => Palette { border: Color::White, aircraft: Color::White, label: Color::White, highlight: Color::Magenta, trail: Color::Grey, ground: Color::Cyan, map: Color::Grey, airspace: Color::Blue, restricted: Color::Red, rings: Color::DarkGrey, alert: Color::Red, fence: Color::Yellow },
+    }
+  }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct FlightData {
   pub flights: Vec<(Position, Label)>,
+  pub aircraft: Vec<ADSBAircraftInformation>,
   pub epoch_timestamp: i64,
 }
 
+impl FlightData {
+  pub fn index_of_hex(&self, hex: &str) -> Option<usize> {
+    self.aircraft.iter().position(|info| info.hex == hex)
+  }
+
+  pub fn index_of_callsign(&self, callsign: &str) -> Option<usize> {
+    let callsign = callsign.trim().to_uppercase();
+    if callsign.is_empty() {
+      return None;
+    }
+
+    self.aircraft.iter()
+      .position(|info| info.flight.as_deref().unwrap_or_default().trim().to_uppercase() == callsign)
+      .or_else(|| self.aircraft.iter().position(|info| info.flight.as_deref().unwrap_or_default().trim().to_uppercase().starts_with(&callsign)))
+  }
+
+  /// Index of the aircraft drawn closest to `coord`, if any lies within `max_cells`.
+  pub fn nearest_to_coord(&self, coord: Coord<f64>, max_cells: f64, args: &FRadarArgs) -> Option<usize> {
+    self.flights.iter()
+      .map(|(position, _)| coord.squared_dist(position.as_terminal_coord_float(args)))
+      .enumerate()
+      .filter(|(_, squared_dist)| *squared_dist <= max_cells.powi(2))
+      .min_by(|(_, a), (_, b)| a.total_cmp(b))
+      .map(|(index, _)| index)
+  }
+
+  pub fn nearest_first(&self, origin: &Position) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..self.flights.len()).collect();
+    indices.sort_by(|&a, &b| {
+      origin.distance_miles(&self.flights[a].0).total_cmp(&origin.distance_miles(&self.flights[b].0))
+    });
+    indices
+  }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct Position {
   pub lat: f64,
@@ -75,6 +480,40 @@ impl Position {
     2.0 // TODO: dynamically find value
   }
 
+  pub fn earth_radius_miles() -> f64 {
+    3958.8
+  }
+
+  /// Great-circle distance in miles (haversine).
+  pub fn distance_miles(&self, other: &Self) -> f64 {
+    let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+    let delta_lat  = lat2 - lat1;
+    let delta_long = (other.long - self.long).to_radians();
+
+    let a = (delta_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (delta_long / 2.0).sin().powi(2);
+    2.0 * Self::earth_radius_miles() * a.sqrt().asin()
+  }
+
+  /// Initial great-circle bearing towards `other`, in degrees clockwise from true north.
+  pub fn bearing_to(&self, other: &Self) -> f64 {
+    let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+    let delta_long = (other.long - self.long).to_radians();
+
+    let y = delta_long.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_long.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+  }
+
+  /// The point `miles` away along a great circle leaving on a true bearing.
+  pub fn destination(&self, bearing: f64, miles: f64) -> Position {
+    let (lat1, long1) = (self.lat.to_radians(), self.long.to_radians());
+    let (bearing, angle) = (bearing.to_radians(), miles / Self::earth_radius_miles());
+
+    let lat2 = (lat1.sin() * angle.cos() + lat1.cos() * angle.sin() * bearing.cos()).asin();
+    let long2 = long1 + (bearing.sin() * angle.sin() * lat1.cos()).atan2(angle.cos() - lat1.sin() * lat2.sin());
+    Position { lat: lat2.to_degrees(), long: long2.to_degrees() }
+  }
+
   pub fn roughly_eq(&self, other: &Self) -> bool {
     (self.lat - other.lat).abs() * Self::latlong_miles_ratio() < 0.1 && (self.long - other.long).abs() * Self::latlong_miles_ratio() < 0.1
   }
@@ -86,11 +525,22 @@ impl Position {
   pub fn as_terminal_coord_float(&self, args: &FRadarArgs) -> Coord<f64> {
     let terminal_cols: f64 = args.terminal_cols.into();
     let terminal_rows: f64 = args.terminal_rows.into();
-  
-    let latlong_to_miles: f64  = Self::latlong_miles_ratio();     // TODO: dynamically find value
-    let char_aspect_ratio: f64 = Self::character_aspect_ratio();  // TODO: dynamically find value
-    let lat_scale_factor: f64  = (f64::max(terminal_cols / 2.0, terminal_rows / 2.0)) / (args.radius as f64) * latlong_to_miles * char_aspect_ratio;
-    let long_scale_factor: f64 = (f64::max(terminal_cols / 2.0, terminal_rows / 2.0)) / (args.radius as f64) * latlong_to_miles;
+    let Coord { col, row } = self.as_terminal_coord_unclamped(args);
+
+    let clamped_col = col.clamp(0.0, terminal_cols);
+    let clamped_row = row.clamp(0.0, terminal_rows);
+
+    Coord {
+      col: clamped_col,
+      row: clamped_row,
+    }
+  }
+
+  /// Projection without clamping to the screen, for geometry that may run off the edges.
+  pub fn as_terminal_coord_unclamped(&self, args: &FRadarArgs) -> Coord<f64> {
+    let terminal_cols: f64 = args.terminal_cols.into();
+    let terminal_rows: f64 = args.terminal_rows.into();
+    let (lat_scale_factor, long_scale_factor) = Self::terminal_scale_factors(args);
 
     let delta_lat  = self.lat - args.origin.lat;
     let delta_long = self.long - args.origin.long;
@@ -98,17 +548,57 @@ impl Position {
     let delta_rows = -delta_lat * lat_scale_factor;
     let delta_cols =  delta_long * long_scale_factor;
 
-    let col = terminal_cols / 2.0 + delta_cols;
-    let row = terminal_rows / 2.0 + delta_rows;
+    Coord {
+      col: terminal_cols / 2.0 + delta_cols,
+      row: terminal_rows / 2.0 + delta_rows,
+    }
+  }
 
-    let clamped_col = col.clamp(0.0, terminal_cols);
-    let clamped_row = row.clamp(0.0, terminal_rows);
+  pub fn is_on_screen(&self, args: &FRadarArgs) -> bool {
+    let Coord { col, row } = self.as_terminal_coord_unclamped(args);
+    col >= 0.0 && row >= 0.0 && col < args.terminal_cols as f64 && row < args.terminal_rows as f64
+  }
 
-    Coord {
-      col: clamped_col,
-      row: clamped_row,
+  /// South-west and north-east corners of the screen, padded by a cell so that
+  /// geometry leaving the screen isn't culled too early.
+  pub fn screen_bounds(args: &FRadarArgs) -> (Position, Position) {
+    let top_left: Position = Self::from_terminal_coord_float(Coord { col: -1.0, row: -1.0 }, args);
+    let bottom_right: Position = Self::from_terminal_coord_float(Coord { col: args.terminal_cols as f64 + 1.0, row: args.terminal_rows as f64 + 1.0 }, args);
+    (
+      Position { lat: bottom_right.lat, long: top_left.long },
+      Position { lat: top_left.lat, long: bottom_right.long },
+    )
+  }
+
+  /// Inverse of `as_terminal_coord_float` (without the clamping).
+  pub fn from_terminal_coord_float(coord: Coord<f64>, args: &FRadarArgs) -> Position {
+    let terminal_cols: f64 = args.terminal_cols.into();
+    let terminal_rows: f64 = args.terminal_rows.into();
+    let (lat_scale_factor, long_scale_factor) = Self::terminal_scale_factors(args);
+
+    Position {
+      lat:  args.origin.lat  - (coord.row - terminal_rows / 2.0) / lat_scale_factor,
+      long: args.origin.long + (coord.col - terminal_cols / 2.0) / long_scale_factor,
     }
   }
+
+  /// Position at the center of a terminal cell.
+  pub fn from_terminal_cell(coord: Coord<u16>, args: &FRadarArgs) -> Position {
+    Self::from_terminal_coord_float(Coord { col: coord.col as f64 + 0.5, row: coord.row as f64 + 0.5 }, args)
+  }
+
+  /// Terminal cells per degree of latitude and longitude respectively.
+  fn terminal_scale_factors(args: &FRadarArgs) -> (f64, f64) {
+    let terminal_cols: f64 = args.terminal_cols.into();
+    let terminal_rows: f64 = args.terminal_rows.into();
+  
+    let latlong_to_miles: f64  = Self::latlong_miles_ratio();     // TODO: dynamically find value
+    let char_aspect_ratio: f64 = Self::character_aspect_ratio();  // TODO: dynamically find value
+    let lat_scale_factor: f64  = (f64::max(terminal_cols / 2.0, terminal_rows / 2.0)) / args.radius * latlong_to_miles * char_aspect_ratio;
+    let long_scale_factor: f64 = (f64::max(terminal_cols / 2.0, terminal_rows / 2.0)) / args.radius * latlong_to_miles;
+
+    (lat_scale_factor, long_scale_factor)
+  }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Hash, Eq)]
@@ -174,7 +664,7 @@ pub struct ADSBData {
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ADSBAircraftInformation {
   pub hex: String,
   #[serde(rename = "type")]
@@ -192,6 +682,7 @@ pub struct ADSBAircraftInformation {
   pub track: Option<f32>,
   pub roll: Option<f32>,
   pub geom_rate: Option<i32>,
+  pub baro_rate: Option<i32>,
   pub squawk: Option<String>,
   pub emergency: Option<String>,
   pub category: Option<String>,
@@ -220,6 +711,31 @@ pub struct ADSBAircraftInformation {
   pub dir: Option<f32>,
 }
 
+impl ADSBAircraftInformation {
+  pub fn is_on_ground(&self) -> bool {
+    self.alt_baro.as_deref() == Some("ground")
+  }
+
+  /// Symbol for the aircraft's emitter category, pointing along its track where that
+  /// means something. `None` leaves it as a plain braille dot.
+  pub fn glyph(&self) -> Option<char> {
+    const ARROWS: [char; 8] = ['↑', '↗', '→', '↘', '↓', '↙', '←', '↖'];
+    const HEAVY_ARROWS: [char; 8] = ['⇑', '⇗', '⇒', '⇘', '⇓', '⇙', '⇐', '⇖'];
+    let octant: Option<usize> = self.track.map(|track| ((track as f64 / 45.0).round() as usize) % 8);
+
+    match self.category.as_deref() {
+      Some("A4") | Some("A5") => octant.map(|octant| HEAVY_ARROWS[octant]),
+      Some("A7") => Some('✱'),
+      Some("B1") | Some("B4") => Some('^'),
+      Some("B2") => Some('o'),
+      Some("B6") => Some('x'),
+      Some("C1") | Some("C2") => Some('▪'),
+      Some("C3") => Some('▲'),
+      _ => octant.map(|octant| ARROWS[octant]),
+    }
+  }
+}
+
 impl TryFrom<ADSBAircraftInformation> for Position {
   type Error = anyhow::Error;
 
@@ -261,7 +777,7 @@ impl Label {
       self.plane.clone(),
       self.squawk.clone(),
     ]);
-    result.retain(|str| str.len() > 0);
+    result.retain(|str| !str.is_empty());
     result
   }
 
@@ -307,3 +823,65 @@ where D: Deserializer<'de> {
     
   Ok(result.unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn formats_coordinates() {
+    let seattle: Position = Position { lat: 47.6062, long: -122.3321 };
+    assert_eq!(CoordFormat::Dms.format(&Position { lat: 47.5, long: -122.25 }), "47°30'00.0\"N 122°15'00.0\"W");
+    assert_eq!(CoordFormat::Dms.format(&seattle), "47°36'22.3\"N 122°19'55.6\"W");
+    assert_eq!(CoordFormat::Decimal.format(&seattle), "47.60620 -122.33210");
+    assert_eq!(CoordFormat::Maidenhead.format(&seattle), "CN87uo");
+    assert_eq!(CoordFormat::Maidenhead.format(&Position { lat: -33.8688, long: 151.2093 }), "QF56od");
+  }
+
+  #[test]
+  fn formats_graticule_lines_as_briefly_as_the_spacing_allows() {
+    assert_eq!(CoordFormat::Dms.format_line(47.0, 1.0, 'N', 'S'), "47°N");
+    assert_eq!(CoordFormat::Maidenhead.format_line(-122.0, 2.0, 'E', 'W'), "122°W");
+    assert_eq!(CoordFormat::Dms.format_line(47.5, 0.5, 'N', 'S'), "47°30'N");
+    assert_eq!(CoordFormat::Dms.format_line(-0.25, 0.25, 'N', 'S'), "0°15'S");
+    assert_eq!(CoordFormat::Decimal.format_line(-122.25, 0.25, 'E', 'W'), "-122.25");
+  }
+
+  #[test]
+  fn terminal_cells_project_back_to_positions() {
+    let args: FRadarArgs = FRadarArgs { origin: Position { lat: 47.45, long: -122.3 }, radius: 25.0, terminal_cols: 120, terminal_rows: 40, ..Default::default() };
+    assert_eq!(Position::from_terminal_coord_float(Coord { col: 60.0, row: 20.0 }, &args), args.origin);
+
+    for (col, row) in [(0.0, 0.0), (119.5, 39.5), (12.25, 31.75)] {
+      let position: Position = Position::from_terminal_coord_float(Coord { col, row }, &args);
+      let coord: Coord<f64> = position.as_terminal_coord_unclamped(&args);
+      assert!((coord.col - col).abs() < 1e-9 && (coord.row - row).abs() < 1e-9, "{:?} came back as {:?}", (col, row), coord);
+    }
+
+    let corner: Position = Position::from_terminal_cell(Coord { col: 0, row: 0 }, &args);
+    assert!(corner.lat > args.origin.lat && corner.long < args.origin.long);
+  }
+
+  #[test]
+  fn picks_glyphs_by_category_and_track() {
+    let aircraft = |category: Option<&str>, track: Option<f32>| ADSBAircraftInformation { category: category.map(str::to_string), track, ..Default::default() };
+    assert_eq!(aircraft(None, Some(0.0)).glyph(), Some('↑'));
+    assert_eq!(aircraft(Some("A3"), Some(100.0)).glyph(), Some('→'));
+    assert_eq!(aircraft(Some("A1"), Some(337.6)).glyph(), Some('↑'), "rounds to the nearest octant");
+    assert_eq!(aircraft(Some("A5"), Some(225.0)).glyph(), Some('⇙'));
+    assert_eq!(aircraft(Some("A4"), None).glyph(), None);
+    assert_eq!(aircraft(Some("A7"), Some(90.0)).glyph(), Some('✱'));
+    assert_eq!(aircraft(Some("B2"), None).glyph(), Some('o'));
+    assert_eq!(aircraft(Some("C3"), None).glyph(), Some('▲'));
+    assert_eq!(aircraft(None, None).glyph(), None);
+  }
+
+  #[test]
+  fn leaders_reach_along_the_track() {
+    // 240 kt for two minutes is 8 nm.
+    let origin: Position = Position { lat: 47.45, long: -122.3 };
+    let ahead: Position = origin.destination(90.0, 240.0 * MILES_PER_NM * (2.0 / 60.0));
+    assert!((origin.distance_miles(&ahead) - 8.0 * MILES_PER_NM).abs() < 1e-6);
+    assert!((origin.bearing_to(&ahead) - 90.0).abs() < 0.1);
+  }
+}
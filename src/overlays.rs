@@ -0,0 +1,265 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use crossterm::style::Color;
+use serde_json::Value;
+
+use crate::{canvas::BrailleCanvas, model::{FRadarArgs, Position}};
+
+
+/// Douglas-Peucker tolerances (degrees) each overlay is pre-simplified at. The view picks
+/// the coarsest one that is still finer than a braille dot at the current zoom.
+const TOLERANCES: [f64; 7] = [0.0, 0.0002, 0.001, 0.004, 0.016, 0.064, 0.256];
+
+/// Long lines are split into chunks of at most this many points, so that the bounding box
+/// test can skip most of a coastline when only a small stretch of it is on screen.
+const CHUNK_POINTS: usize = 64;
+
+/// A vector map layer loaded from a GeoJSON file, drawn as braille lines under the traffic.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+  pub name: String,
+  levels: Vec<Vec<Chunk>>,
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+  points: Vec<Position>,
+  min: Position,
+  max: Position,
+}
+
+impl Chunk {
+  fn new(points: Vec<Position>) -> Chunk {
+    let min: Position = points.iter().fold(points[0], |min, point| Position { lat: min.lat.min(point.lat), long: min.long.min(point.long) });
+    let max: Position = points.iter().fold(points[0], |max, point| Position { lat: max.lat.max(point.lat), long: max.long.max(point.long) });
+    Chunk { points, min, max }
+  }
+
+  fn intersects(&self, min: &Position, max: &Position) -> bool {
+    self.min.lat <= max.lat && self.max.lat >= min.lat && self.min.long <= max.long && self.max.long >= min.long
+  }
+}
+
+impl Overlay {
+  /// Loads every line, polygon ring and point of a GeoJSON file. The layer is named after the file.
+  pub fn load(path: &Path) -> anyhow::Result<Overlay> {
+    let contents: String = std::fs::read_to_string(path)
+      .with_context(|| format!("failed to read {}", path.display()))?;
+    let document: Value = serde_json::from_str(&contents)
+      .with_context(|| format!("{} is not valid JSON", path.display()))?;
+
+    let mut lines: Vec<Vec<Position>> = Vec::new();
+    collect_lines(&document, &mut lines).with_context(|| format!("{} is not valid GeoJSON", path.display()))?;
+    lines.retain(|line| !line.is_empty());
+
+    let levels: Vec<Vec<Chunk>> = TOLERANCES.iter()
+      .map(|&tolerance| {
+        lines.iter()
+          .map(|line| simplify(line, tolerance))
+          .flat_map(chunk)
+          .collect()
+      })
+      .collect();
+
+    Ok(Overlay {
+      name: path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string()),
+      levels,
+    })
+  }
+
+  pub fn draw(&self, canvas: &mut BrailleCanvas, args: &FRadarArgs, color: Color) {
+    let (min, max) = Position::screen_bounds(args);
+
+    // A braille dot is a quarter of a cell tall, and that is the smallest detail worth keeping.
+    let dot_degrees: f64 = (max.lat - min.lat) / (args.terminal_rows as f64 + 2.0) / 4.0;
+    let level: usize = TOLERANCES.iter().rposition(|&tolerance| tolerance <= dot_degrees).unwrap_or(0);
+
+    for chunk in self.levels[level].iter().filter(|chunk| chunk.intersects(&min, &max)) {
+      match &chunk.points[..] {
+        [point] => canvas.plot(point.as_terminal_coord_unclamped(args), color),
+        points => canvas.polyline(points, args, color),
+      }
+    }
+  }
+}
+
+fn collect_lines(value: &Value, lines: &mut Vec<Vec<Position>>) -> anyhow::Result<()> {
+  let kind: &str = value.get("type").and_then(Value::as_str).ok_or_else(|| anyhow!("object without a \"type\""))?;
+  let coordinates = || value.get("coordinates").ok_or_else(|| anyhow!("{} without \"coordinates\"", kind));
+
+  match kind {
+    "FeatureCollection" => {
+      for feature in array(value.get("features"), "features")? {
+        collect_lines(feature, lines)?;
+      }
+    },
+    "Feature" => match value.get("geometry") {
+      None | Some(Value::Null) => {},
+      Some(geometry) => collect_lines(geometry, lines)?,
+    },
+    "GeometryCollection" => {
+      for geometry in array(value.get("geometries"), "geometries")? {
+        collect_lines(geometry, lines)?;
+      }
+    },
+    "Point" => lines.push(Vec::from([position(coordinates()?)?])),
+    "MultiPoint" => {
+      for point in array(Some(coordinates()?), "coordinates")? {
+        lines.push(Vec::from([position(point)?]));
+      }
+    },
+    "LineString" => lines.push(line(coordinates()?)?),
+    "MultiLineString" | "Polygon" => {
+      for ring in array(Some(coordinates()?), "coordinates")? {
+        lines.push(line(ring)?);
+      }
+    },
+    "MultiPolygon" => {
+      for polygon in array(Some(coordinates()?), "coordinates")? {
+        for ring in array(Some(polygon), "coordinates")? {
+          lines.push(line(ring)?);
+        }
+      }
+    },
+    other => bail!("unsupported type \"{}\"", other),
+  }
+
+  Ok(())
+}
+
+fn array<'a>(value: Option<&'a Value>, key: &str) -> anyhow::Result<&'a Vec<Value>> {
+  value.and_then(Value::as_array).ok_or_else(|| anyhow!("\"{}\" must be an array", key))
+}
+
+fn line(value: &Value) -> anyhow::Result<Vec<Position>> {
+  array(Some(value), "coordinates")?.iter().map(position).collect()
+}
+
+/// GeoJSON positions are `[longitude, latitude]`, optionally followed by an altitude.
+fn position(value: &Value) -> anyhow::Result<Position> {
+  match value.as_array().map(|numbers| numbers.iter().map(Value::as_f64).collect::<Vec<Option<f64>>>()).as_deref() {
+    Some([Some(long), Some(lat), ..]) => Ok(Position { lat: *lat, long: *long }),
+    _ => bail!("bad position {}", value),
+  }
+}
+
+/// Douglas-Peucker simplification, treating degrees as planar (which is how they are drawn).
+fn simplify(line: &[Position], tolerance: f64) -> Vec<Position> {
+  if tolerance <= 0.0 || line.len() <= 2 {
+    return line.to_vec();
+  }
+
+  let mut keep: Vec<bool> = vec![false; line.len()];
+  keep[0] = true;
+  keep[line.len() - 1] = true;
+
+  let mut stack: Vec<(usize, usize)> = Vec::from([(0, line.len() - 1)]);
+  while let Some((first, last)) = stack.pop() {
+    let farthest: Option<(usize, f64)> = (first + 1..last)
+      .map(|index| (index, segment_distance(&line[index], &line[first], &line[last])))
+      .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    if let Some((index, distance)) = farthest && distance > tolerance {
+      keep[index] = true;
+      stack.push((first, index));
+      stack.push((index, last));
+    }
+  }
+
+  line.iter().zip(keep).filter(|(_, keep)| *keep).map(|(point, _)| *point).collect()
+}
+
+fn segment_distance(point: &Position, from: &Position, to: &Position) -> f64 {
+  let (dx, dy) = (to.long - from.long, to.lat - from.lat);
+  let length_squared: f64 = dx * dx + dy * dy;
+  let t: f64 = if length_squared > 0.0 {
+    (((point.long - from.long) * dx + (point.lat - from.lat) * dy) / length_squared).clamp(0.0, 1.0)
+  } else {
+    0.0
+  };
+  (point.long - from.long - t * dx).hypot(point.lat - from.lat - t * dy)
+}
+
+fn chunk(line: Vec<Position>) -> Vec<Chunk> {
+  if line.len() <= CHUNK_POINTS {
+    return Vec::from([Chunk::new(line)]);
+  }
+
+  // Consecutive chunks share an end point so the drawn line stays connected.
+  (0..line.len() - 1).step_by(CHUNK_POINTS - 1)
+    .map(|start| Chunk::new(line[start..(start + CHUNK_POINTS).min(line.len())].to_vec()))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn lines(document: Value) -> anyhow::Result<Vec<Vec<Position>>> {
+    let mut lines: Vec<Vec<Position>> = Vec::new();
+    collect_lines(&document, &mut lines)?;
+    Ok(lines)
+  }
+
+  #[test]
+  fn collects_every_geometry_as_lat_long() {
+    let found: Vec<Vec<Position>> = lines(json!({
+      "type": "FeatureCollection",
+      "features": [
+        { "type": "Feature", "geometry": { "type": "Point", "coordinates": [-122.3, 47.45, 130.0] } },
+        { "type": "Feature", "geometry": null },
+        { "type": "Feature", "geometry": { "type": "LineString", "coordinates": [[0, 1], [2, 3]] } },
+        { "type": "Feature", "geometry": { "type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]], [[0.2, 0.2], [0.4, 0.2], [0.2, 0.2]]] } },
+        { "type": "Feature", "geometry": { "type": "GeometryCollection", "geometries": [
+          { "type": "MultiPoint", "coordinates": [[5, 6], [7, 8]] },
+          { "type": "MultiPolygon", "coordinates": [[[[9, 9], [10, 9], [9, 9]]]] },
+        ] } },
+      ],
+    })).unwrap();
+
+    let lengths: Vec<usize> = found.iter().map(Vec::len).collect();
+    assert_eq!(lengths, [1, 2, 4, 3, 1, 1, 3]);
+    assert_eq!(found[0], [Position { lat: 47.45, long: -122.3 }]);
+    assert_eq!(found[1], [Position { lat: 1.0, long: 0.0 }, Position { lat: 3.0, long: 2.0 }]);
+  }
+
+  #[test]
+  fn rejects_what_it_cannot_draw() {
+    let error = |document: Value| lines(document).unwrap_err().to_string();
+    assert_eq!(error(json!({ "type": "Circle", "coordinates": [0, 0] })), "unsupported type \"Circle\"");
+    assert_eq!(error(json!({ "type": "LineString" })), "LineString without \"coordinates\"");
+    assert_eq!(error(json!({ "type": "LineString", "coordinates": [[0, 0], [1]] })), "bad position [1]");
+    assert_eq!(error(json!({ "type": "FeatureCollection", "features": {} })), "\"features\" must be an array");
+    assert_eq!(error(json!({ "coordinates": [0, 0] })), "object without a \"type\"");
+  }
+
+  #[test]
+  fn simplifies_within_the_tolerance() {
+    // East then north, with a little jitter along each leg.
+    let jitter = |step: usize| 0.0001 * (step % 2) as f64;
+    let line: Vec<Position> = (0..=10)
+      .map(|step: usize| match step <= 5 {
+        true => Position { lat: jitter(step), long: step as f64 * 0.01 },
+        false => Position { lat: (step - 5) as f64 * 0.01, long: 0.05 + jitter(step) },
+      })
+      .collect();
+    assert_eq!(simplify(&line, 0.0), line);
+    assert_eq!(simplify(&line, 0.001), [line[0], line[5], line[10]]);
+    assert_eq!(simplify(&line, 0.1), [line[0], line[10]]);
+  }
+
+  #[test]
+  fn chunks_share_their_end_points() {
+    let line: Vec<Position> = (0..150).map(|step| Position { lat: step as f64, long: 0.0 }).collect();
+    let chunks: Vec<Chunk> = chunk(line.clone());
+    assert_eq!(chunks.iter().map(|chunk| chunk.points.len()).collect::<Vec<usize>>(), [64, 64, 24]);
+    assert_eq!(chunks[1].points[0], chunks[0].points[63]);
+    assert_eq!(chunks[2].points.last(), line.last());
+    assert_eq!((chunks[1].min.lat, chunks[1].max.lat), (63.0, 126.0));
+    assert!(chunks[1].intersects(&Position { lat: 100.0, long: -1.0 }, &Position { lat: 200.0, long: 1.0 }));
+    assert!(!chunks[0].intersects(&Position { lat: 100.0, long: -1.0 }, &Position { lat: 200.0, long: 1.0 }));
+  }
+}
@@ -0,0 +1,475 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fs::File, io::{BufRead, BufReader, Write}, path::{Path, PathBuf}, str::FromStr};
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::{aircraft_list::altitude_feet, config::{parse_altitude_feet, parse_distance_miles, parse_origin, OriginValue, ReportArgs, Settings}, logbook::{read_sightings, Sighting, TrackPoint, SIGHTING_GAP_SECONDS}, model::{ADSBData, Position, MILES_PER_NM}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+  Markdown,
+  Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+  Flight,
+  Hour,
+  Day,
+}
+
+impl FromStr for ReportFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "markdown" | "md" => Ok(ReportFormat::Markdown),
+      "csv" => Ok(ReportFormat::Csv),
+      _ => bail!("unknown format '{}' (expected markdown or csv)", s),
+    }
+  }
+}
+
+impl FromStr for Grouping {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "flight" | "flights" => Ok(Grouping::Flight),
+      "hour" | "hourly" => Ok(Grouping::Hour),
+      "day" | "daily" => Ok(Grouping::Day),
+      _ => bail!("unknown grouping '{}' (expected flight, hour or day)", s),
+    }
+  }
+}
+
+/// Where one sighting came closest to the report point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overflight {
+  pub time: i64,
+  pub hex: String,
+  pub registration: Option<String>,
+  pub callsign: Option<String>,
+  pub aircraft_type: Option<String>,
+  pub altitude_ft: Option<f64>,
+  pub distance_nm: f64,
+}
+
+/// Everything `fradar report` was asked for, resolved and checked.
+#[derive(Debug, Clone)]
+struct Query {
+  point: Position,
+  within_nm: f64,
+  below_ft: Option<f64>,
+  since: Option<i64>,
+  until: Option<i64>,
+  grouping: Grouping,
+  format: ReportFormat,
+  logbook: Option<PathBuf>,
+  recordings: Vec<PathBuf>,
+}
+
+impl Query {
+  fn new(report: &ReportArgs, settings: &Settings) -> anyhow::Result<Query> {
+    let point: Position = match &report.origin {
+      Some(origin) => parse_origin(&OriginValue::Text(origin.clone())).map_err(|error| anyhow!("--origin: {}", error))?,
+      None => settings.args.starting_origin,
+    };
+    let within_nm: f64 = match &report.within {
+      Some(within) => parse_distance_miles(within).map_err(|error| anyhow!("--within: {}", error))? / MILES_PER_NM,
+      None => 1.0,
+    };
+    if within_nm <= 0.0 {
+      bail!("--within: distance must be positive");
+    }
+    let below_ft: Option<f64> = match &report.below {
+      Some(below) => Some(parse_altitude_feet(below).map_err(|error| anyhow!("--below: {}", error))?),
+      None => None,
+    };
+
+    let since: Option<i64> = match &report.since {
+      Some(since) => Some(parse_report_time(since, false).map_err(|error| anyhow!("--since: {}", error))?),
+      None => None,
+    };
+    let until: Option<i64> = match &report.until {
+      Some(until) => Some(parse_report_time(until, true).map_err(|error| anyhow!("--until: {}", error))?),
+      None => None,
+    };
+    if let (Some(since), Some(until)) = (since, until) && since >= until {
+      bail!("--since must be before --until");
+    }
+
+    let logbook: Option<PathBuf> = settings.logbook_path.clone().filter(|_| report.recordings.is_empty());
+    if logbook.is_none() && report.recordings.is_empty() {
+      bail!("nothing to report on: set `logbook` in the config, pass --logbook, or give --recording files");
+    }
+
+    Ok(Query {
+      point,
+      within_nm,
+      below_ft,
+      since,
+      until,
+      grouping: report.by.as_deref().map(Grouping::from_str).transpose().map_err(|error| anyhow!("--by: {}", error))?.unwrap_or(Grouping::Flight),
+      format: report.format.as_deref().map(ReportFormat::from_str).transpose().map_err(|error| anyhow!("--format: {}", error))?.unwrap_or(ReportFormat::Markdown),
+      logbook,
+      recordings: report.recordings.clone(),
+    })
+  }
+
+  fn source(&self) -> String {
+    match &self.logbook {
+      Some(path) => format!("logbook {}", path.display()),
+      None => format!("recordings {}", self.recordings.iter().map(|path| path.display().to_string()).collect::<Vec<String>>().join(", ")),
+    }
+  }
+
+  fn accepts(&self, overflight: &Overflight) -> bool {
+    overflight.distance_nm <= self.within_nm &&
+      self.below_ft.is_none_or(|below| overflight.altitude_ft.is_some_and(|feet| feet < below)) &&
+      self.since.is_none_or(|since| overflight.time >= since) &&
+      self.until.is_none_or(|until| overflight.time < until)
+  }
+}
+
+/// Runs `fradar report`, writing the report to stdout.
+pub fn run(report: &ReportArgs, settings: &Settings) -> anyhow::Result<()> {
+  let query: Query = Query::new(report, settings)?;
+
+  let sightings: Vec<Sighting> = match &query.logbook {
+    Some(path) => read_sightings(path, query.since, query.until)?,
+    None => query.recordings.iter().map(|path| read_recording(path)).collect::<anyhow::Result<Vec<Vec<Sighting>>>>()?.into_iter().flatten().collect(),
+  };
+
+  let overflights: Vec<Overflight> = overflights(&query, &sightings);
+  let table: Table = table(query.grouping, &overflights);
+
+  let mut stdout = std::io::stdout().lock();
+  match query.format {
+    ReportFormat::Markdown => write_markdown(&mut stdout, &query, &table, overflights.len())?,
+    ReportFormat::Csv => write_csv(&mut stdout, &table)?,
+  }
+  Ok(())
+}
+
+/// The sightings' overflights that meet the query, oldest first.
+fn overflights(query: &Query, sightings: &[Sighting]) -> Vec<Overflight> {
+  let mut overflights: Vec<Overflight> = sightings.iter()
+    .filter_map(|sighting| overflight(sighting, &query.point))
+    .filter(|overflight| query.accepts(overflight))
+    .collect();
+  overflights.sort_by_key(|overflight| overflight.time);
+  overflights
+}
+
+fn table(grouping: Grouping, overflights: &[Overflight]) -> Table {
+  match grouping {
+    Grouping::Flight => flight_table(overflights),
+    Grouping::Hour => aggregate_table("hour", overflights, |time| time.format("%Y-%m-%d %H:00").to_string()),
+    Grouping::Day => aggregate_table("day", overflights, |time| time.format("%Y-%m-%d").to_string()),
+  }
+}
+
+/// Parses a local date or date and time into Unix seconds. A bare date at the end of
+/// a period means the end of that day.
+fn parse_report_time(text: &str, end: bool) -> anyhow::Result<i64> {
+  let today: NaiveDate = Local::now().date_naive();
+  let date: Option<NaiveDate> = match text.trim().to_lowercase().as_str() {
+    "today" => Some(today),
+    "yesterday" => today.checked_sub_days(Days::new(1)),
+    text => NaiveDate::parse_from_str(text, "%Y-%m-%d").ok(),
+  };
+
+  let time: NaiveDateTime = match date {
+    Some(date) if end => date.checked_add_days(Days::new(1)).ok_or_else(|| anyhow!("date out of range"))?.and_hms_opt(0, 0, 0).unwrap(),
+    Some(date) => date.and_hms_opt(0, 0, 0).unwrap(),
+    None => ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"].iter()
+      .find_map(|format| NaiveDateTime::parse_from_str(text.trim(), format).ok())
+      .ok_or_else(|| anyhow!("expected YYYY-MM-DD, \"YYYY-MM-DD HH:MM\", today or yesterday, got '{}'", text))?,
+  };
+
+  // Times skipped by a daylight saving change fall back to UTC so they still parse.
+  Ok(Local.from_local_datetime(&time).earliest().map(|time| time.timestamp()).unwrap_or_else(|| time.and_utc().timestamp()))
+}
+
+/// Rebuilds sightings from a `:record` file, splitting an airframe's track wherever it
+/// went unseen as long as the logbook would.
+fn read_recording(path: &Path) -> anyhow::Result<Vec<Sighting>> {
+  let file: File = File::open(path).with_context(|| format!("failed to open recording {}", path.display()))?;
+  let mut sightings: Vec<Sighting> = Vec::new();
+  let mut open: HashMap<String, usize> = HashMap::new();
+
+  for (number, line) in BufReader::new(file).lines().enumerate() {
+    let line: String = line.with_context(|| format!("failed to read {}", path.display()))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let snapshot: ADSBData = serde_json::from_str(&line).with_context(|| format!("{}:{}: not a recorded snapshot", path.display(), number + 1))?;
+    let time: i64 = snapshot.ctime.timestamp();
+
+    for info in snapshot.ac.iter() {
+      let point: TrackPoint = (info.lat, info.lon, altitude_feet(info), time);
+      let index: usize = match open.get(&info.hex) {
+        Some(&index) if sightings[index].track.last().is_some_and(|last| time - last.3 <= SIGHTING_GAP_SECONDS) => index,
+        _ => {
+          sightings.push(Sighting { hex: info.hex.clone(), ..Sighting::default() });
+          open.insert(info.hex.clone(), sightings.len() - 1);
+          sightings.len() - 1
+        },
+      };
+
+      let sighting: &mut Sighting = &mut sightings[index];
+      let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+      sighting.registration = sighting.registration.take().or_else(|| text(&info.r));
+      sighting.callsign = sighting.callsign.take().or_else(|| text(&info.flight));
+      sighting.aircraft_type = sighting.aircraft_type.take().or_else(|| text(&info.t));
+      if sighting.track.last().is_none_or(|last| last.3 < time) {
+        sighting.track.push(point);
+      }
+    }
+  }
+
+  Ok(sightings)
+}
+
+/// The closest a sighting's track came to `point`. Positions and altitudes are
+/// interpolated between track points, on a flat plane around `point`.
+pub fn overflight(sighting: &Sighting, point: &Position) -> Option<Overflight> {
+  let local = |(lat, long, _, _): &TrackPoint| -> (f64, f64) {
+    (((long - point.long + 540.0).rem_euclid(360.0) - 180.0) * 60.0 * point.lat.to_radians().cos(), (lat - point.lat) * 60.0)
+  };
+
+  // A single point is its own closest approach.
+  let first: &TrackPoint = sighting.track.first()?;
+  let mut closest: (f64, &TrackPoint, &TrackPoint, f64) = (f64::INFINITY, first, first, 0.0);
+  for (a, b) in sighting.track.iter().zip(sighting.track.iter().skip(1)) {
+    let ((east_a, north_a), (east_b, north_b)) = (local(a), local(b));
+    let (east, north) = (east_b - east_a, north_b - north_a);
+    let length_squared: f64 = east * east + north * north;
+    let fraction: f64 = match length_squared > 0.0 {
+      true => (-(east_a * east + north_a * north) / length_squared).clamp(0.0, 1.0),
+      false => 0.0,
+    };
+    let miss: f64 = (east_a + east * fraction).hypot(north_a + north * fraction);
+    if miss < closest.0 {
+      closest = (miss, a, b, fraction);
+    }
+  }
+
+  let (_, a, b, fraction) = closest;
+  let position: Position = Position { lat: a.0 + (b.0 - a.0) * fraction, long: a.1 + (b.1 - a.1) * fraction };
+  let altitude_ft: Option<f64> = match (a.2, b.2) {
+    (Some(from), Some(to)) => Some(from + (to - from) * fraction),
+    (from, to) => from.or(to),
+  };
+
+  Some(Overflight {
+    time: a.3 + ((b.3 - a.3) as f64 * fraction).round() as i64,
+    hex: sighting.hex.clone(),
+    registration: sighting.registration.clone(),
+    callsign: sighting.callsign.clone(),
+    aircraft_type: sighting.aircraft_type.clone(),
+    altitude_ft,
+    distance_nm: point.distance_miles(&position) / MILES_PER_NM,
+  })
+}
+
+struct Table {
+  header: Vec<&'static str>,
+  rows: Vec<Vec<String>>,
+}
+
+fn local_time(time: i64) -> DateTime<Local> {
+  Local.timestamp_opt(time, 0).single().unwrap_or_default()
+}
+
+fn format_feet(feet: Option<f64>) -> String {
+  feet.map(|feet| format!("{:.0}", feet)).unwrap_or_default()
+}
+
+fn flight_table(overflights: &[Overflight]) -> Table {
+  Table {
+    header: Vec::from(["time", "hex", "callsign", "registration", "type", "altitude_ft", "distance_nm"]),
+    rows: overflights.iter().map(|overflight| Vec::from([
+      local_time(overflight.time).format("%Y-%m-%d %H:%M:%S %:z").to_string(),
+      overflight.hex.clone(),
+      overflight.callsign.clone().unwrap_or_default(),
+      overflight.registration.clone().unwrap_or_default(),
+      overflight.aircraft_type.clone().unwrap_or_default(),
+      format_feet(overflight.altitude_ft),
+      format!("{:.2}", overflight.distance_nm),
+    ])).collect(),
+  }
+}
+
+/// Totals per period, keyed by the local time formatted with `period`.
+fn aggregate_table(name: &'static str, overflights: &[Overflight], period: impl Fn(DateTime<Local>) -> String) -> Table {
+  let mut groups: BTreeMap<String, Vec<&Overflight>> = BTreeMap::new();
+  for overflight in overflights.iter() {
+    groups.entry(period(local_time(overflight.time))).or_default().push(overflight);
+  }
+
+  Table {
+    header: Vec::from([name, "overflights", "aircraft", "lowest_ft", "closest_nm"]),
+    rows: groups.into_iter().map(|(key, group)| Vec::from([
+      key,
+      group.len().to_string(),
+      group.iter().map(|overflight| overflight.hex.as_str()).collect::<HashSet<&str>>().len().to_string(),
+      format_feet(group.iter().filter_map(|overflight| overflight.altitude_ft).reduce(f64::min)),
+      format!("{:.2}", group.iter().map(|overflight| overflight.distance_nm).fold(f64::INFINITY, f64::min)),
+    ])).collect(),
+  }
+}
+
+fn write_markdown(out: &mut impl Write, query: &Query, table: &Table, count: usize) -> anyhow::Result<()> {
+  let period = |time: Option<i64>, open: &str| time.map(|time| local_time(time).format("%Y-%m-%d %H:%M %:z").to_string()).unwrap_or_else(|| open.to_string());
+
+  writeln!(out, "# Overflights of {:.5}, {:.5}", query.point.lat, query.point.long)?;
+  writeln!(out)?;
+  writeln!(out, "- Within: {:.2} nm", query.within_nm)?;
+  writeln!(out, "- Below: {}", query.below_ft.map(|feet| format!("{:.0} ft", feet)).unwrap_or_else(|| "any altitude".to_string()))?;
+  writeln!(out, "- Period: {} to {}", period(query.since, "start of data"), period(query.until, "end of data"))?;
+  writeln!(out, "- Source: {}", query.source())?;
+  writeln!(out, "- Overflights: {}", count)?;
+  writeln!(out)?;
+
+  writeln!(out, "| {} |", table.header.join(" | "))?;
+  writeln!(out, "|{}", table.header.iter().map(|_| "---|").collect::<String>())?;
+  for row in table.rows.iter() {
+    writeln!(out, "| {} |", row.iter().map(|cell| cell.replace('|', "\\|")).collect::<Vec<String>>().join(" | "))?;
+  }
+  Ok(())
+}
+
+fn write_csv(out: impl Write, table: &Table) -> anyhow::Result<()> {
+  let mut writer = csv::Writer::from_writer(out);
+  writer.write_record(&table.header)?;
+  for row in table.rows.iter() {
+    writer.write_record(row)?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fixtures::aircraft, logbook::Logbook, model::ADSBAircraftInformation};
+
+  const POINT: Position = Position { lat: 47.0, long: -122.0 };
+
+  fn local(text: &str) -> i64 {
+    Local.from_local_datetime(&NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()).earliest().unwrap().timestamp()
+  }
+
+  /// Flies an aircraft north past `POINT`, `east_nm` to the east, crossing abeam at `abeam`.
+  fn fly_past(logbook: &mut Logbook, hex: &str, east_nm: f64, altitude: i32, abeam: i64) {
+    for step in -2..=2 {
+      let position: Position = Position { lat: POINT.lat + step as f64 / 60.0, long: POINT.long + east_nm / 60.0 / POINT.lat.to_radians().cos() };
+      let info: ADSBAircraftInformation = ADSBAircraftInformation { flight: Some(hex.to_uppercase()), ..aircraft(hex, &position, altitude) };
+      logbook.record(&[info], &POINT, abeam + step * 10).unwrap();
+    }
+  }
+
+  fn fixture() -> Vec<Sighting> {
+    let mut logbook: Logbook = Logbook::in_memory();
+    fly_past(&mut logbook, "low", 0.2, 2000, local("2026-03-01 14:10:00"));
+    fly_past(&mut logbook, "high", 0.8, 5000, local("2026-03-01 14:40:00"));
+    fly_past(&mut logbook, "wide", 3.0, 2000, local("2026-03-01 15:05:00"));
+    fly_past(&mut logbook, "next", 0.1, 1500, local("2026-03-02 09:00:00"));
+    logbook.close().unwrap();
+    logbook.sightings(None, None).unwrap()
+  }
+
+  fn query() -> Query {
+    Query {
+      point: POINT,
+      within_nm: 1.0,
+      below_ft: None,
+      since: None,
+      until: None,
+      grouping: Grouping::Flight,
+      format: ReportFormat::Csv,
+      logbook: None,
+      recordings: Vec::new(),
+    }
+  }
+
+  fn hexes(query: &Query, sightings: &[Sighting]) -> Vec<String> {
+    overflights(query, sightings).into_iter().map(|overflight| overflight.hex).collect()
+  }
+
+  #[test]
+  fn parses_report_times() {
+    assert_eq!(parse_report_time("2026-03-01", false).unwrap(), local("2026-03-01 00:00:00"));
+    assert_eq!(parse_report_time("2026-03-01", true).unwrap(), local("2026-03-02 00:00:00"));
+    assert_eq!(parse_report_time(" 2026-03-01 14:30 ", false).unwrap(), local("2026-03-01 14:30:00"));
+    assert_eq!(parse_report_time("2026-03-01T14:30:15", true).unwrap(), local("2026-03-01 14:30:15"));
+
+    let midnight = |date: NaiveDate| local(&format!("{} 00:00:00", date));
+    let today: NaiveDate = Local::now().date_naive();
+    assert_eq!(parse_report_time("today", false).unwrap(), midnight(today));
+    assert_eq!(parse_report_time("Today", true).unwrap(), midnight(today + Days::new(1)));
+    assert_eq!(parse_report_time("yesterday", false).unwrap(), midnight(today - Days::new(1)));
+    assert_eq!(parse_report_time("yesterday", true).unwrap(), midnight(today));
+
+    for text in ["", "tomorrow", "2026-02-30", "2026-03-01 25:00", "03/01/2026", "14:30"] {
+      assert!(parse_report_time(text, false).is_err(), "'{}' parsed", text);
+    }
+  }
+
+  #[test]
+  fn finds_overflights_within_the_distance() {
+    let sightings: Vec<Sighting> = fixture();
+    assert_eq!(hexes(&query(), &sightings), ["low", "high", "next"]);
+    assert_eq!(hexes(&Query { within_nm: 0.5, ..query() }, &sightings), ["low", "next"]);
+    assert_eq!(hexes(&Query { within_nm: 5.0, ..query() }, &sightings), ["low", "high", "wide", "next"]);
+
+    let low: Overflight = overflights(&query(), &sightings).remove(0);
+    assert_eq!(low.time, local("2026-03-01 14:10:00"));
+    assert_eq!(low.callsign.as_deref(), Some("LOW"));
+    assert_eq!(low.altitude_ft, Some(2000.0));
+    assert!((low.distance_nm - 0.2).abs() < 0.01, "{:?}", low);
+  }
+
+  #[test]
+  fn filters_on_altitude_and_time() {
+    let sightings: Vec<Sighting> = fixture();
+    assert_eq!(hexes(&Query { below_ft: Some(3000.0), ..query() }, &sightings), ["low", "next"]);
+    assert_eq!(hexes(&Query { below_ft: Some(2000.0), ..query() }, &sightings), ["next"]);
+
+    let day: Query = Query { since: Some(local("2026-03-01 00:00:00")), until: Some(local("2026-03-02 00:00:00")), ..query() };
+    assert_eq!(hexes(&day, &sightings), ["low", "high"]);
+    let afternoon: Query = Query { since: Some(local("2026-03-01 14:30:00")), ..query() };
+    assert_eq!(hexes(&afternoon, &sightings), ["high", "next"]);
+  }
+
+  #[test]
+  fn groups_by_flight_hour_and_day() {
+    let overflights: Vec<Overflight> = overflights(&Query { within_nm: 5.0, ..query() }, &fixture());
+
+    let flights: Table = table(Grouping::Flight, &overflights);
+    assert_eq!(flights.rows.len(), 4);
+    assert_eq!(flights.rows[0][1..], ["low", "LOW", "", "", "2000", "0.20"]);
+
+    let hours: Table = table(Grouping::Hour, &overflights);
+    assert_eq!(hours.header, ["hour", "overflights", "aircraft", "lowest_ft", "closest_nm"]);
+    assert_eq!(hours.rows, [
+      ["2026-03-01 14:00", "2", "2", "2000", "0.20"],
+      ["2026-03-01 15:00", "1", "1", "2000", "3.00"],
+      ["2026-03-02 09:00", "1", "1", "1500", "0.10"],
+    ]);
+
+    let days: Table = table(Grouping::Day, &overflights);
+    assert_eq!(days.rows, [["2026-03-01", "3", "3", "2000", "0.20"], ["2026-03-02", "1", "1", "1500", "0.10"]]);
+  }
+
+  #[test]
+  fn single_point_tracks_count() {
+    let sighting: Sighting = Sighting { hex: "one".to_string(), track: Vec::from([(POINT.lat + 0.5 / 60.0, POINT.long, Some(1200.0), 100)]), ..Sighting::default() };
+    let overflight: Overflight = overflight(&sighting, &POINT).unwrap();
+    assert_eq!((overflight.time, overflight.altitude_ft), (100, Some(1200.0)));
+    assert!((overflight.distance_nm - 0.5).abs() < 0.01);
+  }
+}
@@ -0,0 +1,253 @@
+use crossterm::{cursor, queue, style::{self, Color}};
+
+use crate::{aircraft_list::list_area, canvas::{BrailleCanvas, Stroke}, model::{Coord, FRadarArgs, FRadarUi, ListMode, Position}};
+
+
+/// Vertices per range ring.
+const RING_SEGMENTS: usize = 120;
+
+/// Graticule spacings in degrees, from a minute of arc up to 30 degrees.
+const GRATICULE_STEPS: [f64; 14] = [
+  1.0 / 60.0, 2.0 / 60.0, 5.0 / 60.0, 10.0 / 60.0, 15.0 / 60.0, 30.0 / 60.0,
+  1.0, 2.0, 3.0, 5.0, 10.0, 15.0, 20.0, 30.0,
+];
+
+/// Largest "nice" distance (1, 2 or 5 times a power of ten) that is no bigger than `limit`.
+pub fn nice_distance(limit: f64) -> f64 {
+  let magnitude: f64 = 10f64.powf(limit.log10().floor());
+  [5.0, 2.0, 1.0].into_iter()
+    .map(|mantissa| mantissa * magnitude)
+    .find(|distance| *distance <= limit)
+    .unwrap_or(magnitude)
+}
+
+/// Formats a distance in display units without a pointless trailing `.0`.
+fn format_nice(distance: f64, args: &FRadarArgs) -> String {
+  format!("{} {}", (distance * 1000.0).round() / 1000.0, args.units.distance_unit())
+}
+
+/// Draws range rings around the starting origin into the canvas, with compass ticks on the
+/// outermost ring that fits on screen. Returns the text labels to print once the canvas is flushed.
+pub fn draw_range_rings(canvas: &mut BrailleCanvas, args: &FRadarArgs, color: Color) -> Vec<(Coord<f64>, String)> {
+  let center: Position = args.starting_origin;
+  let miles_per_unit: f64 = 1.0 / args.units.distance(1.0);
+  let step: f64 = nice_distance(args.units.distance(args.radius) / 3.0);
+  let step_miles: f64 = step * miles_per_unit;
+
+  // Rings out to the farthest corner of the screen.
+  let farthest: f64 = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].iter()
+    .map(|(col, row)| Position::from_terminal_coord_float(Coord { col: col * args.terminal_cols as f64, row: row * args.terminal_rows as f64 }, args))
+    .map(|corner| center.distance_miles(&corner))
+    .fold(0.0, f64::max);
+  let count: usize = ((farthest / step_miles).floor() as usize).min(50);
+
+  let mut labels: Vec<(Coord<f64>, String)> = Vec::new();
+  let mut outer: Option<f64> = None;
+
+  for index in 1..=count {
+    let miles: f64 = step_miles * index as f64;
+    let ring: Vec<Position> = (0..=RING_SEGMENTS)
+      .map(|segment| center.destination(360.0 * segment as f64 / RING_SEGMENTS as f64, miles))
+      .collect();
+    canvas.polyline(&ring, args, color);
+
+    let north: Coord<f64> = ring[0].as_terminal_coord_unclamped(args);
+    labels.push((Coord { col: north.col + 1.0, row: north.row }, format_nice(step * index as f64, args)));
+
+    if [0.0, 90.0, 180.0, 270.0].iter().all(|bearing| center.destination(*bearing, miles * 1.2).is_on_screen(args)) {
+      outer = Some(miles);
+    }
+  }
+
+  // Bearing ticks every 10 degrees, longer every 30 with the bearing written beyond them.
+  if let Some(miles) = outer {
+    for bearing in (0..360).step_by(10) {
+      let major: bool = bearing % 30 == 0;
+      let length: f64 = step_miles * if major { 0.12 } else { 0.06 };
+      let from: Coord<f64> = center.destination(bearing as f64, miles).as_terminal_coord_unclamped(args);
+      let to: Coord<f64> = center.destination(bearing as f64, miles + length).as_terminal_coord_unclamped(args);
+      canvas.line(from, to, color);
+
+      if major {
+        let text: String = match bearing {
+          0 => "N".to_string(),
+          90 => "E".to_string(),
+          180 => "S".to_string(),
+          270 => "W".to_string(),
+          _ => format!("{:03}", bearing),
+        };
+        let at: Coord<f64> = center.destination(bearing as f64, miles + step_miles * 0.25).as_terminal_coord_unclamped(args);
+        labels.push((Coord { col: at.col - text.len() as f64 / 2.0, row: at.row }, text));
+      }
+    }
+  }
+
+  labels.retain(|(coord, text)| {
+    coord.col >= 1.0 && coord.row >= 1.0 &&
+      coord.col + (text.len() as f64) < args.terminal_cols as f64 - 1.0 && coord.row < args.terminal_rows as f64 - 1.0
+  });
+  labels
+}
+
+pub fn draw_scope_labels(labels: &[(Coord<f64>, String)], color: Color) -> anyhow::Result<()> {
+  for (coord, text) in labels.iter() {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(coord.col as u16, coord.row as u16),
+      style::SetForegroundColor(color),
+      style::Print(text),
+      style::ResetColor,
+    )?;
+  }
+
+  Ok(())
+}
+
+/// Draws a scale bar in the bottom left corner, measured east-west through the view center.
+pub fn draw_scale_bar(args: &FRadarArgs, ui: &FRadarUi, color: Color) -> anyhow::Result<()> {
+  let bottom: u16 = match (ui.list_mode, list_area(args, ui.list_mode)) {
+    (ListMode::Full, _) => return Ok(()),
+    (_, Some((_, y, _, _))) => y,
+    (_, None) => args.terminal_rows - 1,
+  };
+  if bottom < 4 || args.terminal_cols < 20 {
+    return Ok(());
+  }
+
+  let probe_miles: f64 = args.radius / 4.0;
+  let east: Coord<f64> = args.origin.destination(90.0, probe_miles).as_terminal_coord_unclamped(args);
+  let center: Coord<f64> = args.origin.as_terminal_coord_unclamped(args);
+  let cells_per_unit: f64 = (east.col - center.col) / args.units.distance(probe_miles);
+  if cells_per_unit <= 0.0 || !cells_per_unit.is_finite() {
+    return Ok(());
+  }
+
+  let length: f64 = nice_distance(args.terminal_cols as f64 / 5.0 / cells_per_unit);
+  let cells: usize = ((length * cells_per_unit).round() as usize).max(2);
+  queue!(
+    std::io::stdout(),
+    cursor::MoveTo(3, bottom - 2),
+    style::SetForegroundColor(color),
+    style::Print(format!("├{}┤ {}", "─".repeat(cells - 2), format_nice(length, args))),
+    style::ResetColor,
+  )?;
+
+  Ok(())
+}
+
+/// Draws lat/long lines into the canvas at a spacing that keeps them a comfortable distance
+/// apart. Returns labels for the border frame: latitudes down the left, longitudes along the top.
+pub fn draw_graticule(canvas: &mut BrailleCanvas, args: &FRadarArgs, color: Color) -> Vec<(Coord<f64>, String)> {
+  let (min, max) = Position::screen_bounds(args);
+  let degrees_per_row: f64 = (max.lat - min.lat) / (args.terminal_rows as f64 + 2.0);
+  let degrees_per_col: f64 = (max.long - min.long) / (args.terminal_cols as f64 + 2.0);
+  let spacing = |degrees_per_cell: f64, cells: f64| {
+    GRATICULE_STEPS.iter().copied().find(|step| step / degrees_per_cell >= cells).unwrap_or(GRATICULE_STEPS[GRATICULE_STEPS.len() - 1])
+  };
+  let (lat_step, long_step) = (spacing(degrees_per_row, 5.0), spacing(degrees_per_col, 16.0));
+
+  let mut labels: Vec<(Coord<f64>, String)> = Vec::new();
+
+  let mut lat: f64 = (min.lat / lat_step).ceil() * lat_step;
+  while lat <= max.lat.min(90.0) {
+    let from: Coord<f64> = Position { lat, long: min.long }.as_terminal_coord_unclamped(args);
+    let to: Coord<f64> = Position { lat, long: max.long }.as_terminal_coord_unclamped(args);
+    canvas.stroke(from, to, color, Stroke::Dotted, &mut 0);
+    labels.push((Coord { col: 0.0, row: from.row }, args.coords.format_line(lat, lat_step, 'N', 'S')));
+    lat += lat_step;
+  }
+
+  let mut long: f64 = (min.long / long_step).ceil() * long_step;
+  while long <= max.long {
+    let from: Coord<f64> = Position { lat: max.lat, long }.as_terminal_coord_unclamped(args);
+    let to: Coord<f64> = Position { lat: min.lat, long }.as_terminal_coord_unclamped(args);
+    canvas.stroke(from, to, color, Stroke::Dotted, &mut 0);
+    let text: String = args.coords.format_line((long + 180.0).rem_euclid(360.0) - 180.0, long_step, 'E', 'W');
+    labels.push((Coord { col: from.col - text.chars().count() as f64 / 2.0, row: 0.0 }, text));
+    long += long_step;
+  }
+
+  labels.retain(|(coord, text)| {
+    coord.row >= 1.0 && coord.row < args.terminal_rows as f64 - 1.0 ||
+      coord.row == 0.0 && coord.col >= 1.0 && coord.col + (text.chars().count() as f64) < args.terminal_cols as f64 - 1.0
+  });
+  labels
+}
+
+/// Writes the position under the mouse (or the view center) into the bottom border.
+pub fn draw_position_readout(args: &FRadarArgs, ui: &FRadarUi, color: Color) -> anyhow::Result<()> {
+  let (marker, position): (&str, Position) = match ui.mouse {
+    Some(mouse) => ("⌖", Position::from_terminal_cell(mouse, args)),
+    None => ("◎", args.origin),
+  };
+
+  let text: String = format!(" {} {} ", marker, args.coords.format(&position));
+  let width: u16 = text.chars().count() as u16;
+  if args.terminal_cols < width + 4 {
+    return Ok(());
+  }
+
+  queue!(
+    std::io::stdout(),
+    cursor::MoveTo(args.terminal_cols - width - 2, args.terminal_rows - 1),
+    style::SetForegroundColor(color),
+    style::Print(text),
+    style::ResetColor,
+  )?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::model::{CoordFormat, Units};
+
+  #[test]
+  fn picks_nice_distances() {
+    for (limit, nice) in [(7.3, 5.0), (5.0, 5.0), (2.5, 2.0), (1.9, 1.0), (43.0, 20.0), (100.0, 100.0), (0.3, 0.2)] {
+      assert!((nice_distance(limit) - nice).abs() < 1e-9, "{} gave {}", limit, nice_distance(limit));
+    }
+  }
+
+  #[test]
+  fn spaces_graticule_lines_to_the_zoom() {
+    let args: FRadarArgs = FRadarArgs { origin: Position { lat: 47.45, long: -122.3 }, radius: 30.0, terminal_cols: 120, terminal_rows: 40, ..Default::default() };
+    let graticule = |args: &FRadarArgs| draw_graticule(&mut BrailleCanvas::new(args), args, Color::Reset);
+    let texts = |labels: &[(Coord<f64>, String)], row: bool| -> Vec<String> {
+      labels.iter().filter(|(coord, _)| (coord.row == 0.0) != row).map(|(_, text)| text.clone()).collect()
+    };
+
+    // Latitudes down the left edge, longitudes along the top, at least 5 rows and 16 columns apart.
+    let labels: Vec<(Coord<f64>, String)> = graticule(&args);
+    assert_eq!(texts(&labels, true), ["47°24'N", "47°26'N", "47°28'N", "47°30'N"]);
+    assert_eq!(texts(&labels, false), ["122°40'W", "122°30'W", "122°20'W", "122°10'W", "122°00'W"]);
+    assert!(labels.iter().all(|(coord, _)| coord.row == 0.0 || coord.col == 0.0));
+    assert!(labels.windows(2).filter(|pair| pair[1].0.col == 0.0).all(|pair| pair[0].0.row - pair[1].0.row >= 5.0));
+
+    let labels: Vec<(Coord<f64>, String)> = graticule(&FRadarArgs { radius: 300.0, coords: CoordFormat::Decimal, ..args });
+    assert_eq!(texts(&labels, true), ["47", "47.25", "47.5", "47.75", "48"]);
+    assert_eq!(texts(&labels, false), ["-126", "-124", "-122", "-120"]);
+  }
+
+  #[test]
+  fn labels_rings_and_compass_points() {
+    let args: FRadarArgs = FRadarArgs { radius: 30.0, terminal_cols: 80, terminal_rows: 80, ..Default::default() };
+    let rings = |args: &FRadarArgs| draw_range_rings(&mut BrailleCanvas::new(args), args, Color::Reset);
+
+    // Rings every 10 miles; only the first has its label on screen, and ticks go round it.
+    let labels: Vec<(Coord<f64>, String)> = rings(&args);
+    let texts: Vec<&str> = labels.iter().map(|(_, text)| text.as_str()).collect();
+    assert_eq!(texts, ["10 mi", "N", "030", "060", "E", "120", "150", "S", "210", "240", "W", "300", "330"]);
+    let (north, ring): (&Coord<f64>, &Coord<f64>) = (&labels[1].0, &labels[0].0);
+    assert_eq!(north.col, 39.5);
+    assert!(north.row < ring.row);
+
+    let texts: Vec<String> = rings(&FRadarArgs { units: Units::Nautical, ..args }).into_iter().map(|(_, text)| text).collect();
+    assert_eq!(texts[0], "5 nm");
+
+    // Too short for a whole ring, so no compass ticks.
+    let texts: Vec<String> = rings(&FRadarArgs { terminal_rows: 20, ..args }).into_iter().map(|(_, text)| text).collect();
+    assert!(!texts.contains(&"N".to_string()), "{:?}", texts);
+  }
+}
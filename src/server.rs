@@ -0,0 +1,191 @@
+use std::{convert::Infallible, net::{IpAddr, Ipv4Addr, SocketAddr}, sync::{Arc, Mutex}, time::Duration};
+
+use anyhow::{anyhow, Context};
+use axum::{extract::State, http::StatusCode, response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response}, routing::get, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tokio::{net::TcpListener, sync::broadcast};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::{alerts::Condition, model::{FRadarArgs, FlightData}, snapshot::Snapshot, ticker::TickerEvent};
+
+
+pub const DEFAULT_PORT: u16 = 8787;
+
+/// Events a slow `/events` client can fall behind by before it misses some.
+const EVENT_BACKLOG: usize = 256;
+
+/// An event as sent on `/events`, named after the ticker event (`enter`, `leave`, `emergency`, ...).
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEvent {
+  pub event: String,
+  pub time: DateTime<Utc>,
+  pub hex: String,
+  pub callsign: String,
+  pub lat: f64,
+  pub lon: f64,
+  pub altitude_ft: Option<f64>,
+  pub detail: String,
+}
+
+impl ServerEvent {
+  pub fn new(now_millis: i64, event: &str, condition: &Condition) -> ServerEvent {
+    ServerEvent {
+      event: event.to_string(),
+      time: DateTime::from_timestamp_millis(now_millis).unwrap_or_default(),
+      hex: condition.hex.clone(),
+      callsign: condition.callsign.clone(),
+      lat: condition.position.lat,
+      lon: condition.position.long,
+      altitude_ft: condition.altitude,
+      detail: condition.reason.clone(),
+    }
+  }
+}
+
+/// What the HTTP server serves: the latest snapshot and a feed of events. Updated by
+/// whichever loop is fetching data, the scope's controller or the ticker.
+#[derive(Debug, Clone)]
+pub struct Server {
+  snapshot: Arc<Mutex<Option<Snapshot>>>,
+  events: broadcast::Sender<ServerEvent>,
+  /// Data older than this makes `/health` fail.
+  stale_after: Duration,
+  /// Why the server stopped, if it has, until someone reports it.
+  failure: Arc<Mutex<Option<String>>>,
+}
+
+impl Server {
+  pub fn new(data_interval: Duration) -> Server {
+    Server {
+      snapshot: Arc::new(Mutex::new(None)),
+      events: broadcast::channel(EVENT_BACKLOG).0,
+      stale_after: (data_interval * 10).max(Duration::from_secs(30)),
+      failure: Arc::new(Mutex::new(None)),
+    }
+  }
+
+  /// Takes a new snapshot and sends the events the caller's tracker found in it.
+  pub fn update(&self, flights_data: &FlightData, args: &FRadarArgs, events: &[TickerEvent]) {
+    for event in events.iter() {
+      self.announce(ServerEvent::new(flights_data.epoch_timestamp, &event.kind.name().to_lowercase(), &event.condition));
+    }
+    *self.snapshot.lock().unwrap() = Some(Snapshot::new(flights_data, args));
+  }
+
+  /// Sends an event to every `/events` client. Nobody listening is fine.
+  pub fn announce(&self, event: ServerEvent) {
+    let _ = self.events.send(event);
+  }
+
+  /// Binds now, so a taken port is reported at startup, and serves in the background.
+  /// Returns the address it is listening on.
+  pub async fn start(&self, address: SocketAddr) -> anyhow::Result<SocketAddr> {
+    let listener: TcpListener = TcpListener::bind(address).await.with_context(|| format!("failed to listen on {}", address))?;
+    let local_address: SocketAddr = listener.local_addr()?;
+    let router: Router = Router::new()
+      .route("/aircraft", get(aircraft))
+      .route("/events", get(events))
+      .route("/health", get(health))
+      .with_state(self.clone());
+
+    let failure: Arc<Mutex<Option<String>>> = self.failure.clone();
+    tokio::spawn(async move {
+      if let Err(error) = axum::serve(listener, router).await {
+        *failure.lock().unwrap() = Some(error.to_string());
+      }
+    });
+    Ok(local_address)
+  }
+
+  /// Why the server stopped, once, if it has.
+  pub fn take_failure(&self) -> Option<String> {
+    self.failure.lock().unwrap().take()
+  }
+}
+
+async fn aircraft(State(server): State<Server>) -> Response {
+  match server.snapshot.lock().unwrap().as_ref() {
+    Some(snapshot) => Json(snapshot).into_response(),
+    None => (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": "no data yet" }))).into_response(),
+  }
+}
+
+async fn events(State(server): State<Server>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+  // A client too slow to keep up skips what it missed rather than being disconnected.
+  let stream = BroadcastStream::new(server.events.subscribe())
+    .filter_map(|event| event.ok())
+    .map(|event| Ok(Event::default().event(event.event.clone()).json_data(&event).unwrap_or_default()));
+  Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn health(State(server): State<Server>) -> Response {
+  let updated: Option<DateTime<Utc>> = server.snapshot.lock().unwrap().as_ref().map(|snapshot| snapshot.time);
+  let age: Option<Duration> = updated.map(|time| (Utc::now() - time).to_std().unwrap_or_default());
+  let (status, text) = match age {
+    Some(age) if age <= server.stale_after => (StatusCode::OK, "ok"),
+    Some(_) => (StatusCode::SERVICE_UNAVAILABLE, "stale"),
+    None => (StatusCode::SERVICE_UNAVAILABLE, "starting"),
+  };
+  (status, Json(json!({ "status": text, "updated": updated, "age_seconds": age.map(|age| age.as_secs_f64()) }))).into_response()
+}
+
+/// Parses `8787` (on localhost), `127.0.0.1:8787` or `0.0.0.0:8787` (every interface).
+pub fn parse_address(text: &str) -> anyhow::Result<SocketAddr> {
+  let text: &str = text.trim();
+  match text.parse::<u16>() {
+    Ok(port) => Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)),
+    Err(_) => text.parse().map_err(|_| anyhow!("expected a port or an address like 127.0.0.1:{}, got '{}'", DEFAULT_PORT, text)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fixtures::{aircraft, flights_data}, model::Position, ticker::TickerEventKind};
+
+  #[test]
+  fn parses_addresses() {
+    assert_eq!(parse_address("8787").unwrap(), "127.0.0.1:8787".parse::<SocketAddr>().unwrap());
+    assert_eq!(parse_address(" 0.0.0.0:9000 ").unwrap(), "0.0.0.0:9000".parse::<SocketAddr>().unwrap());
+    assert!(parse_address("localhost").is_err());
+  }
+
+  #[tokio::test]
+  async fn serves_health_aircraft_and_events() {
+    let server: Server = Server::new(Duration::from_secs(1));
+    let address: SocketAddr = server.start("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let client = reqwest::Client::new();
+    let url = |path: &str| format!("http://{}{}", address, path);
+
+    let health = client.get(url("/health")).send().await.unwrap();
+    assert_eq!(health.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(health.json::<serde_json::Value>().await.unwrap()["status"], "starting");
+    assert_eq!(client.get(url("/aircraft")).send().await.unwrap().status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    let mut events = client.get(url("/events")).send().await.unwrap();
+    assert_eq!(events.status(), reqwest::StatusCode::OK);
+
+    let info = aircraft("abc123", &Position { lat: 47.45, long: -122.3 }, 3000);
+    let data: FlightData = flights_data(Utc::now().timestamp_millis(), vec![info.clone()]);
+    let args: FRadarArgs = FRadarArgs { origin: Position { lat: 47.45, long: -122.3 }, ..Default::default() };
+    let enter: TickerEvent = TickerEvent { kind: TickerEventKind::Enter, condition: Condition::new(&info, data.flights[0].0, "entered range".to_string()) };
+    server.update(&data, &args, &[enter]);
+
+    let mut received: String = String::new();
+    while !received.contains("\n\n") {
+      let chunk = tokio::time::timeout(Duration::from_secs(5), events.chunk()).await.unwrap().unwrap().unwrap();
+      received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    assert!(received.starts_with("event: enter\n"), "{}", received);
+    assert!(received.contains("\"hex\":\"abc123\""), "{}", received);
+    assert!(received.contains("\"detail\":\"entered range\""), "{}", received);
+
+    let health = client.get(url("/health")).send().await.unwrap();
+    assert_eq!(health.status(), reqwest::StatusCode::OK);
+    let snapshot: serde_json::Value = client.get(url("/aircraft")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(snapshot["aircraft"][0]["hex"], "abc123");
+    assert_eq!(server.take_failure(), None);
+  }
+}
@@ -0,0 +1,198 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Settings, filter::Filter, model::{CoordFormat, FRadarData, Layers, ListColumn, ListMode, Position, Theme, Units}};
+
+
+/// The parts of a running session worth restoring with `--resume`.
+///
+/// Every field is optional so that state files written by older versions still load.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedView {
+  pub saved_at: Option<DateTime<Utc>>,
+
+  pub origin: Option<Position>,
+  pub starting_origin: Option<Position>,
+  pub radius: Option<f64>,
+  pub filter: Option<String>,
+  pub theme: Option<Theme>,
+  pub units: Option<Units>,
+  pub coords: Option<CoordFormat>,
+  pub trail_seconds: Option<f64>,
+  pub leader_seconds: Option<f64>,
+
+  pub selected_hex: Option<String>,
+  pub follow: Option<bool>,
+  pub list_mode: Option<ListMode>,
+  pub list_sort: Option<ListColumn>,
+  pub list_descending: Option<bool>,
+  pub layers: Option<Layers>,
+}
+
+impl SavedView {
+  pub fn capture(fradar_data_ref: &FRadarData) -> SavedView {
+    let args = &fradar_data_ref.args;
+    let ui = &fradar_data_ref.ui;
+
+    SavedView {
+      saved_at: Some(Utc::now()),
+
+      origin: Some(args.origin),
+      starting_origin: Some(args.starting_origin),
+      radius: Some(args.radius),
+      filter: fradar_data_ref.filter.as_ref().map(|filter| filter.source().to_string()),
+      theme: Some(args.theme),
+      units: Some(args.units),
+      coords: Some(args.coords),
+      trail_seconds: Some(args.trail_duration.as_secs_f64()),
+      leader_seconds: Some(args.leader_duration.as_secs_f64()),
+
+      selected_hex: ui.selected_hex.clone(),
+      follow: Some(ui.follow),
+      list_mode: Some(ui.list_mode),
+      list_sort: Some(ui.list_sort),
+      list_descending: Some(ui.list_descending),
+      layers: Some(ui.layers.clone()),
+    }
+  }
+
+  /// Overlays the saved view onto settings resolved from defaults, config and profile.
+  pub fn apply(&self, settings: &mut Settings) -> anyhow::Result<()> {
+    let args = &mut settings.args;
+    let ui = &mut settings.ui;
+
+    if let Some(origin) = self.origin {
+      args.origin = origin;
+    }
+    if let Some(starting_origin) = self.starting_origin {
+      args.starting_origin = starting_origin;
+    }
+    if let Some(radius) = self.radius.filter(|radius| *radius > 0.0) {
+      args.radius = radius;
+    }
+    if let Some(theme) = self.theme {
+      args.theme = theme;
+    }
+    if let Some(units) = self.units {
+      args.units = units;
+    }
+    if let Some(coords) = self.coords {
+      args.coords = coords;
+    }
+    if let Some(trail_seconds) = self.trail_seconds.filter(|seconds| seconds.is_finite() && *seconds >= 0.0) {
+      args.trail_duration = std::time::Duration::from_secs_f64(trail_seconds);
+    }
+    if let Some(leader_seconds) = self.leader_seconds.filter(|seconds| seconds.is_finite() && *seconds >= 0.0) {
+      args.leader_duration = std::time::Duration::from_secs_f64(leader_seconds);
+    }
+    if let Some(filter) = &self.filter {
+      settings.filter = Some(Filter::parse(filter).map_err(|error| anyhow!("saved filter: {}", error))?);
+    }
+
+    ui.selected_hex = self.selected_hex.clone();
+    ui.show_details = ui.selected_hex.is_some();
+    ui.follow = self.follow.unwrap_or(false) && ui.selected_hex.is_some();
+    if let Some(list_mode) = self.list_mode {
+      ui.list_mode = list_mode;
+    }
+    if let Some(list_sort) = self.list_sort {
+      ui.list_sort = list_sort;
+    }
+    if let Some(list_descending) = self.list_descending {
+      ui.list_descending = list_descending;
+    }
+    if let Some(layers) = &self.layers {
+      ui.layers = layers.clone();
+    }
+
+    Ok(())
+  }
+}
+
+/// `$XDG_STATE_HOME/fradar`, falling back to `~/.local/state/fradar`.
+pub fn state_dir() -> Option<PathBuf> {
+  std::env::var_os("XDG_STATE_HOME")
+    .map(PathBuf::from)
+    .filter(|path| path.is_absolute())
+    .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("state")))
+    .map(|state| state.join("fradar"))
+}
+
+pub fn state_file_path() -> Option<PathBuf> {
+  state_dir().map(|state| state.join("state.json"))
+}
+
+pub fn save_view(fradar_data_ref: &FRadarData) -> anyhow::Result<()> {
+  let path: PathBuf = state_file_path().ok_or_else(|| anyhow!("can't determine a state directory"))?;
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+
+  // Write then rename, so a crash mid-write never leaves a truncated state file.
+  let temporary: PathBuf = path.with_extension("json.tmp");
+  std::fs::write(&temporary, serde_json::to_string_pretty(&SavedView::capture(fradar_data_ref))?)?;
+  std::fs::rename(&temporary, &path)?;
+  Ok(())
+}
+
+pub fn load_view() -> anyhow::Result<SavedView> {
+  let path: PathBuf = state_file_path().ok_or_else(|| anyhow!("can't determine a state directory"))?;
+  let contents: String = std::fs::read_to_string(&path)
+    .with_context(|| format!("no saved view to resume ({})", path.display()))?;
+  serde_json::from_str(&contents)
+    .with_context(|| format!("{} is not a valid state file", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+  use crate::{fixtures::fradar_data, model::FRadarArgs};
+
+  #[test]
+  fn restores_the_captured_view() {
+    let mut fradar_data: FRadarData = fradar_data(FRadarArgs { radius: 12.5, theme: Theme::Amber, trail_duration: Duration::from_secs(30), ..Default::default() });
+    fradar_data.args.origin = Position { lat: 47.45, long: -122.3 };
+    fradar_data.filter = Some(Filter::parse("alt < 3000").unwrap());
+    fradar_data.ui.selected_hex = Some("a1b2c3".to_string());
+    fradar_data.ui.follow = true;
+    fradar_data.ui.list_mode = ListMode::Split;
+    fradar_data.ui.layers.rings = false;
+
+    let json: String = serde_json::to_string(&SavedView::capture(&fradar_data)).unwrap();
+    let mut settings: Settings = Settings::default();
+    serde_json::from_str::<SavedView>(&json).unwrap().apply(&mut settings).unwrap();
+
+    assert_eq!(settings.args.origin, fradar_data.args.origin);
+    assert_eq!(settings.args.radius, 12.5);
+    assert_eq!(settings.args.theme, Theme::Amber);
+    assert_eq!(settings.args.trail_duration, Duration::from_secs(30));
+    assert_eq!(settings.filter.as_ref().map(Filter::source), Some("alt < 3000"));
+    assert_eq!(settings.ui.selected_hex.as_deref(), Some("a1b2c3"));
+    assert!(settings.ui.show_details && settings.ui.follow);
+    assert_eq!(settings.ui.list_mode, ListMode::Split);
+    assert!(!settings.ui.layers.rings);
+  }
+
+  #[test]
+  fn missing_fields_keep_the_configured_settings() {
+    let mut settings: Settings = Settings::default();
+    settings.args.radius = 20.0;
+    serde_json::from_str::<SavedView>(r#"{"radius": -1, "follow": true, "unknown": 1}"#).unwrap().apply(&mut settings).unwrap();
+
+    assert_eq!(settings.args.radius, 20.0);
+    assert_eq!(settings.args.origin, Settings::default().args.origin);
+    assert!(!settings.ui.follow, "nothing selected to follow");
+  }
+
+  #[test]
+  fn rejects_a_bad_saved_filter() {
+    let error: String = SavedView { filter: Some("alt >".to_string()), ..Default::default() }.apply(&mut Settings::default()).unwrap_err().to_string();
+    assert!(error.starts_with("saved filter: "), "{}", error);
+  }
+}
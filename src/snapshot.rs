@@ -0,0 +1,246 @@
+use std::{io::Write, str::FromStr, time::Duration};
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{aircraft_list::{altitude_feet, format_header, format_row, sorted_rows}, config::Settings, controller::{flight_data, request}, model::{ADSBAircraftInformation, ADSBData, FRadarArgs, FlightData, Position}};
+
+
+/// How long `--once` waits for the data source before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+  #[default]
+  Table,
+  Json,
+  Csv,
+}
+
+impl FromStr for SnapshotFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "table" => Ok(SnapshotFormat::Table),
+      "json" => Ok(SnapshotFormat::Json),
+      "csv" => Ok(SnapshotFormat::Csv),
+      _ => bail!("unknown format '{}' (expected json, csv or table)", s),
+    }
+  }
+}
+
+/// One aircraft as printed by `--once`. Distances are in the chosen units, the rest in
+/// feet, knots and degrees.
+#[derive(Debug, Clone, Serialize)]
+pub struct Row {
+  hex: String,
+  callsign: Option<String>,
+  registration: Option<String>,
+  #[serde(rename = "type")]
+  aircraft_type: Option<String>,
+  squawk: Option<String>,
+  lat: f64,
+  lon: f64,
+  altitude_ft: Option<f64>,
+  on_ground: bool,
+  ground_speed_kt: Option<f64>,
+  track: Option<f64>,
+  vertical_rate_fpm: Option<i32>,
+  distance: f64,
+  bearing: f64,
+}
+
+/// The aircraft at one moment, nearest first, as `--once --format json` and the server's
+/// `/aircraft` give it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+  pub time: DateTime<Utc>,
+  pub origin: Position,
+  pub distance_unit: &'static str,
+  pub aircraft: Vec<Row>,
+}
+
+impl Snapshot {
+  pub fn new(flights_data: &FlightData, args: &FRadarArgs) -> Snapshot {
+    let mut aircraft: Vec<Row> = flights_data.aircraft.iter().zip(flights_data.flights.iter())
+      .map(|(info, (position, _))| Row::new(info, position, args))
+      .collect();
+    aircraft.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    Snapshot {
+      time: DateTime::from_timestamp_millis(flights_data.epoch_timestamp).unwrap_or_default(),
+      origin: args.origin,
+      distance_unit: args.units.distance_unit(),
+      aircraft,
+    }
+  }
+}
+
+impl Row {
+  fn new(info: &ADSBAircraftInformation, position: &Position, args: &FRadarArgs) -> Row {
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+    Row {
+      hex: info.hex.clone(),
+      callsign: text(&info.flight),
+      registration: text(&info.r),
+      aircraft_type: text(&info.t),
+      squawk: text(&info.squawk),
+      lat: position.lat,
+      lon: position.long,
+      altitude_ft: altitude_feet(info),
+      on_ground: info.is_on_ground(),
+      ground_speed_kt: info.gs.map(f64::from),
+      track: info.track.map(f64::from),
+      vertical_rate_fpm: info.geom_rate,
+      distance: (args.units.distance(args.origin.distance_miles(position)) * 100.0).round() / 100.0,
+      bearing: args.origin.bearing_to(position).round(),
+    }
+  }
+}
+
+/// Fetches one snapshot around the configured origin and prints it to stdout, without
+/// touching the terminal.
+pub async fn run(settings: &Settings, format: SnapshotFormat) -> anyhow::Result<()> {
+  let args: FRadarArgs = settings.args;
+  let response = request(&reqwest::Client::new(), &args)
+    .timeout(REQUEST_TIMEOUT)
+    .send().await
+    .context("request failed")?
+    .error_for_status()?;
+  let adsb_data: ADSBData = response.json().await.context("unexpected response from the data source")?;
+  print(settings, format, adsb_data.ac)
+}
+
+/// Prints the aircraft inside the radius that pass the filter, nearest first.
+fn print(settings: &Settings, format: SnapshotFormat, mut aircraft: Vec<ADSBAircraftInformation>) -> anyhow::Result<()> {
+  let args: FRadarArgs = settings.args;
+  aircraft.retain(|info| {
+    args.origin.distance_miles(&Position { lat: info.lat, long: info.lon }) <= args.radius &&
+      settings.filter.as_ref().is_none_or(|filter| filter.matches(info))
+  });
+  let flights_data: FlightData = flight_data(aircraft)?;
+
+  let mut out = std::io::stdout().lock();
+  match format {
+    SnapshotFormat::Table => {
+      writeln!(out, "{}", format_header(&settings.ui))?;
+      for row in sorted_rows(&flights_data, &args, &settings.ui, flights_data.epoch_timestamp).iter() {
+        writeln!(out, "{}", format_row(row))?;
+      }
+    },
+    SnapshotFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&Snapshot::new(&flights_data, &args))?)?,
+    SnapshotFormat::Csv => write_csv(&mut out, &Snapshot::new(&flights_data, &args))?,
+  }
+  Ok(())
+}
+
+type CsvColumn = (&'static str, fn(&Row) -> String);
+
+/// The CSV columns in order, each with the value it takes from a row. The `distance`
+/// header gets the distance unit appended.
+const CSV_COLUMNS: [CsvColumn; 14] = [
+  ("hex", |row| row.hex.clone()),
+  ("callsign", |row| row.callsign.clone().unwrap_or_default()),
+  ("registration", |row| row.registration.clone().unwrap_or_default()),
+  ("type", |row| row.aircraft_type.clone().unwrap_or_default()),
+  ("squawk", |row| row.squawk.clone().unwrap_or_default()),
+  ("lat", |row| row.lat.to_string()),
+  ("lon", |row| row.lon.to_string()),
+  ("altitude_ft", |row| optional(row.altitude_ft)),
+  ("on_ground", |row| row.on_ground.to_string()),
+  ("ground_speed_kt", |row| optional(row.ground_speed_kt)),
+  ("track", |row| optional(row.track)),
+  ("vertical_rate_fpm", |row| optional(row.vertical_rate_fpm)),
+  ("distance", |row| row.distance.to_string()),
+  ("bearing", |row| row.bearing.to_string()),
+];
+
+fn optional(value: Option<impl ToString>) -> String {
+  value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn write_csv(out: impl Write, snapshot: &Snapshot) -> anyhow::Result<()> {
+  let mut writer = csv::Writer::from_writer(out);
+  writer.write_record(CSV_COLUMNS.iter().map(|(name, _)| match *name {
+    "distance" => format!("distance_{}", snapshot.distance_unit),
+    name => name.to_string(),
+  }))?;
+  for row in snapshot.aircraft.iter() {
+    writer.write_record(CSV_COLUMNS.iter().map(|(_, value)| value(row)))?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::Value;
+
+  use super::*;
+  use crate::{fixtures::{aircraft, flights_data}, model::{Units, MILES_PER_NM}};
+
+  const ORIGIN: Position = Position { lat: 47.45, long: -122.3 };
+
+  fn snapshot() -> Snapshot {
+    let far: ADSBAircraftInformation = ADSBAircraftInformation {
+      flight: Some("ASA12   ".to_string()),
+      t: Some("B739".to_string()),
+      gs: Some(420.5),
+      track: Some(180.0),
+      geom_rate: Some(-640),
+      ..aircraft("a1b2c3", &ORIGIN.destination(90.0, 10.0 * MILES_PER_NM), 12000)
+    };
+    let near: ADSBAircraftInformation = ADSBAircraftInformation {
+      flight: Some("  ".to_string()),
+      alt_baro: Some("ground".to_string()),
+      ..aircraft("d4e5f6", &ORIGIN.destination(225.0, 2.5 * MILES_PER_NM), 0)
+    };
+    let args: FRadarArgs = FRadarArgs { origin: ORIGIN, units: Units::Nautical, ..Default::default() };
+    Snapshot::new(&flights_data(1_767_225_600_000, vec![far, near]), &args)
+  }
+
+  #[test]
+  fn parses_formats() {
+    assert_eq!("JSON".parse::<SnapshotFormat>().unwrap(), SnapshotFormat::Json);
+    assert_eq!("csv".parse::<SnapshotFormat>().unwrap(), SnapshotFormat::Csv);
+    assert_eq!("xml".parse::<SnapshotFormat>().unwrap_err().to_string(), "unknown format 'xml' (expected json, csv or table)");
+  }
+
+  #[test]
+  fn lists_the_nearest_aircraft_first() {
+    let snapshot: Snapshot = snapshot();
+    let rows: Vec<(&str, f64, f64)> = snapshot.aircraft.iter().map(|row| (row.hex.as_str(), row.distance, row.bearing)).collect();
+    assert_eq!(rows, [("d4e5f6", 2.5, 225.0), ("a1b2c3", 10.0, 90.0)]);
+    assert_eq!(snapshot.distance_unit, "nm");
+    assert_eq!(snapshot.time.to_rfc3339(), "2026-01-01T00:00:00+00:00");
+
+    let near: &Row = &snapshot.aircraft[0];
+    assert_eq!((near.callsign.as_deref(), near.on_ground, near.altitude_ft), (None, true, Some(0.0)));
+  }
+
+  #[test]
+  fn writes_csv_with_the_distance_unit() {
+    let mut out: Vec<u8> = Vec::new();
+    write_csv(&mut out, &snapshot()).unwrap();
+    let csv: String = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "hex,callsign,registration,type,squawk,lat,lon,altitude_ft,on_ground,ground_speed_kt,track,vertical_rate_fpm,distance_nm,bearing");
+    assert!(lines[1].starts_with("d4e5f6,,,,,"), "{}", lines[1]);
+    assert!(lines[1].ends_with(",0,true,,,,2.5,225"), "{}", lines[1]);
+    assert!(lines[2].starts_with("a1b2c3,ASA12,,B739,,"), "{}", lines[2]);
+    assert!(lines[2].ends_with(",12000,false,420.5,180,-640,10,90"), "{}", lines[2]);
+  }
+
+  #[test]
+  fn serializes_to_json() {
+    let json: Value = serde_json::to_value(snapshot()).unwrap();
+    assert_eq!(json["distance_unit"], "nm");
+    assert_eq!(json["origin"]["lat"], 47.45);
+    assert_eq!(json["aircraft"][1]["type"], "B739");
+    assert_eq!(json["aircraft"][1]["vertical_rate_fpm"], -640);
+    assert_eq!(json["aircraft"][0]["callsign"], Value::Null);
+  }
+}
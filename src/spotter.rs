@@ -0,0 +1,198 @@
+use crate::{aircraft_list::altitude_feet, model::{ADSBAircraftInformation, FRadarArgs, FlightData, Position, MILES_PER_NM}};
+
+
+/// WGS84 semi-major axis (meters) and first eccentricity squared.
+const WGS84_A: f64 = 6_378_137.0;
+const WGS84_E2: f64 = 6.694_379_990_14e-3;
+
+const METERS_PER_FOOT: f64 = 0.3048;
+const METERS_PER_MILE: f64 = 1609.344;
+
+/// Only passes this soon are worth predicting.
+const PASS_HORIZON_SECONDS: f64 = 600.0;
+
+/// Where to look from the observer: degrees clockwise from true north, degrees above the horizon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LookAngle {
+  pub azimuth: f64,
+  pub elevation: f64,
+  pub slant_miles: f64,
+}
+
+/// The point where an aircraft holding its track comes closest to the observer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+  pub seconds: f64,
+  pub miles: f64,
+  /// Bearing from the observer to the point of closest approach.
+  pub bearing: f64,
+  pub altitude: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sighting {
+  pub index: usize,
+  pub look: LookAngle,
+  pub pass: Option<Pass>,
+}
+
+fn ecef(position: &Position, meters: f64) -> [f64; 3] {
+  let (lat, long) = (position.lat.to_radians(), position.long.to_radians());
+  let prime_vertical: f64 = WGS84_A / (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+  [
+    (prime_vertical + meters) * lat.cos() * long.cos(),
+    (prime_vertical + meters) * lat.cos() * long.sin(),
+    (prime_vertical * (1.0 - WGS84_E2) + meters) * lat.sin(),
+  ]
+}
+
+/// Azimuth, elevation and slant range on the WGS84 ellipsoid, by way of the
+/// observer's local east-north-up frame.
+pub fn look_angle(observer: &Position, observer_feet: f64, target: &Position, target_feet: f64) -> LookAngle {
+  let from: [f64; 3] = ecef(observer, observer_feet * METERS_PER_FOOT);
+  let to: [f64; 3] = ecef(target, target_feet * METERS_PER_FOOT);
+  let (dx, dy, dz) = (to[0] - from[0], to[1] - from[1], to[2] - from[2]);
+
+  let (lat, long) = (observer.lat.to_radians(), observer.long.to_radians());
+  let east: f64 = -long.sin() * dx + long.cos() * dy;
+  let north: f64 = -lat.sin() * long.cos() * dx - lat.sin() * long.sin() * dy + lat.cos() * dz;
+  let up: f64 = lat.cos() * long.cos() * dx + lat.cos() * long.sin() * dy + lat.sin() * dz;
+
+  LookAngle {
+    azimuth: east.atan2(north).to_degrees().rem_euclid(360.0),
+    elevation: up.atan2(east.hypot(north)).to_degrees(),
+    slant_miles: (dx * dx + dy * dy + dz * dz).sqrt() / METERS_PER_MILE,
+  }
+}
+
+/// The closest approach to the observer if the aircraft is still closing on it.
+pub fn closest_approach(observer: &Position, position: &Position, info: &ADSBAircraftInformation) -> Option<Pass> {
+  let (track, gs) = (info.track? as f64, info.gs? as f64);
+  if gs <= 0.0 || info.is_on_ground() {
+    return None;
+  }
+
+  let east: f64 = ((position.long - observer.long + 540.0).rem_euclid(360.0) - 180.0) * 60.0 * observer.lat.to_radians().cos();
+  let north: f64 = (position.lat - observer.lat) * 60.0;
+  let (velocity_east, velocity_north) = (gs * track.to_radians().sin(), gs * track.to_radians().cos());
+  let hours: f64 = -(east * velocity_east + north * velocity_north) / (gs * gs);
+  if hours <= 0.0 {
+    return None;
+  }
+
+  let closest: Position = position.destination(track, gs * MILES_PER_NM * hours);
+  let altitude: f64 = altitude_feet(info)? + info.geom_rate.unwrap_or(0) as f64 * hours * 60.0;
+  Some(Pass { seconds: hours * 3600.0, miles: observer.distance_miles(&closest), bearing: observer.bearing_to(&closest), altitude })
+}
+
+/// Geometric altitude where the aircraft reports it, since that is what the ellipsoid math wants.
+fn height_feet(info: &ADSBAircraftInformation) -> Option<f64> {
+  match info.is_on_ground() {
+    true => Some(0.0),
+    false => info.alt_geom.map(f64::from).or_else(|| altitude_feet(info)),
+  }
+}
+
+pub fn look_at(info: &ADSBAircraftInformation, position: &Position, args: &FRadarArgs) -> Option<LookAngle> {
+  Some(look_angle(&args.starting_origin, args.observer_elevation, position, height_feet(info)?))
+}
+
+/// Every aircraft with a known altitude as seen from the observer, highest in the sky first.
+pub fn sightings(flights_data: &FlightData, args: &FRadarArgs) -> Vec<Sighting> {
+  let mut sightings: Vec<Sighting> = flights_data.aircraft.iter().zip(flights_data.flights.iter()).enumerate()
+    .filter_map(|(index, (info, (position, _)))| {
+      let look: LookAngle = look_at(info, position, args)?;
+      let pass: Option<Pass> = closest_approach(&args.starting_origin, position, info);
+      Some(Sighting { index, look, pass })
+    })
+    .collect();
+  sightings.sort_by(|a, b| b.look.elevation.total_cmp(&a.look.elevation));
+  sightings
+}
+
+/// The nearest upcoming pass within the next few minutes.
+pub fn next_pass(sightings: &[Sighting]) -> Option<&Sighting> {
+  sightings.iter()
+    .filter(|sighting| sighting.pass.is_some_and(|pass| pass.seconds <= PASS_HORIZON_SECONDS))
+    .min_by(|a, b| a.pass.unwrap().miles.total_cmp(&b.pass.unwrap().miles))
+}
+
+/// Eight-point compass name for a bearing.
+pub fn compass_point(bearing: f64) -> &'static str {
+  const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+  POINTS[((bearing.rem_euclid(360.0) / 45.0).round() as usize) % 8]
+}
+
+pub fn format_seconds(seconds: f64) -> String {
+  let seconds: u64 = seconds.round() as u64;
+  format!("{}m{:02}s", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::flying;
+
+  const OBSERVER: Position = Position { lat: 47.45, long: -122.3 };
+
+  #[test]
+  fn overhead_is_straight_up() {
+    let look: LookAngle = look_angle(&OBSERVER, 0.0, &OBSERVER, 10_000.0);
+    assert!((look.elevation - 90.0).abs() < 1e-6, "{:?}", look);
+    assert!((look.slant_miles - 10_000.0 / 5280.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn due_north_at_the_same_height_dips_below_the_horizon() {
+    let target: Position = OBSERVER.destination(0.0, 20.0);
+    let look: LookAngle = look_angle(&OBSERVER, 5000.0, &target, 5000.0);
+    assert!(look.azimuth.min(360.0 - look.azimuth) < 0.01, "{:?}", look);
+    // The curve of the earth drops it about 0.14° at 20 miles.
+    assert!(look.elevation < 0.0 && look.elevation > -0.3, "{:?}", look);
+    assert!((look.slant_miles - 20.0).abs() < 0.1);
+  }
+
+  #[test]
+  fn due_east_at_forty_five_degrees() {
+    let target: Position = OBSERVER.destination(90.0, 10.0);
+    let look: LookAngle = look_angle(&OBSERVER, 0.0, &target, 10.0 * 5280.0);
+    assert!((look.azimuth - 90.0).abs() < 0.1, "{:?}", look);
+    assert!((look.elevation - 45.0).abs() < 0.5, "{:?}", look);
+  }
+
+  #[test]
+  fn closest_approach_of_a_passing_aircraft() {
+    // 10nm south and 2nm west, heading north: passes 2nm abeam in 90 seconds at 400kt.
+    let position: Position = OBSERVER.destination(180.0, 10.0 * MILES_PER_NM).destination(270.0, 2.0 * MILES_PER_NM);
+    let info: ADSBAircraftInformation = ADSBAircraftInformation { geom_rate: Some(-800), ..flying(&position, 6000, 0.0, 400.0) };
+    let pass: Pass = closest_approach(&OBSERVER, &position, &info).unwrap();
+    assert!((pass.seconds - 90.0).abs() < 1.0, "{:?}", pass);
+    assert!((pass.miles - 2.0 * MILES_PER_NM).abs() < 0.05, "{:?}", pass);
+    assert!((pass.bearing - 270.0).abs() < 1.0, "{:?}", pass);
+    assert!((pass.altitude - 4800.0).abs() < 15.0, "{:?}", pass);
+  }
+
+  #[test]
+  fn no_approach_when_moving_away_or_unknown() {
+    let position: Position = OBSERVER.destination(0.0, 10.0);
+    assert_eq!(closest_approach(&OBSERVER, &position, &flying(&position, 6000, 0.0, 400.0)), None);
+    assert_eq!(closest_approach(&OBSERVER, &position, &flying(&position, 6000, 180.0, 0.0)), None);
+    let info: ADSBAircraftInformation = ADSBAircraftInformation { track: None, ..flying(&position, 6000, 180.0, 400.0) };
+    assert_eq!(closest_approach(&OBSERVER, &position, &info), None);
+  }
+
+  #[test]
+  fn next_pass_is_the_nearest_within_the_horizon() {
+    let pass = |seconds: f64, miles: f64| Some(Pass { seconds, miles, bearing: 0.0, altitude: 5000.0 });
+    let look: LookAngle = LookAngle { azimuth: 0.0, elevation: 10.0, slant_miles: 5.0 };
+    let sightings: Vec<Sighting> = vec![
+      Sighting { index: 0, look, pass: pass(300.0, 3.0) },
+      Sighting { index: 1, look, pass: None },
+      Sighting { index: 2, look, pass: pass(60.0, 1.5) },
+      Sighting { index: 3, look, pass: pass(PASS_HORIZON_SECONDS + 1.0, 0.1) },
+    ];
+    assert_eq!(next_pass(&sightings).map(|sighting| sighting.index), Some(2));
+    assert_eq!(next_pass(&sightings[..2]).map(|sighting| sighting.index), Some(0));
+    assert_eq!(next_pass(&sightings[1..2]), None);
+  }
+}
@@ -0,0 +1,374 @@
+use std::{collections::HashMap, io::Write};
+
+use chrono::{TimeZone, Utc};
+use tokio::time::Instant;
+
+use crate::{alerts::{emergency_conditions, emergency_reason, AlertKind, Alerts, Condition, MISSING_GRACE_MILLIS}, config::Settings, controller::{fence_condition, flight_data, report_fence_events, request}, geofence::{FenceEvent, Geofences}, logbook::LogbookWriter, model::{ADSBData, FRadarArgs, FlightData, Position}, server::Server, spotter::compass_point, watchlist::{watchlist_conditions, Watchlist}};
+
+/// Aircraft enter at the radius but only leave beyond this multiple of it, so one
+/// hovering on the edge doesn't flap in and out.
+const LEAVE_MARGIN: f64 = 1.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickerEventKind {
+  Enter,
+  Leave,
+  Squawk,
+  Emergency,
+  Watchlist,
+  Climb,
+  Descend,
+  Takeoff,
+  Landing,
+  Fence,
+}
+
+impl TickerEventKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      TickerEventKind::Enter => "ENTER",
+      TickerEventKind::Leave => "LEAVE",
+      TickerEventKind::Squawk => "SQUAWK",
+      TickerEventKind::Emergency => "EMERGENCY",
+      TickerEventKind::Watchlist => "WATCHLIST",
+      TickerEventKind::Climb => "CLIMB",
+      TickerEventKind::Descend => "DESCEND",
+      TickerEventKind::Takeoff => "TAKEOFF",
+      TickerEventKind::Landing => "LANDING",
+      TickerEventKind::Fence => "FENCE",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickerEvent {
+  pub kind: TickerEventKind,
+  /// The aircraft, with what happened as its reason.
+  pub condition: Condition,
+}
+
+impl TickerEvent {
+  /// `TIME EVENT HEX CALLSIGN LAT,LONG ALTITUDE what happened`, one event per line.
+  pub fn line(&self, now_millis: i64) -> String {
+    let time: String = Utc.timestamp_millis_opt(now_millis).single().unwrap_or_else(Utc::now).format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let condition: &Condition = &self.condition;
+    let altitude: String = condition.altitude.map(|feet| format!("{:.0}ft", feet)).unwrap_or_else(|| "-".to_string());
+    format!(
+      "{} {:<9} {} {} {:.5},{:.5} {} {}",
+      time, self.kind.name(), condition.hex, condition.callsign, condition.position.lat, condition.position.long, altitude, condition.reason,
+    )
+  }
+}
+
+/// What the tracker remembers about an aircraft between snapshots.
+#[derive(Debug, Clone)]
+struct Track {
+  squawk: Option<String>,
+  on_ground: bool,
+  emergency: bool,
+  watched: bool,
+  seen_millis: i64,
+  last: Condition,
+}
+
+/// The aircraft in range, diffed snapshot by snapshot into events.
+#[derive(Debug, Clone, Default)]
+pub struct Tracker {
+  tracks: HashMap<String, Track>,
+  /// Altitudes (feet, ascending) whose crossing is reported.
+  pub thresholds: Vec<f64>,
+}
+
+impl Tracker {
+  pub fn new(thresholds: Vec<f64>) -> Tracker {
+    Tracker { tracks: HashMap::new(), thresholds }
+  }
+
+  /// Compares a snapshot with the last one. Aircraft enter within `args.radius` of the
+  /// origin and leave beyond [`LEAVE_MARGIN`] times it; ones that vanish from the data
+  /// leave once they have been gone a while.
+  pub fn update(&mut self, flights_data: &FlightData, args: &FRadarArgs, watchlist: &Watchlist) -> Vec<TickerEvent> {
+    let now: i64 = flights_data.epoch_timestamp;
+    let mut events: Vec<TickerEvent> = Vec::new();
+    let mut event = |kind: TickerEventKind, condition: &Condition, reason: String| {
+      events.push(TickerEvent { kind, condition: Condition { reason, ..condition.clone() } });
+    };
+    let whereabouts = |position: &Position| -> String {
+      let miles: f64 = args.origin.distance_miles(position);
+      format!("{} {}", args.units.format_distance(miles), compass_point(args.origin.bearing_to(position)))
+    };
+
+    for (info, (position, _)) in flights_data.aircraft.iter().zip(flights_data.flights.iter()) {
+      let condition: Condition = Condition::new(info, *position, String::new());
+      let distance: f64 = args.origin.distance_miles(position);
+      let emergency: Option<String> = emergency_reason(info);
+      let watched: Option<String> = watchlist.reason(info);
+      let squawk: Option<String> = info.squawk.clone().filter(|squawk| !squawk.trim().is_empty());
+
+      let Some(track) = self.tracks.get_mut(&info.hex) else {
+        if distance <= args.radius {
+          let aircraft_type: String = info.t.as_deref().map(str::trim).filter(|t| !t.is_empty()).map(|t| format!(" ({})", t)).unwrap_or_default();
+          event(TickerEventKind::Enter, &condition, format!("entered range {}{}", whereabouts(position), aircraft_type));
+          if let Some(reason) = &emergency {
+            event(TickerEventKind::Emergency, &condition, reason.clone());
+          }
+          if let Some(reason) = &watched {
+            event(TickerEventKind::Watchlist, &condition, reason.clone());
+          }
+          self.tracks.insert(info.hex.clone(), Track {
+            squawk,
+            on_ground: info.is_on_ground(),
+            emergency: emergency.is_some(),
+            watched: watched.is_some(),
+            seen_millis: now,
+            last: condition,
+          });
+        }
+        continue;
+      };
+
+      if distance > args.radius * LEAVE_MARGIN {
+        event(TickerEventKind::Leave, &condition, format!("left range {}", whereabouts(position)));
+        self.tracks.remove(&info.hex);
+        continue;
+      }
+
+      if let (Some(before), Some(after)) = (&track.squawk, &squawk) && before != after {
+        event(TickerEventKind::Squawk, &condition, format!("squawk {} -> {}", before, after));
+      }
+      if let Some(reason) = &emergency && !track.emergency {
+        event(TickerEventKind::Emergency, &condition, reason.clone());
+      }
+      if let Some(reason) = &watched && !track.watched {
+        event(TickerEventKind::Watchlist, &condition, reason.clone());
+      }
+
+      match (track.on_ground, info.is_on_ground()) {
+        (false, true) => event(TickerEventKind::Landing, &condition, format!("landed {}", whereabouts(position))),
+        (true, false) => event(TickerEventKind::Takeoff, &condition, format!("took off {}", whereabouts(position))),
+        _ => {},
+      }
+      if let (Some(before), Some(after)) = (track.last.altitude, condition.altitude) {
+        for threshold in self.thresholds.iter() {
+          if before < *threshold && after >= *threshold {
+            event(TickerEventKind::Climb, &condition, format!("climbed through {:.0}ft", threshold));
+          }
+          if before >= *threshold && after < *threshold {
+            event(TickerEventKind::Descend, &condition, format!("descended through {:.0}ft", threshold));
+          }
+        }
+      }
+
+      (track.squawk, track.on_ground) = (squawk.or(track.squawk.take()), info.is_on_ground());
+      (track.emergency, track.watched) = (emergency.is_some(), watched.is_some());
+      track.seen_millis = now;
+      track.last = condition;
+    }
+
+    self.tracks.retain(|_, track| {
+      if now - track.seen_millis < MISSING_GRACE_MILLIS {
+        return true;
+      }
+      event(TickerEventKind::Leave, &track.last, "lost".to_string());
+      false
+    });
+
+    events
+  }
+}
+
+/// Polls the data source and prints events to stdout until interrupted. Failed requests
+/// are reported on stderr and retried at the next interval. The logbook, alert log and
+/// geofences work as they do under the scope, and fence events are printed too.
+pub async fn run(settings: &Settings, watchlist: &Watchlist, server: Option<Server>, mut logbook: Option<LogbookWriter>) -> anyhow::Result<()> {
+  let args: FRadarArgs = settings.args;
+  let client = reqwest::Client::new();
+  let mut tracker: Tracker = Tracker::new(settings.altitude_thresholds.clone());
+  let mut alerts: Alerts = Alerts { log_path: settings.alert_log_path.clone(), ..Alerts::default() };
+  let mut geofences: Geofences = Geofences::new(settings.fences.clone(), settings.fence_hook.clone());
+
+  // Ctrl-C stops the loop so the logbook can close its open sightings.
+  let interrupt = tokio::signal::ctrl_c();
+  tokio::pin!(interrupt);
+
+  loop {
+    let start_time = Instant::now();
+
+    let snapshot: anyhow::Result<ADSBData> = tokio::select! {
+      snapshot = async {
+        let response = request(&client, &args).timeout(args.data_interval.max(std::time::Duration::from_secs(5))).send().await?.error_for_status()?;
+        Ok(response.json::<ADSBData>().await?)
+      } => snapshot,
+      _ = &mut interrupt => break,
+    };
+
+    match snapshot {
+      Ok(mut adsb_data) => {
+        // The logbook keeps everything, whatever the filter.
+        if let Some(writer) = logbook.as_mut() && let Err(error) = writer.record(adsb_data.ac.clone(), args.starting_origin, Utc::now().timestamp()) {
+          logbook = None;
+          eprintln!("fradar: logbook stopped: {:#}", error);
+        }
+
+        // Fences and alerts see all the traffic, as they do under the scope.
+        let all_flights_data: FlightData = flight_data(adsb_data.ac.clone())?;
+
+        // The same aircraft the scope would show: emergencies and watched aircraft bypass the filter.
+        if let Some(filter) = &settings.filter {
+          adsb_data.ac.retain(|info| filter.matches(info) || emergency_reason(info).is_some() || watchlist.reason(info).is_some());
+        }
+        let flights_data: FlightData = FlightData { epoch_timestamp: all_flights_data.epoch_timestamp, ..flight_data(adsb_data.ac)? };
+        let mut out = std::io::stdout().lock();
+        let events: Vec<TickerEvent> = tracker.update(&flights_data, &args, watchlist);
+        if let Some(server) = &server {
+          server.update(&flights_data, &args, &events);
+          if let Some(error) = server.take_failure() {
+            eprintln!("fradar: server stopped: {}", error);
+          }
+        }
+        for event in events.iter() {
+          writeln!(out, "{}", event.line(flights_data.epoch_timestamp))?;
+        }
+
+        alerts.sync(AlertKind::Emergency, emergency_conditions(&all_flights_data), &all_flights_data);
+        alerts.sync(AlertKind::Watchlist, watchlist_conditions(watchlist, &all_flights_data), &all_flights_data);
+
+        let fence_events: Vec<FenceEvent> = geofences.update(&all_flights_data);
+        alerts.write_log();
+        report_fence_events(&fence_events, &geofences, alerts.log_path.as_deref(), server.as_ref(), flights_data.epoch_timestamp);
+        for fence_event in fence_events.iter() {
+          let event: TickerEvent = TickerEvent { kind: TickerEventKind::Fence, condition: fence_condition(fence_event) };
+          writeln!(out, "{}", event.line(flights_data.epoch_timestamp))?;
+        }
+      },
+      Err(error) => eprintln!("fradar: {:#}", error),
+    }
+
+    tokio::select! {
+      _ = tokio::time::sleep(args.data_interval.saturating_sub(start_time.elapsed())) => {},
+      _ = &mut interrupt => break,
+    }
+  }
+
+  if let Some(writer) = logbook {
+    tokio::task::spawn_blocking(move || writer.finish()).await??;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fixtures::{aircraft, flights_data}, model::{ADSBAircraftInformation, Units, MILES_PER_NM}};
+
+  const ORIGIN: Position = Position { lat: 47.0, long: -122.0 };
+
+  fn args() -> FRadarArgs {
+    FRadarArgs { origin: ORIGIN, radius: 10.0, units: Units::Nautical, ..Default::default() }
+  }
+
+  /// N123, `miles` east of the origin.
+  fn n123(miles: f64, altitude: i32) -> ADSBAircraftInformation {
+    ADSBAircraftInformation { flight: Some("N123".to_string()), ..aircraft("abc123", &ORIGIN.destination(90.0, miles), altitude) }
+  }
+
+  fn update(tracker: &mut Tracker, now: i64, info: ADSBAircraftInformation, watchlist: &Watchlist) -> Vec<(TickerEventKind, String)> {
+    tracker.update(&flights_data(now, vec![info]), &args(), watchlist).into_iter().map(|event| (event.kind, event.condition.reason)).collect()
+  }
+
+  #[test]
+  fn leaves_only_beyond_the_margin() {
+    let mut tracker: Tracker = Tracker::default();
+    let mut update = |now: i64, miles: f64| -> Vec<TickerEventKind> {
+      update(&mut tracker, now, n123(miles, 3000), &Watchlist::default()).into_iter().map(|(kind, _)| kind).collect()
+    };
+
+    assert_eq!(update(0, 10.5), []);
+    assert_eq!(update(1000, 9.9), [TickerEventKind::Enter]);
+    assert_eq!(update(2000, 10.2), []);
+    assert_eq!(update(3000, 9.8), []);
+    assert_eq!(update(4000, 10.4), []);
+    assert_eq!(update(5000, 10.6), [TickerEventKind::Leave]);
+    assert_eq!(update(6000, 10.2), []);
+    assert_eq!(update(7000, 9.9), [TickerEventKind::Enter]);
+  }
+
+  #[test]
+  fn reports_entering_leaving_and_losing_aircraft() {
+    let mut tracker: Tracker = Tracker::default();
+    let info: ADSBAircraftInformation = ADSBAircraftInformation { t: Some("C172".to_string()), ..n123(5.0 * MILES_PER_NM, 3000) };
+    assert_eq!(update(&mut tracker, 0, info, &Watchlist::default()), [(TickerEventKind::Enter, "entered range 5.0 nm E (C172)".to_string())]);
+    assert_eq!(update(&mut tracker, 1000, n123(12.0, 3000), &Watchlist::default())[0].0, TickerEventKind::Leave);
+
+    update(&mut tracker, 2000, n123(5.0, 3000), &Watchlist::default());
+    assert!(tracker.update(&flights_data(2000 + MISSING_GRACE_MILLIS - 1, Vec::new()), &args(), &Watchlist::default()).is_empty());
+    let lost: Vec<TickerEvent> = tracker.update(&flights_data(2000 + MISSING_GRACE_MILLIS, Vec::new()), &args(), &Watchlist::default());
+    assert_eq!((lost[0].kind, lost[0].condition.reason.as_str()), (TickerEventKind::Leave, "lost"));
+  }
+
+  #[test]
+  fn reports_squawk_changes() {
+    let mut tracker: Tracker = Tracker::default();
+    let squawking = |squawk: Option<&str>| ADSBAircraftInformation { squawk: squawk.map(str::to_string), ..n123(5.0, 3000) };
+    update(&mut tracker, 0, squawking(Some("1200")), &Watchlist::default());
+    assert_eq!(update(&mut tracker, 1000, squawking(None), &Watchlist::default()), []);
+    assert_eq!(update(&mut tracker, 2000, squawking(Some("4512")), &Watchlist::default()), [(TickerEventKind::Squawk, "squawk 1200 -> 4512".to_string())]);
+  }
+
+  #[test]
+  fn reports_emergencies_once() {
+    let mut tracker: Tracker = Tracker::default();
+    let squawking = |squawk: &str| ADSBAircraftInformation { squawk: Some(squawk.to_string()), ..n123(5.0, 3000) };
+    update(&mut tracker, 0, squawking("1200"), &Watchlist::default());
+
+    let events: Vec<(TickerEventKind, String)> = update(&mut tracker, 1000, squawking("7700"), &Watchlist::default());
+    assert_eq!(events[1], (TickerEventKind::Emergency, "squawk 7700 (emergency)".to_string()));
+    assert_eq!(update(&mut tracker, 2000, squawking("7700"), &Watchlist::default()), []);
+
+    let mut tracker: Tracker = Tracker::default();
+    let kinds: Vec<TickerEventKind> = update(&mut tracker, 0, squawking("7600"), &Watchlist::default()).into_iter().map(|(kind, _)| kind).collect();
+    assert_eq!(kinds, [TickerEventKind::Enter, TickerEventKind::Emergency]);
+  }
+
+  #[test]
+  fn reports_watched_aircraft_once() {
+    let watchlist: Watchlist = Watchlist::parse("call N123 the usual").unwrap();
+    let mut tracker: Tracker = Tracker::default();
+    let events: Vec<(TickerEventKind, String)> = update(&mut tracker, 0, n123(5.0, 3000), &watchlist);
+    assert_eq!(events[1], (TickerEventKind::Watchlist, "watchlist: call N123 (the usual)".to_string()));
+    assert_eq!(update(&mut tracker, 1000, n123(5.0, 3000), &watchlist), []);
+
+    let mut tracker: Tracker = Tracker::default();
+    update(&mut tracker, 0, n123(5.0, 3000), &Watchlist::default());
+    assert_eq!(update(&mut tracker, 1000, n123(5.0, 3000), &watchlist)[0].0, TickerEventKind::Watchlist);
+  }
+
+  #[test]
+  fn reports_altitude_thresholds_both_ways() {
+    let mut tracker: Tracker = Tracker::new(Vec::from([5000.0, 10000.0]));
+    let watchlist: Watchlist = Watchlist::default();
+    update(&mut tracker, 0, n123(5.0, 4000), &watchlist);
+    assert_eq!(update(&mut tracker, 1000, n123(5.0, 4900), &watchlist), []);
+    assert_eq!(update(&mut tracker, 2000, n123(5.0, 10000), &watchlist), [
+      (TickerEventKind::Climb, "climbed through 5000ft".to_string()),
+      (TickerEventKind::Climb, "climbed through 10000ft".to_string()),
+    ]);
+    assert_eq!(update(&mut tracker, 3000, n123(5.0, 9999), &watchlist), [(TickerEventKind::Descend, "descended through 10000ft".to_string())]);
+  }
+
+  #[test]
+  fn reports_takeoffs_and_landings() {
+    let mut tracker: Tracker = Tracker::default();
+    let watchlist: Watchlist = Watchlist::default();
+    let on_ground: ADSBAircraftInformation = ADSBAircraftInformation { alt_baro: Some("ground".to_string()), ..n123(2.0 * MILES_PER_NM, 0) };
+    update(&mut tracker, 0, on_ground.clone(), &watchlist);
+    assert_eq!(update(&mut tracker, 1000, n123(2.0 * MILES_PER_NM, 300), &watchlist), [(TickerEventKind::Takeoff, "took off 2.0 nm E".to_string())]);
+    assert_eq!(update(&mut tracker, 2000, on_ground, &watchlist), [(TickerEventKind::Landing, "landed 2.0 nm E".to_string())]);
+  }
+
+  #[test]
+  fn prints_one_line_per_event() {
+    let event: TickerEvent = TickerEvent { kind: TickerEventKind::Climb, condition: Condition { reason: "climbed through 5000ft".to_string(), ..Condition::new(&n123(0.0, 5100), ORIGIN, String::new()) } };
+    assert_eq!(event.line(1_767_225_600_000), "2026-01-01T00:00:00Z CLIMB     abc123 N123 47.00000,-122.00000 5100ft climbed through 5000ft");
+  }
+}
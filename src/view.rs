@@ -1,11 +1,15 @@
-use std::{collections::HashMap, io::Write, sync::{Arc, Mutex}};
+use std::{collections::{HashMap, VecDeque}, fmt::Display, io::Write, sync::{Arc, Mutex}};
 
-use crossterm::{cursor, execute, queue, style::{self}, terminal::{Clear, ClearType}};
+use chrono::Utc;
+use crossterm::{cursor, execute, queue, style::{self, Attribute}, terminal::{Clear, ClearType}};
 use tokio::{time::Instant};
 
-use crate::model::{Coord, FRadarArgs, FRadarData, FRadarState, FlightData, Label, LabelPosition, Position};
+use crate::{aircraft_list::{altitude_feet, format_header, format_row, list_area, sorted_rows, visible_rows, ListRow}, airports::Airport, airspace::Airspace, alerts::{callsign, Alert, AlertKind, Alerts}, canvas::BrailleCanvas, conflicts::Conflict, model::{ADSBAircraftInformation, Coord, FRadarArgs, FRadarData, FRadarState, FRadarUi, FlightData, Label, LabelPosition, ListMode, Palette, Position, Units, MILES_PER_NM}, scope::{draw_graticule, draw_position_readout, draw_range_rings, draw_scale_bar, draw_scope_labels}, spotter::{compass_point, format_seconds, look_at, next_pass, sightings, Pass, Sighting}};
 
 
+/// Runways are only legible once zoomed in this far (miles).
+const RUNWAY_MAX_RADIUS: f64 = 20.0;
+
 pub async fn view_thread(fradar_data: Arc<Mutex<FRadarData>>) -> tokio::task::JoinHandle<anyhow::Result<()>> {
   tokio::spawn(async move {
     crossterm::terminal::enable_raw_mode()?;
@@ -34,22 +38,128 @@ pub async fn draw(fradar_data: Arc<Mutex<FRadarData>>) -> anyhow::Result<()> {
   )?;
   
   let args: FRadarArgs;
+  let state: FRadarState;
+  let ui: FRadarUi;
+  let flights_data: Arc<Mutex<FlightData>>;
+  let palette: Palette;
+  let airspaces: Arc<Vec<Airspace>>;
+  let alerts: Alerts;
+  let conflicts: Vec<Conflict>;
+  let mut graticule_labels: Vec<(Coord<f64>, String)> = Vec::new();
+
+  // Move the camera (bookmark transitions, follow mode) before anything is projected.
+  update_camera(fradar_data.clone());
+  ring_bell(fradar_data.clone())?;
 
   {
     let fradar_data_locked: FRadarData = fradar_data.lock().unwrap().clone();
-    let flights_data: Arc<Mutex<FlightData>> = fradar_data_locked.flights_data;
+    flights_data = fradar_data_locked.flights_data;
     args = fradar_data_locked.args;
-
-
-    // Draw planes as dots on a radar.
-    draw_radar_layer(flights_data.clone(), args)?;
+    state = fradar_data_locked.state;
+    ui = fradar_data_locked.ui;
+    palette = args.theme.palette();
+    airspaces = fradar_data_locked.airspaces.clone();
+    alerts = fradar_data_locked.alerts.clone();
+    conflicts = fradar_data_locked.conflicts.clone();
+
+
+    // Draw aircraft on the radar, with their recent trails underneath.
+    if ui.list_mode != ListMode::Full {
+      // Map lines, airspace, runways and range rings share one canvas so they merge where they cross.
+      let mut canvas: BrailleCanvas = BrailleCanvas::new(&args);
+      if ui.layers.graticule {
+        graticule_labels = draw_graticule(&mut canvas, &args, palette.map);
+      }
+      let ring_labels: Vec<(Coord<f64>, String)> = match ui.layers.rings {
+        true => draw_range_rings(&mut canvas, &args, palette.rings),
+        false => Vec::new(),
+      };
+      for overlay in fradar_data_locked.overlays.iter().filter(|overlay| !ui.layers.hidden_overlays.contains(&overlay.name)) {
+        overlay.draw(&mut canvas, &args, palette.map);
+      }
+      let fence_labels: Vec<(Coord<f64>, String)> = match ui.layers.fences {
+        true => fradar_data_locked.geofences.fences.iter().filter_map(|fence| fence.draw(&mut canvas, &args, palette.fence)).collect(),
+        false => Vec::new(),
+      };
+      if ui.layers.airspace {
+        draw_airspace(&mut canvas, &airspaces, &flights_data.lock().unwrap(), &args, &ui);
+      }
+      let visible_airports: Vec<&Airport> = fradar_data_locked.airports.visible(&args);
+      if ui.layers.runways && args.radius <= RUNWAY_MAX_RADIUS {
+        for runway in visible_airports.iter().flat_map(|airport| airport.runways.iter()) {
+          canvas.line(runway.le.as_terminal_coord_unclamped(&args), runway.he.as_terminal_coord_unclamped(&args), palette.ground);
+        }
+      }
+      canvas.flush()?;
+      draw_scope_labels(&ring_labels, palette.rings)?;
+      draw_scope_labels(&fence_labels, palette.fence)?;
+
+      let ground_labels: Vec<(Position, Label)> = draw_airports(&visible_airports, &args, &ui)?;
+      draw_trails(&fradar_data_locked.flights_data_history, &args)?;
+      draw_conflict_lines(&conflicts, &flights_data.lock().unwrap(), &args, palette.alert)?;
+      draw_radar_layer(flights_data.clone(), args, ground_labels, &alerts)?;
+    }
   }
 
   // Draw side borders.
-  draw_box_with_label(0, 0, args.terminal_cols, args.terminal_rows, " fradar ".to_string())?;
+  let title: String = match (ui.follow, ui.selected_hex.as_deref()) {
+    (true, Some(hex)) => format!(" fradar · following {} ", hex),
+    _ => " fradar ".to_string(),
+  };
+  queue!(std::io::stdout(), style::SetForegroundColor(palette.border))?;
+  // Graticule labels sit in the frame, clear of the title.
+  let title_end: f64 = 3.0 + title.chars().count() as f64;
+  graticule_labels.retain(|(coord, _)| coord.row > 0.0 || coord.col > title_end);
+  draw_box_with_label(0, 0, args.terminal_cols, args.terminal_rows, title)?;
+  queue!(std::io::stdout(), style::ResetColor)?;
+  draw_scope_labels(&graticule_labels, palette.border)?;
+  draw_position_readout(&args, &ui, palette.border)?;
 
   // Draw center crosshair
-  draw_crosshair(&args)?;
+  if ui.list_mode != ListMode::Full {
+    draw_crosshair(&args)?;
+  }
+
+  // Draw scale bar
+  if ui.layers.scale {
+    draw_scale_bar(&args, &ui, palette.rings)?;
+  }
+
+  // Draw aircraft list panel
+  draw_list_panel(flights_data.clone(), &args, &ui)?;
+
+  // Panels in the top corners start below the alert banner while it is up.
+  let now_millis: i64 = flights_data.lock().unwrap().epoch_timestamp;
+  let top: u16 = if alerts.banner(now_millis).is_empty() { 1 } else { 2 };
+
+  // Draw conflicts panel
+  if ui.list_mode != ListMode::Full {
+    draw_conflict_panel(&conflicts, &flights_data.lock().unwrap(), &args, top, palette.alert)?;
+  }
+
+  // Draw selection highlight and the detail pane for the selected aircraft.
+  let right_top: u16 = draw_selection(flights_data.clone(), &args, &ui, top)?;
+
+  // Draw spotter panel, underneath the detail pane if there is one.
+  if ui.spotter && ui.list_mode != ListMode::Full {
+    draw_spotter_panel(&flights_data.lock().unwrap(), &args, right_top)?;
+  }
+
+  // Draw hover tooltip
+  draw_tooltip(flights_data.clone(), &airspaces, &args, &ui)?;
+
+  // Alerts go over everything but the prompt.
+  draw_alert_banner(&alerts, now_millis, &args, &palette)?;
+
+  // Draw search or command prompt, otherwise any recent status message
+  match state {
+    FRadarState::Search => draw_prompt(&args, format!("/{}_", ui.input_buffer))?,
+    FRadarState::Command => draw_prompt(&args, format!(":{}_", ui.input_buffer))?,
+    _ => match &ui.status_message {
+      Some((message, timestamp)) if Utc::now().timestamp_millis() - timestamp < 5000 => draw_prompt(&args, message.clone())?,
+      _ => {},
+    },
+  }
   
   std::io::stdout().flush()?;
 
@@ -61,6 +171,285 @@ pub async fn draw(fradar_data: Arc<Mutex<FRadarData>>) -> anyhow::Result<()> {
   Ok(())
 }
 
+fn update_camera(fradar_data: Arc<Mutex<FRadarData>>) {
+  let fradar_data_ref: &mut FRadarData = &mut fradar_data.lock().unwrap();
+
+  if let Some(transition) = fradar_data_ref.ui.transition {
+    let (origin, radius, done) = transition.at(Utc::now().timestamp_millis());
+    fradar_data_ref.args.origin = origin;
+    fradar_data_ref.args.radius = radius;
+    if done {
+      fradar_data_ref.ui.transition = None;
+    }
+  }
+
+  if !fradar_data_ref.ui.follow {
+    return;
+  }
+
+  let flights_data: Arc<Mutex<FlightData>> = fradar_data_ref.flights_data.clone();
+  let flights_data: &FlightData = &flights_data.lock().unwrap();
+  let selected: Option<usize> = fradar_data_ref.ui.selected_hex.as_deref().and_then(|hex| flights_data.index_of_hex(hex));
+
+  // Hold the last known position if the aircraft drops out of a single update.
+  if let Some(index) = selected {
+    fradar_data_ref.args.origin = flights_data.flights[index].0;
+  }
+}
+
+/// Joins each conflicting pair with a line.
+fn draw_conflict_lines(conflicts: &[Conflict], flights_data: &FlightData, args: &FRadarArgs, color: style::Color) -> anyhow::Result<()> {
+  if conflicts.is_empty() {
+    return Ok(());
+  }
+
+  let mut canvas: BrailleCanvas = BrailleCanvas::new(args);
+  for conflict in conflicts.iter() {
+    let (Some((a, _)), Some((b, _))) = (flights_data.flights.get(conflict.a), flights_data.flights.get(conflict.b)) else {
+      continue;
+    };
+    canvas.line(a.as_terminal_coord_unclamped(args), b.as_terminal_coord_unclamped(args), color);
+  }
+  canvas.flush()
+}
+
+/// Lists conflicts in the top left corner, from row `top` down, soonest first.
+fn draw_conflict_panel(conflicts: &[Conflict], flights_data: &FlightData, args: &FRadarArgs, top: u16, color: style::Color) -> anyhow::Result<()> {
+  let w: u16 = 46;
+  let h: u16 = (conflicts.len().min(8) as u16 + 2).min(args.terminal_rows.saturating_sub(top + 3));
+  if conflicts.is_empty() || args.terminal_cols < w + 4 || h < 3 {
+    return Ok(());
+  }
+  let (x, y): (u16, u16) = (1, top);
+
+  for row in y..(y + h) {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x, row),
+      style::Print(" ".repeat(w.into())),
+    )?;
+  }
+
+  queue!(std::io::stdout(), style::SetForegroundColor(color))?;
+  draw_box_with_label(x, y, w, h, format!(" conflicts ({}) ", conflicts.len()))?;
+  for (ind, conflict) in conflicts.iter().take((h - 2).into()).enumerate() {
+    let (Some(a), Some(b)) = (flights_data.aircraft.get(conflict.a), flights_data.aircraft.get(conflict.b)) else {
+      continue;
+    };
+    let when: String = match conflict.seconds.round() as u64 {
+      0 => "now".to_string(),
+      _ => format_seconds(conflict.seconds),
+    };
+    let line: String = format!(
+      "{:<8} {:<8} {:>8} {:>8} {:>6}",
+      callsign(a), callsign(b), args.units.format_distance(conflict.lateral_miles), args.units.format_altitude(conflict.vertical_feet), when,
+    );
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x + 2, y + 1 + ind as u16),
+      style::Print(line.chars().take((w - 4).into()).collect::<String>()),
+    )?;
+  }
+  queue!(std::io::stdout(), style::ResetColor)?;
+
+  Ok(())
+}
+
+/// Lists aircraft above the horizon as seen from the origin, highest first, with the
+/// nearest upcoming pass underneath. The panel hangs from row `top` on the right.
+fn draw_spotter_panel(flights_data: &FlightData, args: &FRadarArgs, top: u16) -> anyhow::Result<()> {
+  let sightings: Vec<Sighting> = sightings(flights_data, args);
+  let visible: Vec<&Sighting> = sightings.iter().filter(|sighting| sighting.look.elevation > 0.0).take(10).collect();
+  let pass: Option<String> = next_pass(&sightings).and_then(|sighting| {
+    let pass: Pass = sighting.pass?;
+    Some(format!(
+      "{} passes {} {} at {} in {}",
+      callsign(&flights_data.aircraft[sighting.index]), args.units.format_distance(pass.miles), compass_point(pass.bearing),
+      args.units.format_altitude(pass.altitude), format_seconds(pass.seconds),
+    ))
+  });
+
+  let w: u16 = 44;
+  let h: u16 = (visible.len().max(1) as u16 + 2 + pass.is_some() as u16).min(args.terminal_rows.saturating_sub(top + 3));
+  if args.terminal_cols < w + 4 || h < 3 {
+    return Ok(());
+  }
+  let (x, y): (u16, u16) = (args.terminal_cols - w - 1, top);
+
+  for row in y..(y + h) {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x, row),
+      style::Print(" ".repeat(w.into())),
+    )?;
+  }
+  draw_box_with_label(x, y, w, h, " look here ".to_string())?;
+
+  let mut lines: Vec<(String, style::Color)> = visible.iter()
+    .map(|sighting| {
+      let when: String = sighting.pass.map(|pass| format_seconds(pass.seconds)).unwrap_or_else(|| "-".to_string());
+      let line: String = format!(
+        "{:<8} {:>3.0}° {:<2} {:>3.0}° {:>9} {:>6}",
+        callsign(&flights_data.aircraft[sighting.index]), sighting.look.azimuth, compass_point(sighting.look.azimuth),
+        sighting.look.elevation, args.units.format_distance(sighting.look.slant_miles), when,
+      );
+      (line, style::Color::Reset)
+    })
+    .collect();
+  if lines.is_empty() {
+    lines.push(("nothing above the horizon".to_string(), style::Color::Reset));
+  }
+  if let Some(pass) = pass {
+    lines.push((pass, args.theme.palette().highlight));
+  }
+
+  for (ind, (line, color)) in lines.iter().take((h - 2).into()).enumerate() {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x + 2, y + 1 + ind as u16),
+      style::SetForegroundColor(*color),
+      style::Print(line.chars().take((w - 4).into()).collect::<String>()),
+      style::ResetColor,
+    )?;
+  }
+
+  Ok(())
+}
+
+/// Rings the terminal bell once for each batch of newly raised alerts.
+fn ring_bell(fradar_data: Arc<Mutex<FRadarData>>) -> anyhow::Result<()> {
+  let alerts: &mut Alerts = &mut fradar_data.lock().unwrap().alerts;
+  if alerts.bell_pending {
+    alerts.bell_pending = false;
+    queue!(std::io::stdout(), style::Print('\x07'))?;
+  }
+
+  Ok(())
+}
+
+/// A reverse-video line across the top of the scope naming every alert still in the banner.
+fn draw_alert_banner(alerts: &Alerts, now_millis: i64, args: &FRadarArgs, palette: &Palette) -> anyhow::Result<()> {
+  let active: Vec<&Alert> = alerts.banner(now_millis);
+  let Some(first) = active.first() else {
+    return Ok(());
+  };
+  if args.terminal_cols < 10 || args.terminal_rows < 4 {
+    return Ok(());
+  }
+
+  let color: style::Color = alert_color(first.kind, palette);
+  let width: usize = args.terminal_cols as usize - 2;
+  let text: String = active.iter()
+    .map(|alert| format!("{} {} {}", alert.kind.symbol(), alert.callsign, alert.reason))
+    .collect::<Vec<String>>()
+    .join("   ");
+  let text: String = text.chars().take(width).collect();
+  queue!(
+    std::io::stdout(),
+    cursor::MoveTo(1, 1),
+    style::SetForegroundColor(color),
+    style::SetAttribute(Attribute::Reverse),
+    style::Print(format!("{:^width$}", text, width = width)),
+    style::SetAttribute(Attribute::Reset),
+    style::ResetColor,
+  )?;
+
+  Ok(())
+}
+
+fn draw_prompt(args: &FRadarArgs, text: String) -> anyhow::Result<()> {
+  let width: usize = args.terminal_cols.saturating_sub(4).into();
+  let text: String = text.chars().rev().take(width.saturating_sub(2)).collect::<Vec<char>>().into_iter().rev().collect();
+  queue!(
+    std::io::stdout(),
+    cursor::MoveTo(2, args.terminal_rows - 1),
+    style::Print(format!(" {} ", text)),
+  )?;
+
+  Ok(())
+}
+
+/// Draws airspace boundaries, only those at the selected aircraft's altitude if asked to.
+fn draw_airspace(canvas: &mut BrailleCanvas, airspaces: &[Airspace], flights_data: &FlightData, args: &FRadarArgs, ui: &FRadarUi) {
+  let palette: Palette = args.theme.palette();
+  let altitude: Option<f64> = match ui.layers.airspace_at_altitude {
+    true => selected_altitude(flights_data, ui),
+    false => None,
+  };
+
+  for airspace in airspaces.iter().filter(|airspace| altitude.is_none_or(|feet| airspace.contains_altitude(feet))) {
+    if let Some((color, stroke)) = airspace.class.style(&palette) {
+      airspace.draw(canvas, args, color, stroke);
+    }
+  }
+}
+
+fn selected_altitude(flights_data: &FlightData, ui: &FRadarUi) -> Option<f64> {
+  let index: usize = flights_data.index_of_hex(ui.selected_hex.as_deref()?)?;
+  altitude_feet(&flights_data.aircraft[index])
+}
+
+/// Draws airport symbols, returning the airport labels for the label engine.
+fn draw_airports(visible: &[&Airport], args: &FRadarArgs, ui: &FRadarUi) -> anyhow::Result<Vec<(Position, Label)>> {
+  let palette: Palette = args.theme.palette();
+
+  if !ui.layers.airports {
+    return Ok(Vec::new());
+  }
+
+  for airport in visible.iter() {
+    let coord: Coord<u16> = airport.position.as_terminal_coord(args)?;
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(coord.col, coord.row),
+      style::SetForegroundColor(palette.ground),
+      style::Print(airport.kind.symbol()),
+      style::ResetColor,
+    )?;
+  }
+
+  Ok(visible.iter()
+    .filter(|airport| args.radius <= airport.kind.max_label_radius())
+    .map(|airport| (airport.position, Label { flight: airport.code().to_string(), ..Default::default() }))
+    .collect())
+}
+
+fn draw_trails(flights_data_history: &VecDeque<Arc<Mutex<FlightData>>>, args: &FRadarArgs) -> anyhow::Result<()> {
+  if args.trail_duration.is_zero() {
+    return Ok(());
+  }
+
+  let cutoff: i64 = Utc::now().timestamp_millis() - args.trail_duration.as_millis() as i64;
+  let sectorizer: &mut HashMap<Coord<u16>, Vec<Coord<f64>>> = &mut HashMap::new();
+
+  // The newest snapshot is the one drawn as live dots, so skip it.
+  for flights_data in flights_data_history.iter().rev().skip(1) {
+    let flights_data: &FlightData = &flights_data.lock().unwrap();
+    if flights_data.epoch_timestamp < cutoff {
+      break;
+    }
+
+    for (position, _) in flights_data.flights.iter() {
+      let coord_float: Coord<f64> = position.as_terminal_coord_float(args);
+      sectorizer.entry(coord_float.try_into()?)
+        .or_default()
+        .push(coord_float);
+    }
+  }
+
+  queue!(std::io::stdout(), style::SetForegroundColor(args.theme.palette().trail), style::SetAttribute(Attribute::Dim))?;
+  for (sector, dots_coord_float) in sectorizer.iter() {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(sector.col, sector.row),
+      style::Print(generate_subchar_braille(&subchar_coords(dots_coord_float))),
+    )?;
+  }
+  queue!(std::io::stdout(), style::SetAttribute(Attribute::Reset), style::ResetColor)?;
+
+  Ok(())
+}
+
 fn draw_crosshair(args: &FRadarArgs) -> anyhow::Result<()> {
   if args.origin.roughly_eq(&args.starting_origin) {
     queue!(
@@ -122,7 +511,7 @@ fn draw_box(x: u16, y: u16, w: u16, h: u16) -> anyhow::Result<()> {
       std::io::stdout(),
       cursor::MoveTo(x, i),
       style::Print("│"),
-      cursor::MoveTo(x + w, i),
+      cursor::MoveTo(x + w - 1, i),
       style::Print("│"),
     )?;
   }
@@ -140,46 +529,70 @@ fn draw_box(x: u16, y: u16, w: u16, h: u16) -> anyhow::Result<()> {
   Ok(())
 }
 
-fn draw_radar_layer(flights_data: Arc<Mutex<FlightData>>, args: FRadarArgs) -> anyhow::Result<()> {
-  let flights_data: Vec<(Position, Label)> = flights_data.lock().unwrap().flights.clone();
+fn draw_radar_layer(flights_data: Arc<Mutex<FlightData>>, args: FRadarArgs, ground_labels: Vec<(Position, Label)>, alerts: &Alerts) -> anyhow::Result<()> {
+  let flights_data: FlightData = flights_data.lock().unwrap().clone();
+  let palette: Palette = args.theme.palette();
+  let alerting: Vec<(usize, AlertKind)> = flights_data.aircraft.iter().enumerate()
+    .filter_map(|(index, info)| alerts.kind_of(&info.hex).map(|kind| (index, kind)))
+    .collect();
+  let pinned: Vec<(usize, style::Color)> = alerting.iter()
+    .map(|(index, kind)| (*index, alert_color(*kind, &palette)))
+    .collect();
 
   // Preemptive step: spin up label engine
-  let label_engine_handle = label_engine(flights_data.clone(), args);
+  let label_engine_handle = label_engine(flights_data.flights.clone(), ground_labels, pinned, args);
+
+  // Velocity leaders go underneath the aircraft symbols.
+  if !args.leader_duration.is_zero() {
+    let hours: f64 = args.leader_duration.as_secs_f64() / 3600.0;
+    let mut canvas: BrailleCanvas = BrailleCanvas::new(&args);
+    for ((position, _), info) in flights_data.flights.iter().zip(flights_data.aircraft.iter()) {
+      if let (Some(track), Some(gs)) = (info.track, info.gs) && !info.is_on_ground() {
+        let ahead: Position = position.destination(track as f64, gs as f64 * MILES_PER_NM * hours);
+        canvas.line(position.as_terminal_coord_unclamped(&args), ahead.as_terminal_coord_unclamped(&args), palette.trail);
+      }
+    }
+    canvas.flush()?;
+  }
 
   // First step: generate sectorizer hashmap to correctly get braille for sub-character drawing
-  let sectorizer: &mut HashMap<Coord<u16>, Vec<Coord<f64>>> = &mut HashMap::new();
+  let sectorizer: &mut HashMap<Coord<u16>, Vec<(Coord<f64>, usize)>> = &mut HashMap::new();
 
-  let dots_coord: Vec<Coord<u16>> = flights_data.iter()
-    .map(|(position, _)| position.as_terminal_coord(&args).unwrap()) // TODO: handle the unwrap from converting float to coord
-    .collect();
-  let dots_coord_float: Vec<Coord<f64>> = flights_data.iter()
-    .map(|(position, _)| position.as_terminal_coord_float(&args))
-    .collect();
-
-  let zipped_dots: Vec<(Coord<u16>, Coord<f64>)> = dots_coord.iter()
-    .zip(dots_coord_float.iter())
-    .map(|(coord, coord_float)| (*coord, *coord_float))
-    .collect();
-
-  for (coord, coord_float) in zipped_dots {
+  for (index, (position, _)) in flights_data.flights.iter().enumerate() {
+    let coord: Coord<u16> = position.as_terminal_coord(&args)?;
     sectorizer.entry(coord)
-      .or_insert_with(Vec::new)
-      .push(coord_float);
+      .or_default()
+      .push((position.as_terminal_coord_float(&args), index));
   }
 
-  // Second step: using sectorizer hashmap, determine what braille character to display, and queue draw to stdout
-  for (sector, dots_coord_float) in sectorizer.iter() {
-    let braille_coords: Vec<Coord<usize>> = dots_coord_float.iter()
-      .map(|coord_float| Coord::<usize> {
-        col: ((coord_float.col - coord_float.col.trunc()) * 2.0).trunc() as usize, // extract only decimal place, then
-        row: ((coord_float.row - coord_float.row.trunc()) * 4.0).trunc() as usize, // multiple by col or row respectively
-      })
-      .collect();
+  // Second step: a lone aircraft in a cell gets its symbol, several share a braille character
+  let flash_on: bool = (Utc::now().timestamp_millis() / 500) % 2 == 0;
+  for (sector, dots) in sectorizer.iter() {
+    let glyph: Option<char> = match dots[..] {
+      [(_, index)] => flights_data.aircraft.get(index).and_then(ADSBAircraftInformation::glyph),
+      _ => None,
+    };
+    let dots_coord_float: Vec<Coord<f64>> = dots.iter().map(|(coord_float, _)| *coord_float).collect();
+
+    // Emergencies flash between normal and reverse video every half second; watched aircraft are highlighted.
+    let kind: Option<AlertKind> = alerting.iter()
+      .filter(|(index, _)| dots.iter().any(|(_, dot)| dot == index))
+      .map(|(_, kind)| *kind)
+      .min();
+    let (color, attribute) = match kind {
+      Some(AlertKind::Emergency) if flash_on => (palette.alert, Attribute::Reverse),
+      Some(kind) => (alert_color(kind, &palette), Attribute::NoReverse),
+      None => (palette.aircraft, Attribute::NoReverse),
+    };
 
     queue!(
       std::io::stdout(),
       cursor::MoveTo(sector.col, sector.row),
-      style::Print(generate_subchar_braille(&braille_coords)),
+      style::SetForegroundColor(color),
+      style::SetAttribute(attribute),
+      style::Print(glyph.unwrap_or_else(|| generate_subchar_braille(&subchar_coords(&dots_coord_float)))),
+      style::SetAttribute(Attribute::Reset),
+      style::ResetColor,
     )?;
   }
 
@@ -189,71 +602,372 @@ fn draw_radar_layer(flights_data: Arc<Mutex<FlightData>>, args: FRadarArgs) -> a
   Ok(())
 }
 
-fn label_engine(flights_data: Vec<(Position, Label)>, args: FRadarArgs) -> std::thread::JoinHandle<anyhow::Result<()>> {
-  std::thread::spawn(move || -> anyhow::Result<()> {
-    for (position, label) in flights_data.iter() {
-      // Moving ("pushed") coordinate for this label
-      let pushed_coord = &mut position.as_terminal_coord_float(&args);
-
-      // Initialize to the "top right"
-      pushed_coord.col += 1.0;
-      pushed_coord.row -= 1.0;
-      
-      // Simulate inverse gravitational forces between labels and points
-      for (other_position, _) in flights_data.iter() {
-        let other_coord_float: Coord<f64> = other_position.as_terminal_coord_float(&args);
-        let hypot_squared: f64 = pushed_coord.squared_dist(other_coord_float);
-        if hypot_squared > 0.1 {
-          pushed_coord.col -= args.label_point_repelling_force *
-            (other_coord_float.col - pushed_coord.col) /
-            hypot_squared;
-          pushed_coord.row -= args.label_point_repelling_force *
-            (other_coord_float.row - pushed_coord.row) /
-            hypot_squared;
-        }
-      }
+fn alert_color(kind: AlertKind, palette: &Palette) -> style::Color {
+  match kind {
+    AlertKind::Emergency => palette.alert,
+    AlertKind::Watchlist => palette.highlight,
+  }
+}
 
-      let original_coord: Coord<f64> = position.as_terminal_coord_float(&args);
-      let label_position = match (pushed_coord.col - original_coord.col, pushed_coord.row - original_coord.row) {
-        (dc, dr) if (dc > 0.0 && dr > 0.0) => LabelPosition::BottomRight,
-        (dc, dr) if (dc < 0.0 && dr > 0.0) => LabelPosition::BottomLeft,
-        (dc, dr) if (dc > 0.0 && dr < 0.0) => LabelPosition::TopRight,
-        (dc, dr) if (dc < 0.0 && dr < 0.0) => LabelPosition::TopLeft,
-        (_, _) => LabelPosition::default(),
+fn label_engine(flights_data: Vec<(Position, Label)>, ground_labels: Vec<(Position, Label)>, pinned: Vec<(usize, style::Color)>, args: FRadarArgs) -> std::thread::JoinHandle<anyhow::Result<()>> {
+  std::thread::spawn(move || -> anyhow::Result<()> {
+    let palette: Palette = args.theme.palette();
+    let mut drawn: Vec<(u16, u16, u16, u16)> = Vec::new();
+
+    // Pinned labels go first and are always drawn, squeezed onto the screen if need be.
+    for &(index, color) in pinned.iter() {
+      let (position, label) = &flights_data[index];
+      let placed: (u16, u16, LabelPosition) = match place_label(position, label, &flights_data, &args)? {
+        Some(placed) => placed,
+        None => clamp_label(position, label, &args),
       };
+      print_label(label, placed, color)?;
+      drawn.push(label_rect(label, placed));
+    }
 
-      let (del_col, del_row) = label.compute_display_delta(label_position);
-      let (res_col, res_row) = (del_col as f64 + original_coord.col, del_row as f64 + original_coord.row);
-      if res_col < 3.0 || res_col + label.len() as f64 > -3.0 + args.terminal_cols as f64 ||
-         res_row < 3.0 || res_row + label.height() as f64 > -3.0 + args.terminal_rows as f64 {
+    for (index, (position, label)) in flights_data.iter().enumerate() {
+      if pinned.iter().any(|(pinned_index, _)| *pinned_index == index) {
         continue;
       }
-
-      let mut do_draw = true;
-      for (other_position, _) in flights_data.iter() {
-        if other_position.as_terminal_coord(&args)?.is_in_box(res_col as u16, res_row as u16, label.len() as u16, label.height() as u16) {
-          do_draw = false;
+      if let Some(placed) = place_label(position, label, &flights_data, &args)? {
+        let rect: (u16, u16, u16, u16) = label_rect(label, placed);
+        if drawn[..pinned.len()].iter().any(|other| rects_overlap(rect, *other)) {
+          continue;
         }
+        print_label(label, placed, palette.label)?;
+        drawn.push(rect);
       }
+    }
 
-      if !do_draw {
+    // Ground labels yield to traffic: they are dropped rather than drawn over aircraft or their labels.
+    for (position, label) in ground_labels.iter() {
+      let Some(placed) = place_label(position, label, &ground_labels, &args)? else {
+        continue;
+      };
+
+      let (col, row, width, height) = label_rect(label, placed);
+      let hits_aircraft: bool = flights_data.iter()
+        .any(|(other_position, _)| other_position.as_terminal_coord(&args).is_ok_and(|coord| coord.is_in_box(col, row, width, height)));
+      let hits_label: bool = drawn.iter().any(|other| rects_overlap((col, row, width, height), *other));
+      if hits_aircraft || hits_label {
         continue;
       }
 
-      let label_string: String = label.to_string(label_position);
-      label_string.split("\n").enumerate().for_each(|(ind, str)| {
-          queue!(
-            std::io::stdout(),
-            cursor::MoveTo(res_col as u16, (res_row as usize + ind) as u16),
-            style::Print(str),
-          ).unwrap()
-      });
+      print_label(label, placed, palette.ground)?;
+      drawn.push((col, row, width, height));
     }
 
     Ok(())
   })
 }
 
+/// Picks a corner for a label by pushing it away from nearby points, returning the
+/// label's top-left cell and corner, or `None` if it would not fit cleanly on screen.
+fn place_label(position: &Position, label: &Label, points: &[(Position, Label)], args: &FRadarArgs) -> anyhow::Result<Option<(u16, u16, LabelPosition)>> {
+  // Moving ("pushed") coordinate for this label
+  let pushed_coord = &mut position.as_terminal_coord_float(args);
+
+  // Initialize to the "top right"
+  pushed_coord.col += 1.0;
+  pushed_coord.row -= 1.0;
+
+  // Simulate inverse gravitational forces between labels and points
+  for (other_position, _) in points.iter() {
+    let other_coord_float: Coord<f64> = other_position.as_terminal_coord_float(args);
+    let hypot_squared: f64 = pushed_coord.squared_dist(other_coord_float);
+    if hypot_squared > 0.1 {
+      pushed_coord.col -= args.label_point_repelling_force *
+        (other_coord_float.col - pushed_coord.col) /
+        hypot_squared;
+      pushed_coord.row -= args.label_point_repelling_force *
+        (other_coord_float.row - pushed_coord.row) /
+        hypot_squared;
+    }
+  }
+
+  let original_coord: Coord<f64> = position.as_terminal_coord_float(args);
+  let label_position = match (pushed_coord.col - original_coord.col, pushed_coord.row - original_coord.row) {
+    (dc, dr) if (dc > 0.0 && dr > 0.0) => LabelPosition::BottomRight,
+    (dc, dr) if (dc < 0.0 && dr > 0.0) => LabelPosition::BottomLeft,
+    (dc, dr) if (dc > 0.0 && dr < 0.0) => LabelPosition::TopRight,
+    (dc, dr) if (dc < 0.0 && dr < 0.0) => LabelPosition::TopLeft,
+    (_, _) => LabelPosition::default(),
+  };
+
+  let (del_col, del_row) = label.compute_display_delta(label_position);
+  let (res_col, res_row) = (del_col as f64 + original_coord.col, del_row as f64 + original_coord.row);
+  if res_col < 3.0 || res_col + label.len() as f64 > -3.0 + args.terminal_cols as f64 ||
+     res_row < 3.0 || res_row + label.height() as f64 > -3.0 + args.terminal_rows as f64 {
+    return Ok(None);
+  }
+
+  for (other_position, _) in points.iter() {
+    if other_position.as_terminal_coord(args)?.is_in_box(res_col as u16, res_row as u16, label.len() as u16, label.height() as u16) {
+      return Ok(None);
+    }
+  }
+
+  Ok(Some((res_col as u16, res_row as u16, label_position)))
+}
+
+/// Top-right placement pulled inside the border, for labels that must be shown.
+fn clamp_label(position: &Position, label: &Label, args: &FRadarArgs) -> (u16, u16, LabelPosition) {
+  let coord: Coord<f64> = position.as_terminal_coord_float(args);
+  let (del_col, del_row) = label.compute_display_delta(LabelPosition::TopRight);
+  let max_col: f64 = (args.terminal_cols as f64 - label.len() as f64 - 1.0).max(1.0);
+  let max_row: f64 = (args.terminal_rows as f64 - label.height() as f64 - 1.0).max(1.0);
+  let col: f64 = (coord.col + del_col as f64).clamp(1.0, max_col);
+  let row: f64 = (coord.row + del_row as f64).clamp(1.0, max_row);
+  (col as u16, row as u16, LabelPosition::TopRight)
+}
+
+fn rects_overlap((col, row, width, height): (u16, u16, u16, u16), (other_col, other_row, other_width, other_height): (u16, u16, u16, u16)) -> bool {
+  col < other_col + other_width && other_col < col + width && row < other_row + other_height && other_row < row + height
+}
+
+fn label_rect(label: &Label, (col, row, _): (u16, u16, LabelPosition)) -> (u16, u16, u16, u16) {
+  // One extra row for the leader line.
+  (col, row, label.len() as u16, label.height() as u16 + 1)
+}
+
+fn print_label(label: &Label, (col, row, label_position): (u16, u16, LabelPosition), color: style::Color) -> anyhow::Result<()> {
+  let label_string: String = label.to_string(label_position);
+  for (ind, str) in label_string.split("\n").enumerate() {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(col, row + ind as u16),
+      style::SetForegroundColor(color),
+      style::Print(str),
+      style::ResetColor,
+    )?;
+  }
+
+  Ok(())
+}
+
+/// Returns the first row in the top right corner left free by the detail pane.
+fn draw_selection(flights_data: Arc<Mutex<FlightData>>, args: &FRadarArgs, ui: &FRadarUi, top: u16) -> anyhow::Result<u16> {
+  let flights_data: FlightData = flights_data.lock().unwrap().clone();
+
+  let Some(index) = ui.selected_hex.as_deref().and_then(|hex| flights_data.index_of_hex(hex)) else {
+    return Ok(top);
+  };
+
+  let (position, _) = &flights_data.flights[index];
+  let coord: Coord<u16> = position.as_terminal_coord(args)?;
+  let under_list: bool = list_area(args, ui.list_mode).is_some_and(|(x, y, w, h)| coord.is_in_box(x, y, w, h));
+  if !under_list {
+    queue!(
+      std::io::stdout(),
+      style::SetForegroundColor(args.theme.palette().highlight),
+      style::SetAttribute(Attribute::Bold),
+      cursor::MoveTo(coord.col.saturating_sub(1), coord.row),
+      style::Print("["),
+      cursor::MoveTo(coord.col + 1, coord.row),
+      style::Print("]"),
+      style::SetAttribute(Attribute::Reset),
+      style::ResetColor,
+    )?;
+  }
+
+  if ui.show_details {
+    let age_seconds: f64 = (Utc::now().timestamp_millis() - flights_data.epoch_timestamp) as f64 / 1000.0;
+    return draw_detail_pane(&flights_data.aircraft[index], position, args, top, age_seconds);
+  }
+
+  Ok(top)
+}
+
+fn draw_list_panel(flights_data: Arc<Mutex<FlightData>>, args: &FRadarArgs, ui: &FRadarUi) -> anyhow::Result<()> {
+  let Some((x, y, w, h)) = list_area(args, ui.list_mode) else {
+    return Ok(());
+  };
+  if w < 4 || h < 4 {
+    return Ok(());
+  }
+
+  let flights_data: FlightData = flights_data.lock().unwrap().clone();
+  let rows: Vec<ListRow> = sorted_rows(&flights_data, args, ui, Utc::now().timestamp_millis());
+  let visible: usize = visible_rows(args, ui.list_mode);
+  let scroll: usize = ui.list_scroll.min(rows.len().saturating_sub(visible));
+  let inner_width: usize = (w - 4).into();
+
+  for row in y..(y + h) {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x, row),
+      style::Print(" ".repeat(w.into())),
+    )?;
+  }
+
+  let title: String = if rows.len() > visible {
+    format!(" aircraft {}-{} of {} ", scroll + 1, (scroll + visible).min(rows.len()), rows.len())
+  } else {
+    format!(" aircraft ({}) ", rows.len())
+  };
+  draw_box_with_label(x, y, w, h, title)?;
+
+  queue!(
+    std::io::stdout(),
+    cursor::MoveTo(x + 2, y + 1),
+    style::SetAttribute(Attribute::Bold),
+    style::Print(format_header(ui).chars().take(inner_width).collect::<String>()),
+    style::SetAttribute(Attribute::Reset),
+  )?;
+
+  for (ind, row) in rows.iter().skip(scroll).take(visible).enumerate() {
+    let selected: bool = ui.selected_hex.as_deref() == Some(row.hex.as_str());
+    let line: String = format!("{:<width$}", format_row(row).chars().take(inner_width).collect::<String>(), width = inner_width);
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x + 2, y + 2 + ind as u16),
+      style::SetAttribute(if selected { Attribute::Reverse } else { Attribute::NoReverse }),
+      style::Print(line),
+      style::SetAttribute(Attribute::Reset),
+    )?;
+  }
+
+  Ok(())
+}
+
+fn draw_tooltip(flights_data: Arc<Mutex<FlightData>>, airspaces: &[Airspace], args: &FRadarArgs, ui: &FRadarUi) -> anyhow::Result<()> {
+  let Some(mouse) = ui.mouse else {
+    return Ok(());
+  };
+  if ui.drag_anchor.is_some() {
+    return Ok(());
+  }
+
+  let flights_data: FlightData = flights_data.lock().unwrap().clone();
+  let hover: Coord<f64> = Coord { col: mouse.col as f64 + 0.5, row: mouse.row as f64 + 0.5 };
+  let tooltip: String = match flights_data.nearest_to_coord(hover, 1.5, args) {
+    Some(index) => {
+      let info: &ADSBAircraftInformation = &flights_data.aircraft[index];
+      [
+        Some(info.flight.as_deref().map(str::trim).filter(|f| !f.is_empty()).unwrap_or(&info.hex).to_string()),
+        info.t.clone(),
+        info.alt_baro.as_ref().map(|alt| match alt.parse::<f64>() {
+          Ok(feet) => args.units.format_altitude(feet),
+          Err(_) => alt.clone(),
+        }),
+        info.gs.map(|gs| args.units.format_speed(gs as f64)),
+        Some(args.units.format_distance(args.origin.distance_miles(&flights_data.flights[index].0))),
+      ].into_iter().flatten().collect::<Vec<String>>().join(" ")
+    },
+    // Away from traffic, name the airspace under the cursor (floor-ceiling, sectional style).
+    None if ui.layers.airspace => {
+      let position: Position = Position::from_terminal_coord_float(hover, args);
+      airspaces.iter()
+        .filter(|airspace| airspace.class.style(&args.theme.palette()).is_some() && airspace.contains(&position))
+        .map(|airspace| format!("{} {}-{}", airspace.name, airspace.floor, airspace.ceiling))
+        .collect::<Vec<String>>()
+        .join(" | ")
+    },
+    None => String::new(),
+  };
+  if tooltip.is_empty() {
+    return Ok(());
+  }
+
+  let width: u16 = tooltip.chars().count() as u16 + 2;
+  let col: u16 = (mouse.col + 2).min(args.terminal_cols.saturating_sub(width + 1));
+  let row: u16 = if mouse.row + 2 < args.terminal_rows { mouse.row + 1 } else { mouse.row.saturating_sub(1) };
+  queue!(
+    std::io::stdout(),
+    cursor::MoveTo(col, row),
+    style::SetAttribute(Attribute::Reverse),
+    style::Print(format!(" {} ", tooltip)),
+    style::SetAttribute(Attribute::Reset),
+  )?;
+
+  Ok(())
+}
+
+/// Returns the row below the pane.
+fn draw_detail_pane(info: &ADSBAircraftInformation, position: &Position, args: &FRadarArgs, top: u16, age_seconds: f64) -> anyhow::Result<u16> {
+  let lines: Vec<(&str, String)> = detail_lines(info, position, args, age_seconds);
+
+  let w: u16 = 34;
+  let h: u16 = (lines.len() as u16 + 2).min(args.terminal_rows.saturating_sub(top + 1));
+  if args.terminal_cols < w + 4 || h < 3 {
+    return Ok(top);
+  }
+  let (x, y): (u16, u16) = (args.terminal_cols - w - 1, top);
+
+  // Blank out whatever the radar layer drew underneath the pane.
+  for row in y..(y + h) {
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x, row),
+      style::Print(" ".repeat(w.into())),
+    )?;
+  }
+
+  let title: String = info.flight.as_deref().map(str::trim).filter(|f| !f.is_empty()).unwrap_or(&info.hex).to_string();
+  draw_box_with_label(x, y, w, h, format!(" {} ", title))?;
+
+  for (ind, (key, value)) in lines.iter().take((h - 2).into()).enumerate() {
+    let line: String = format!("{:<10}{}", key, value);
+    queue!(
+      std::io::stdout(),
+      cursor::MoveTo(x + 2, y + 1 + ind as u16),
+      style::Print(line.chars().take((w - 4).into()).collect::<String>()),
+    )?;
+  }
+
+  Ok(y + h)
+}
+
+fn detail_lines(info: &ADSBAircraftInformation, position: &Position, args: &FRadarArgs, age_seconds: f64) -> Vec<(&'static str, String)> {
+  let seen: Option<f64> = info.seen.map(|seen| seen as f64 + age_seconds);
+  let units: Units = args.units;
+
+  Vec::from([
+    ("hex",       info.hex.clone()),
+    ("reg",       or_dash(info.r.as_ref())),
+    ("type",      or_dash(info.t.as_ref())),
+    ("category",  or_dash(info.category.as_ref())),
+    ("source",    or_dash(info.aircraft_type.as_ref())),
+    ("alt baro",  or_dash(info.alt_baro.as_ref().map(|alt| match alt.parse::<f64>() {
+      Ok(feet) => units.format_altitude(feet),
+      Err(_) => alt.clone(),
+    }))),
+    ("alt geom",  or_dash(info.alt_geom.map(|alt| units.format_altitude(alt as f64)))),
+    ("gs",        or_dash(info.gs.map(|gs| units.format_speed(gs as f64)))),
+    ("tas",       or_dash(info.tas.map(|tas| units.format_speed(tas as f64)))),
+    ("track",     or_dash(info.track.map(|track| format!("{:.0}°", track)))),
+    ("roll",      or_dash(info.roll.map(|roll| format!("{:.1}°", roll)))),
+    ("rate baro", or_dash(info.baro_rate.map(|rate| format!("{:+} ft/min", rate)))),
+    ("rate geom", or_dash(info.geom_rate.map(|rate| format!("{:+} ft/min", rate)))),
+    ("squawk",    or_dash(info.squawk.as_ref())),
+    ("emergency", or_dash(info.emergency.as_ref())),
+    ("nic",       or_dash(info.nic)),
+    ("nac p/v",   format!("{}/{}", or_dash(info.nac_p), or_dash(info.nac_v))),
+    ("sil",       or_dash(info.sil)),
+    ("rssi",      or_dash(info.rssi.map(|rssi| format!("{:.1} dBFS", rssi)))),
+    ("messages",  or_dash(info.messages)),
+    ("seen",      or_dash(seen.map(|seen| format!("{:.1} s ago", seen)))),
+    ("distance",  units.format_distance(args.origin.distance_miles(position))),
+    ("bearing",   format!("{:.0}°", args.origin.bearing_to(position))),
+    ("look",      or_dash(look_at(info, position, args).map(|look| {
+      format!("{:.0}° {} {:.0}° up {}", look.azimuth, compass_point(look.azimuth), look.elevation, units.format_distance(look.slant_miles))
+    }))),
+  ])
+}
+
+fn or_dash<T: Display>(value: Option<T>) -> String {
+  value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+fn subchar_coords(dots_coord_float: &[Coord<f64>]) -> Vec<Coord<usize>> {
+  dots_coord_float.iter()
+    .map(|coord_float| Coord::<usize> {
+      col: ((coord_float.col - coord_float.col.trunc()) * 2.0).trunc() as usize, // extract only decimal place, then
+      row: ((coord_float.row - coord_float.row.trunc()) * 4.0).trunc() as usize, // multiple by col or row respectively
+    })
+    .collect()
+}
+
 fn generate_subchar_braille(braille_coords: &Vec<Coord<usize>>) -> char {
   let mut braille_unicode: u32 = 0;
 
@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use regex::{Regex, RegexBuilder};
+
+use crate::{alerts::Condition, model::{ADSBAircraftInformation, FlightData}};
+
+
+/// `dbFlags` bits set by readsb-based aggregators.
+const DB_FLAGS: [(&str, u32); 4] = [("military", 1), ("interesting", 2), ("pia", 4), ("ladd", 8)];
+
+#[derive(Debug, Clone)]
+enum Rule {
+  Hex(String),
+  Registration(String),
+  Callsign(Regex),
+  Type(String),
+  Flag(u32),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+  rule: Rule,
+  /// The rule as written, for alerts and the log.
+  source: String,
+  note: Option<String>,
+}
+
+/// Airframes to look out for, one rule per line:
+///
+/// ```text
+/// hex ae1460          Air Force One
+/// reg N12345
+/// call ^(UAL|SWA)9\d\d$
+/// type B52
+/// flag military
+/// ```
+///
+/// Anything after the value is a note shown with the alert. `#` starts a comment line.
+#[derive(Debug, Clone, Default)]
+pub struct Watchlist {
+  entries: Vec<Entry>,
+}
+
+impl Watchlist {
+  pub fn load(path: &Path) -> anyhow::Result<Watchlist> {
+    let contents: String = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Watchlist::parse(&contents).with_context(|| format!("failed to parse {}", path.display()))
+  }
+
+  pub fn parse(contents: &str) -> anyhow::Result<Watchlist> {
+    let mut entries: Vec<Entry> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+      let line: &str = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let Some((kind, rest)) = line.split_once(char::is_whitespace) else {
+        bail!("line {}: expected a kind and a value", index + 1);
+      };
+      let (value, note): (&str, &str) = rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest.trim_start(), ""));
+      let note: Option<String> = Some(note.trim().to_string()).filter(|note| !note.is_empty());
+
+      let rule: Rule = match kind.to_lowercase().as_str() {
+        "hex" => Rule::Hex(value.trim_start_matches('~').to_lowercase()),
+        "reg" | "registration" => Rule::Registration(normalize_registration(value)),
+        "call" | "callsign" => Rule::Callsign(
+          RegexBuilder::new(value).case_insensitive(true).build().map_err(|error| anyhow!("line {}: {}", index + 1, error))?,
+        ),
+        "type" => Rule::Type(value.to_uppercase()),
+        "flag" => match DB_FLAGS.iter().find(|(name, _)| name.eq_ignore_ascii_case(value)) {
+          Some((_, bit)) => Rule::Flag(*bit),
+          None => bail!("line {}: unknown flag '{}' (expected military, interesting, pia or ladd)", index + 1, value),
+        },
+        _ => bail!("line {}: unknown kind '{}' (expected hex, reg, call, type or flag)", index + 1, kind),
+      };
+
+      entries.push(Entry { rule, source: format!("{} {}", kind.to_lowercase(), value), note });
+    }
+
+    Ok(Watchlist { entries })
+  }
+
+  /// Why the aircraft is on the watchlist, if it is.
+  pub fn reason(&self, info: &ADSBAircraftInformation) -> Option<String> {
+    let entry: &Entry = self.entries.iter().find(|entry| entry.matches(info))?;
+    Some(match &entry.note {
+      Some(note) => format!("watchlist: {} ({})", entry.source, note),
+      None => format!("watchlist: {}", entry.source),
+    })
+  }
+}
+
+impl Entry {
+  fn matches(&self, info: &ADSBAircraftInformation) -> bool {
+    match &self.rule {
+      Rule::Hex(hex) => info.hex.trim_start_matches('~').eq_ignore_ascii_case(hex),
+      Rule::Registration(registration) => info.r.as_deref().is_some_and(|r| normalize_registration(r) == *registration),
+      Rule::Callsign(pattern) => info.flight.as_deref().is_some_and(|flight| pattern.is_match(flight.trim())),
+      Rule::Type(aircraft_type) => info.t.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(aircraft_type)),
+      Rule::Flag(bit) => info.dbFlags.is_some_and(|flags| flags & bit != 0),
+    }
+  }
+}
+
+/// Registrations are compared without dashes or case, so `G-EUPT` matches `geupt`.
+fn normalize_registration(registration: &str) -> String {
+  registration.chars().filter(|c| *c != '-').collect::<String>().to_uppercase()
+}
+
+/// Watched aircraft currently in the data.
+pub fn watchlist_conditions(watchlist: &Watchlist, flights_data: &FlightData) -> Vec<Condition> {
+  flights_data.aircraft.iter().zip(flights_data.flights.iter())
+    .filter_map(|(info, (position, _))| watchlist.reason(info).map(|reason| Condition::new(info, *position, reason)))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fixtures::{aircraft, flights_data}, model::Position};
+
+  const WATCHLIST: &str = "
+# Aircraft of interest
+hex ~AE1460        Air Force One
+reg g-eupt
+call ^(UAL|SWA)9\\d\\d$
+TYPE b52
+flag Military      anything grey
+";
+
+  fn reason(watchlist: &Watchlist, info: ADSBAircraftInformation) -> Option<String> {
+    watchlist.reason(&info)
+  }
+
+  #[test]
+  fn parses_rules_notes_and_comments() {
+    let watchlist: Watchlist = Watchlist::parse(WATCHLIST).unwrap();
+    assert_eq!(watchlist.entries.len(), 5);
+    let sources: Vec<&str> = watchlist.entries.iter().map(|entry| entry.source.as_str()).collect();
+    assert_eq!(sources, ["hex ~AE1460", "reg g-eupt", "call ^(UAL|SWA)9\\d\\d$", "type b52", "flag Military"]);
+    assert_eq!(watchlist.entries[0].note.as_deref(), Some("Air Force One"));
+    assert_eq!(watchlist.entries[1].note, None);
+
+    let error = |contents: &str| Watchlist::parse(contents).unwrap_err().to_string();
+    assert_eq!(error("\nhex"), "line 2: expected a kind and a value");
+    assert_eq!(error("tail N123"), "line 1: unknown kind 'tail' (expected hex, reg, call, type or flag)");
+    assert_eq!(error("flag secret"), "line 1: unknown flag 'secret' (expected military, interesting, pia or ladd)");
+    assert!(error("call (UAL").starts_with("line 1: "));
+  }
+
+  #[test]
+  fn matches_each_kind_of_rule() {
+    let watchlist: Watchlist = Watchlist::parse(WATCHLIST).unwrap();
+    let plain = |hex: &str| aircraft(hex, &Position::default(), 30000);
+
+    assert_eq!(reason(&watchlist, plain("ae1460")).as_deref(), Some("watchlist: hex ~AE1460 (Air Force One)"));
+    assert_eq!(reason(&watchlist, plain("~AE1460")).as_deref(), Some("watchlist: hex ~AE1460 (Air Force One)"), "non-ICAO addresses match too");
+    assert_eq!(reason(&watchlist, plain("ae1461")), None);
+
+    let registered = ADSBAircraftInformation { r: Some("GEUPT".to_string()), ..plain("400000") };
+    assert_eq!(reason(&watchlist, registered).as_deref(), Some("watchlist: reg g-eupt"));
+
+    let callsign = |flight: &str| ADSBAircraftInformation { flight: Some(flight.to_string()), ..plain("a00000") };
+    assert!(reason(&watchlist, callsign("swa912  ")).is_some());
+    assert_eq!(reason(&watchlist, callsign("SWA1912")), None);
+
+    assert!(reason(&watchlist, ADSBAircraftInformation { t: Some("B52".to_string()), ..plain("a00001") }).is_some());
+    assert_eq!(reason(&watchlist, ADSBAircraftInformation { t: Some("B52H".to_string()), ..plain("a00002") }), None);
+
+    let flagged = |flags: u32| ADSBAircraftInformation { dbFlags: Some(flags), ..plain("a00003") };
+    assert_eq!(reason(&watchlist, flagged(1)).as_deref(), Some("watchlist: flag Military (anything grey)"));
+    assert_eq!(reason(&watchlist, flagged(3)).as_deref(), Some("watchlist: flag Military (anything grey)"));
+    assert_eq!(reason(&watchlist, flagged(2 | 4 | 8)), None);
+  }
+
+  #[test]
+  fn first_matching_rule_gives_the_reason() {
+    let watchlist: Watchlist = Watchlist::parse(WATCHLIST).unwrap();
+    let both: ADSBAircraftInformation = ADSBAircraftInformation { dbFlags: Some(1), ..aircraft("ae1460", &Position { lat: 38.9, long: -77.0 }, 30000) };
+    let data: FlightData = flights_data(0, vec![both, aircraft("a00000", &Position::default(), 30000)]);
+
+    let conditions: Vec<Condition> = watchlist_conditions(&watchlist, &data);
+    assert_eq!(conditions.len(), 1);
+    assert_eq!((conditions[0].hex.as_str(), conditions[0].reason.as_str()), ("ae1460", "watchlist: hex ~AE1460 (Air Force One)"));
+    assert_eq!(conditions[0].position, Position { lat: 38.9, long: -77.0 });
+  }
+}